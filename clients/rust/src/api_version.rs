@@ -0,0 +1,22 @@
+//! API version negotiation through the `Accept` header
+
+use reqwest::header::HeaderValue;
+use reqwest::StatusCode;
+
+use crate::{LicenseClient, LicenseError, Result};
+
+impl LicenseClient {
+    /// The `Accept` header requesting the configured API version
+    pub(crate) fn accept_header(&self) -> Option<HeaderValue> {
+        let version = self.api_version?;
+        HeaderValue::from_str(&format!("application/vnd.permetix.v{}+json", version)).ok()
+    }
+    
+    /// Turn a `406` for the requested API version into `UnsupportedApiVersion`
+    pub(crate) fn check_api_version(&self, status: StatusCode) -> Result<()> {
+        match self.api_version {
+            Some(version) if status == StatusCode::NOT_ACCEPTABLE => Err(LicenseError::UnsupportedApiVersion(version)),
+            _ => Ok(()),
+        }
+    }
+}
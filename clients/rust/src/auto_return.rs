@@ -0,0 +1,213 @@
+//! Best-effort return of licenses whose handles are dropped unreturned
+
+use std::mem;
+use std::ops::Deref;
+use std::sync::Mutex;
+
+use tokio::task::JoinHandle;
+
+use crate::{LicenseClient, LicenseHandle, Result, ReturnOptions};
+
+/// Returns (and cancellations) spawned for dropped handles that may still be running
+#[derive(Debug, Default)]
+pub(crate) struct PendingReturns(Mutex<Vec<JoinHandle<()>>>);
+
+impl PendingReturns {
+    pub(crate) fn push(&self, task: JoinHandle<()>) {
+        let mut tasks = self.0.lock().unwrap();
+        tasks.retain(|task| !task.is_finished());
+        tasks.push(task);
+    }
+}
+
+impl LicenseClient {
+    /// The runtime to return a new handle on if it is dropped, if enabled
+    pub(crate) fn drop_runtime(&self) -> Option<tokio::runtime::Handle> {
+        if !self.auto_return {
+            return None;
+        }
+        tokio::runtime::Handle::try_current().ok()
+    }
+    
+    /// Wait for the returns of dropped handles to complete
+    ///
+    /// Returns of handles dropped by this client or its clones run on
+    /// detached tasks, which a program exiting right after would cut off.
+    /// Call this before shutting down to flush them; failures have already
+    /// been logged by then. Returns spawned while draining are awaited too.
+    pub async fn drain(&self) {
+        loop {
+            let tasks = mem::take(&mut *self.pending_returns.0.lock().unwrap());
+            if tasks.is_empty() {
+                return;
+            }
+            for task in tasks {
+                let _ = task.await;
+            }
+        }
+    }
+}
+
+impl LicenseHandle {
+    /// Return a handle that is being dropped from a detached task
+    ///
+    /// Returns `false` if there is no runtime to spawn on, leaving the
+    /// license unreturned. The task is not awaited, so the return is lost
+    /// if the runtime shuts down first.
+    pub(crate) fn spawn_return(&mut self) -> bool {
+        let runtime = match self.runtime.take() {
+            Some(runtime) => runtime,
+            None => return false,
+        };
+        
+        // The detached handle keeps the host lock, permit and managed-mode
+        // registration until the server has taken the license back
+        let handle = LicenseHandle {
+            id: mem::take(&mut self.id),
+            tool: mem::take(&mut self.tool),
+            user: mem::take(&mut self.user),
+            idempotency_key: mem::take(&mut self.idempotency_key),
+            borrowed_at: self.borrowed_at.take(),
+            expires_at: self.expires_at.take(),
+            acquired: self.acquired,
+            client: self.client.clone(),
+            returned: false,
+            expiry_warning: None,
+            renewer: None,
+            operation: self.operation.take(),
+            grant: self.grant.take(),
+            host_lock: self.host_lock.take(),
+            host_permit: self.host_permit.take(),
+            registration: self.registration.take(),
+            offline_grant: self.offline_grant.take(),
+            runtime: None,
+            #[cfg(feature = "ws")]
+            ws: self.ws.take(),
+        };
+        let pending_returns = self.client.pending_returns.clone();
+        pending_returns.push(runtime.spawn(handle.return_on_drop()));
+        true
+    }
+    
+    /// Turn the handle into a guard that always returns it when dropped
+    ///
+    /// Unlike a plain handle, the guard returns its license on drop even if
+    /// the client has [`auto_return_on_drop`](crate::LicenseClientBuilder::auto_return_on_drop)
+    /// disabled, including while unwinding from a panic. Use
+    /// [`LicenseClient::drain`] at shutdown to wait for those returns.
+    /// Must be called within a tokio runtime for that; outside one, the
+    /// guard only warns like an unreturned handle.
+    pub fn into_guard(mut self) -> LicenseGuard {
+        if self.runtime.is_none() {
+            self.runtime = tokio::runtime::Handle::try_current().ok();
+        }
+        LicenseGuard { handle: Some(self) }
+    }
+    
+    async fn return_on_drop(mut self) {
+        let result = self.return_impl(&ReturnOptions::default()).await;
+        // Failed returns stay in the journal, if any; don't try again on drop
+        self.returned = true;
+        if let Err(e) = result {
+            eprintln!("Warning: failed to return dropped license {}: {}", self.id, e);
+        }
+    }
+}
+
+/// A license that is returned whenever the guard goes out of scope
+///
+/// Created by [`LicenseHandle::into_guard`]; dereferences to the handle.
+#[derive(Debug)]
+pub struct LicenseGuard {
+    // Only taken by `return_license`
+    handle: Option<LicenseHandle>,
+}
+
+impl LicenseGuard {
+    /// Return the license now, reporting any failure
+    pub async fn return_license(mut self) -> Result<()> {
+        self.handle.take().expect("guard holds a handle until returned").return_license().await
+    }
+}
+
+impl Deref for LicenseGuard {
+    type Target = LicenseHandle;
+    
+    fn deref(&self) -> &LicenseHandle {
+        self.handle.as_ref().expect("guard holds a handle until returned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::LicenseClient;
+    use std::time::Duration;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_dropped_handle_is_returned() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .and(body_json(serde_json::json!({ "id": "abc-123" })))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        drop(client.borrow("cad_tool", "alice").await.unwrap());
+        client.drain().await;
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .auto_return_on_drop(false)
+            .build();
+        drop(client.borrow("cad_tool", "alice").await.unwrap());
+        client.drain().await;
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_drain_awaits_returns_of_dropped_guards() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(100)))
+            .expect(3)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .auto_return_on_drop(false)
+            .build();
+        let mut guards = Vec::new();
+        for _ in 0..3 {
+            guards.push(client.borrow("cad_tool", "alice").await.unwrap().into_guard());
+        }
+        assert_eq!(guards[0].id(), "abc-123");
+        drop(guards);
+        
+        client.drain().await;
+        let returns = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|request| request.url.path() == "/licenses/return")
+            .count();
+        assert_eq!(returns, 3);
+    }
+}
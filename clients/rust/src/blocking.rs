@@ -0,0 +1,253 @@
+//! Synchronous client for programs without an async runtime
+//!
+//! Enabled by the `blocking` feature. Mirrors the core of the async API
+//! using `reqwest::blocking`; like it, these calls must not be made from
+//! within an async runtime.
+//!
+//! ```no_run
+//! use license_client::blocking::LicenseClient;
+//!
+//! fn main() -> license_client::Result<()> {
+//!     let client = LicenseClient::new("http://localhost:8000");
+//!     let license = client.borrow("cad_tool", "rust-user")?;
+//!     println!("Got license: {}", license.id());
+//!     license.return_license()
+//! }
+//! ```
+
+use std::io::Read;
+
+use crate::{
+    parse_json, Buffered, LicenseError, LicenseStatus, PendingBorrow, Result, ReturnOptions, DEFAULT_TIMEOUT,
+};
+
+/// Blocking license client
+///
+/// Settings (security, API key from `LICENSE_API_KEY`, response size limit)
+/// match the async [`LicenseClient`](crate::LicenseClient) defaults.
+#[derive(Debug, Clone)]
+pub struct LicenseClient {
+    // Holds the configuration and signing; never used to send
+    inner: crate::LicenseClient,
+    http: reqwest::blocking::Client,
+}
+
+impl LicenseClient {
+    /// Create a new blocking client with security enabled by default
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - Base URL of the license server (e.g., "http://localhost:8000")
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_security(base_url, true)
+    }
+    
+    /// Create a new blocking client with configurable security
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - Base URL of the license server
+    /// * `enable_security` - Whether to enable HMAC signature authentication
+    pub fn with_security(base_url: impl Into<String>, enable_security: bool) -> Self {
+        let http = reqwest::blocking::Client::builder()
+            .timeout(DEFAULT_TIMEOUT)
+            .build()
+            .expect("failed to build HTTP client");
+        Self {
+            inner: crate::LicenseClient::with_security(base_url, enable_security),
+            http,
+        }
+    }
+    
+    /// Send `request` and read the body to EOF, within the size limit
+    ///
+    /// Like the async client, the limit doesn't rely on `Content-Length`,
+    /// which HTTP/1.0 servers and some proxies leave out.
+    fn send(&self, request: reqwest::blocking::RequestBuilder) -> Result<Buffered> {
+        let limit = self.inner.max_response_bytes;
+        let request = match self.inner.accept_header() {
+            Some(accept) => request.header(reqwest::header::ACCEPT, accept),
+            None => request,
+        };
+        let response = request.send()?;
+        self.inner.check_api_version(response.status())?;
+        let status = response.status();
+        let date = crate::clock::response_date(response.headers());
+        if let Some(length) = response.content_length() {
+            if length > limit as u64 {
+                return Err(LicenseError::ResponseTooLarge(limit));
+            }
+        }
+        
+        // One byte past the limit tells an oversized body from one that fits
+        let mut body = Vec::new();
+        response
+            .take(limit as u64 + 1)
+            .read_to_end(&mut body)
+            .map_err(|e| LicenseError::IncompleteResponse(e.to_string()))?;
+        if body.len() > limit {
+            return Err(LicenseError::ResponseTooLarge(limit));
+        }
+        Ok(Buffered { status, body, date })
+    }
+    
+    /// Borrow a license for a specific tool
+    ///
+    /// # Arguments
+    ///
+    /// * `tool` - Tool name (e.g., "cad_tool")
+    /// * `user` - Username
+    ///
+    /// # Errors
+    ///
+    /// Same as the async [`borrow`](crate::LicenseClient::borrow).
+    pub fn borrow(&self, tool: impl Into<String>, user: impl Into<String>) -> Result<LicenseHandle> {
+        let pending = PendingBorrow::new(tool, user, crate::LicenseClient::generate_idempotency_key());
+        pending.validate()?;
+        
+        let tool = self.inner.qualified_tool(&pending.tool).into_owned();
+        let mut request = self
+            .http
+            .post(self.inner.endpoints().borrow)
+            .header("Idempotency-Key", &pending.idempotency_key)
+            .json(&serde_json::json!({ "tool": tool, "user": pending.user }));
+        let timestamp = self.inner.timestamp()?;
+        for (name, value) in self.inner.security_headers(&tool, &pending.user, timestamp)?.headers {
+            request = request.header(name, value);
+        }
+        
+        let response = self.send(request)?;
+        // Shares the async client's response mapping; the async handle is
+        // only a carrier for the license fields
+        let mut handle = self.inner.handle_from_borrow_body(response.status, &response.body, pending)?;
+        handle.returned = true;
+        
+        Ok(LicenseHandle {
+            id: handle.id.clone(),
+            tool: handle.tool.clone(),
+            user: handle.user.clone(),
+            client: self.clone(),
+            returned: false,
+        })
+    }
+    
+    /// Add the bearer token to a request that isn't signed, in bearer mode
+    fn authorized(&self, request: reqwest::blocking::RequestBuilder) -> reqwest::blocking::RequestBuilder {
+        match &self.inner.auth {
+            crate::AuthMode::Bearer(token) => request.bearer_auth(token),
+            _ => request,
+        }
+    }
+    
+    fn send_return(&self, id: &str) -> Result<()> {
+        let mut request = self
+            .http
+            .post(self.inner.endpoints().return_license)
+            .json(&ReturnOptions::default().body(id));
+        for (name, value) in self.inner.return_headers(id)?.headers {
+            request = request.header(name, value);
+        }
+        self.send(request)?.error_for_status()?;
+        Ok(())
+    }
+    
+    /// Get status for a specific tool
+    ///
+    /// # Arguments
+    ///
+    /// * `tool` - Tool name
+    pub fn get_status(&self, tool: impl Into<String>) -> Result<LicenseStatus> {
+        let url = self.inner.endpoints().status(&tool.into());
+        let response = self.send(self.authorized(self.http.get(url)))?.error_for_status()?;
+        parse_json(&response.body)
+    }
+    
+    /// Get status for all tools
+    pub fn get_all_statuses(&self) -> Result<Vec<LicenseStatus>> {
+        let url = self.inner.endpoints().all_statuses;
+        let response = self.send(self.authorized(self.http.get(url)))?.error_for_status()?;
+        parse_json(&response.body)
+    }
+}
+
+/// Blocking license handle, returned when dropped
+#[derive(Debug)]
+pub struct LicenseHandle {
+    id: String,
+    tool: String,
+    user: String,
+    client: LicenseClient,
+    returned: bool,
+}
+
+impl LicenseHandle {
+    /// Get the license ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    
+    /// Get the tool name
+    pub fn tool(&self) -> &str {
+        &self.tool
+    }
+    
+    /// Get the username
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+    
+    /// Explicitly return the license, reporting any failure
+    ///
+    /// Dropping the handle also returns it, but can only log failures.
+    pub fn return_license(mut self) -> Result<()> {
+        self.returned = true;
+        self.client.send_return(&self.id)
+    }
+}
+
+impl Drop for LicenseHandle {
+    fn drop(&mut self) {
+        if !self.returned {
+            if let Err(e) = self.client.send_return(&self.id) {
+                eprintln!("Warning: failed to return license {} on drop: {}", self.id, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_blocking_borrow_returns_on_drop() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .and(body_json(serde_json::json!({ "id": "abc-123" })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let uri = server.uri();
+        // Blocking calls have to run off the async runtime
+        tokio::task::spawn_blocking(move || {
+            let client = LicenseClient::with_security(uri, false);
+            let license = client.borrow("cad_tool", "alice").unwrap();
+            assert_eq!(license.id(), "abc-123");
+            drop(license);
+            
+            assert!(matches!(client.borrow("", "alice"), Err(LicenseError::InvalidInput(_))));
+        })
+        .await
+        .unwrap();
+    }
+}
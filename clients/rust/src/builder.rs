@@ -0,0 +1,599 @@
+//! Builder for configuring a [`LicenseClient`]
+
+use std::sync::atomic::AtomicBool;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::header::HeaderMap;
+
+use crate::clock::{Clock, SharedClock, SkewedClock};
+use crate::hedge::Hedger;
+use crate::host_semaphore::HostSemaphore;
+use crate::idempotency::SharedStore;
+use crate::managed::{ManagedHandles, Watchdog};
+use crate::retry::Retrier;
+use crate::signing::SharedSigner;
+use crate::{
+    ApiPaths, AuthMode, HedgePolicy, HeldLicense, IdempotencyStore, LicenseClient, RetryPolicy, Signer, DEFAULT_MAX_RESPONSE_BYTES,
+    DEFAULT_POLL_INTERVAL, DEFAULT_TIMEOUT, VENDOR_ID, VENDOR_SECRET,
+};
+
+/// Builder for [`LicenseClient`]
+///
+/// Obtained from [`LicenseClient::builder`]. Settings that are not touched
+/// keep the same defaults as [`LicenseClient::new`].
+#[derive(Clone)]
+pub struct LicenseClientBuilder {
+    base_url: String,
+    enable_security: bool,
+    api_key: Option<String>,
+    vendor_id: String,
+    vendor_secret: String,
+    bearer_token: Option<String>,
+    signer: Option<SharedSigner>,
+    clock: Option<SharedClock>,
+    clock_offset: chrono::Duration,
+    correct_clock_skew: bool,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    pool_max_idle_per_host: usize,
+    pool_idle_timeout: Option<Duration>,
+    tcp_keepalive: Option<Duration>,
+    default_headers: HeaderMap,
+    http_client: Option<reqwest::Client>,
+    tool_prefix: Option<String>,
+    api_paths: ApiPaths,
+    content_type: Option<String>,
+    api_version: Option<u32>,
+    host_lock_dir: Option<PathBuf>,
+    host_semaphore: Option<HostSemaphore>,
+    return_journal: Option<PathBuf>,
+    offline_grants: Option<PathBuf>,
+    security_downgrade: bool,
+    max_response_bytes: usize,
+    poll_interval: Duration,
+    fast_fail: Option<Duration>,
+    status_cache_ttl: Duration,
+    #[cfg(feature = "tracing")]
+    redact_user: bool,
+    managed: bool,
+    auto_return_on_drop: bool,
+    watchdog: Option<Watchdog>,
+    hedge: Option<HedgePolicy>,
+    retry: Option<RetryPolicy>,
+    idempotency_store: SharedStore,
+}
+
+// Secrets are redacted so builders can be logged safely, like the client
+impl std::fmt::Debug for LicenseClientBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LicenseClientBuilder")
+            .field("base_url", &self.base_url)
+            .field("enable_security", &self.enable_security)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .field("vendor_id", &self.vendor_id)
+            .field("vendor_secret", &"<redacted>")
+            .field("bearer_token", &self.bearer_token.as_ref().map(|_| "<redacted>"))
+            .field("tool_prefix", &self.tool_prefix)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LicenseClientBuilder {
+    pub(crate) fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            enable_security: true,
+            api_key: std::env::var("LICENSE_API_KEY").ok(),
+            vendor_id: VENDOR_ID.to_string(),
+            vendor_secret: VENDOR_SECRET.to_string(),
+            bearer_token: None,
+            signer: None,
+            clock: None,
+            clock_offset: chrono::Duration::zero(),
+            correct_clock_skew: false,
+            timeout: Some(DEFAULT_TIMEOUT),
+            connect_timeout: None,
+            // reqwest's defaults
+            pool_max_idle_per_host: usize::MAX,
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            tcp_keepalive: None,
+            default_headers: HeaderMap::new(),
+            http_client: None,
+            tool_prefix: None,
+            api_paths: ApiPaths::default(),
+            content_type: None,
+            api_version: None,
+            host_lock_dir: None,
+            host_semaphore: None,
+            return_journal: None,
+            offline_grants: None,
+            security_downgrade: false,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            fast_fail: None,
+            status_cache_ttl: Duration::ZERO,
+            #[cfg(feature = "tracing")]
+            redact_user: false,
+            managed: false,
+            auto_return_on_drop: true,
+            watchdog: None,
+            hedge: None,
+            retry: None,
+            idempotency_store: SharedStore::default(),
+        }
+    }
+    
+    /// Enable or disable HMAC signature authentication (enabled by default)
+    pub fn enable_security(mut self, enable_security: bool) -> Self {
+        self.enable_security = enable_security;
+        self
+    }
+    
+    /// Set the API key (defaults to the `LICENSE_API_KEY` environment variable)
+    pub fn api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+    
+    /// Sign requests with these vendor credentials
+    ///
+    /// `vendor_id` is sent as `X-Vendor-ID` and `vendor_secret` keys the
+    /// HMAC signature. Defaults to the demo credentials embedded in the
+    /// library.
+    pub fn credentials(mut self, vendor_id: impl Into<String>, vendor_secret: impl Into<String>) -> Self {
+        self.vendor_id = vendor_id.into();
+        self.vendor_secret = vendor_secret.into();
+        self.bearer_token = None;
+        self
+    }
+    
+    /// Authenticate requests with `mode` (HMAC with the demo credentials by default)
+    ///
+    /// `AuthMode::Hmac` is the same as [`credentials`](Self::credentials)
+    /// with security enabled, and `AuthMode::None` the same as disabling
+    /// security. `AuthMode::Bearer` sends a static `Authorization: Bearer`
+    /// token with every request instead of signing it.
+    pub fn auth(mut self, mode: AuthMode) -> Self {
+        match mode {
+            AuthMode::None => self.enable_security = false,
+            AuthMode::Hmac { vendor_id, secret } => {
+                self = self.credentials(vendor_id, secret);
+                self.enable_security = true;
+            }
+            AuthMode::Bearer(token) => {
+                self.bearer_token = Some(token);
+                self.enable_security = true;
+            }
+        }
+        self
+    }
+    
+    /// Produce the authentication headers of signed requests with `signer`
+    ///
+    /// Replaces the built-in HMAC-SHA256 headers (`X-Signature`,
+    /// `X-Timestamp`, `X-Vendor-ID` and the bearer API key) entirely, for
+    /// servers with a different scheme. Only used in HMAC
+    /// [auth mode](Self::auth).
+    pub fn signer(mut self, signer: Signer) -> Self {
+        self.signer = Some(SharedSigner(signer));
+        self
+    }
+    
+    /// Take signature timestamps from `clock` instead of the system clock
+    ///
+    /// Mostly useful to test skew handling with a fake clock.
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.clock = Some(SharedClock(clock));
+        self
+    }
+    
+    /// Add `offset` to the clock when timestamping signatures (zero by default)
+    ///
+    /// For machines whose clock is known to drift from the server's; the
+    /// server rejects signatures whose timestamp is too far off.
+    pub fn clock_offset(mut self, offset: chrono::Duration) -> Self {
+        self.clock_offset = offset;
+        self
+    }
+    
+    /// Correct the clock offset from the server's time (disabled by default)
+    ///
+    /// When a signed request (a borrow, return, renewal, reservation, ...)
+    /// is rejected with 401 or 403 and the server's time is more than 30s
+    /// off, the offset is set to the difference and the request is retried
+    /// once. The server's time is taken from the error body's `server_time`,
+    /// or else the response's `Date` header. The new offset applies to every
+    /// later request of the client and its clones. Borrows prepared with a
+    /// fixed timestamp are not retried.
+    pub fn correct_clock_skew(mut self, enabled: bool) -> Self {
+        self.correct_clock_skew = enabled;
+        self
+    }
+    
+    /// Fail requests that take longer than `timeout` in total (30s by default)
+    ///
+    /// Covers connecting, sending and reading the whole response. Timed-out
+    /// requests fail with `RequestFailed` and count as retryable.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+    
+    /// Let requests take as long as the server needs
+    pub fn no_timeout(mut self) -> Self {
+        self.timeout = None;
+        self
+    }
+    
+    /// Fail requests that can't connect within `timeout`
+    ///
+    /// By default only the overall [`timeout`](Self::timeout) applies.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+    
+    /// Keep at most `max` idle connections per host (unlimited by default)
+    ///
+    /// Under heavy concurrency, raise the idle timeout and keep-alive
+    /// rather than limiting this, so connections are reused instead of
+    /// churned:
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use license_client::LicenseClient;
+    ///
+    /// let client = LicenseClient::builder("http://localhost:8000")
+    ///     .pool_max_idle_per_host(256)
+    ///     .pool_idle_timeout(Duration::from_secs(300))
+    ///     .tcp_keepalive(Duration::from_secs(30))
+    ///     .connect_timeout(Duration::from_secs(2))
+    ///     .build();
+    /// ```
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = max;
+        self
+    }
+    
+    /// Close pooled connections idle for longer than `timeout` (90s by default)
+    ///
+    /// `None` keeps idle connections open indefinitely.
+    pub fn pool_idle_timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.pool_idle_timeout = timeout.into();
+        self
+    }
+    
+    /// Send TCP keep-alive probes every `interval` (disabled by default)
+    pub fn tcp_keepalive(mut self, interval: impl Into<Option<Duration>>) -> Self {
+        self.tcp_keepalive = interval.into();
+        self
+    }
+    
+    /// Send `headers` with every request, e.g. for a proxy or tracing
+    ///
+    /// Headers set by the client itself (security, idempotency, content
+    /// type) take precedence.
+    pub fn default_headers(mut self, headers: HeaderMap) -> Self {
+        self.default_headers = headers;
+        self
+    }
+    
+    /// Send requests through `client` instead of one built for this client
+    ///
+    /// Lets many license clients share one connection pool, proxy setup and
+    /// user agent. The [`timeout`](Self::timeout),
+    /// [`connect_timeout`](Self::connect_timeout), connection pool and
+    /// [`default_headers`](Self::default_headers) settings are ignored; set
+    /// them on `client` instead.
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+    
+    /// Prefix every tool name sent to the server with `prefix`
+    ///
+    /// Lets call sites keep using bare names (`cad_tool`) against a server
+    /// that namespaces tools (`team-a/cad_tool`). The prefix is applied to
+    /// borrow requests, their signatures and status URLs (URL-encoded);
+    /// `LicenseHandle::tool()` still reports the bare name, while
+    /// `LicenseStatus::tool` is reported exactly as the server sends it.
+    pub fn tool_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.tool_prefix = Some(prefix.into());
+        self
+    }
+    
+    /// Send requests to these routes instead of the standard ones
+    ///
+    /// For servers mounted under a sub-path or behind a proxy that rewrites
+    /// routes; see [`ApiPaths`].
+    pub fn api_paths(mut self, paths: ApiPaths) -> Self {
+        self.api_paths = paths;
+        self
+    }
+    
+    /// Mount every route below `prefix`, e.g. `/api/v1`
+    ///
+    /// Shorthand for setting [`ApiPaths::prefix`], keeping other overrides.
+    pub fn api_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.api_paths.prefix = prefix.into();
+        self
+    }
+    
+    /// Retry borrows unsigned if the server lacks security (disabled by default)
+    ///
+    /// A borrow is retried once without security headers, and only when the
+    /// server answers with the `SECURITY_NOT_SUPPORTED` error code; a warning
+    /// is logged each time. Meant for rolling upgrades where older servers
+    /// are still in rotation: with this enabled, anything able to fake that
+    /// response can make the client skip signing, so turn it off again once
+    /// all servers support security.
+    pub fn allow_security_downgrade(mut self, allow: bool) -> Self {
+        self.security_downgrade = allow;
+        self
+    }
+    
+    /// Send request bodies with a custom `Content-Type`
+    ///
+    /// Bodies are still serialized as JSON; only the header changes, e.g. to
+    /// `application/vnd.license.v2+json` for gateways that route on it.
+    /// Defaults to `application/json`.
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+    
+    /// Ask the server for a specific API version (unset by default)
+    ///
+    /// Every request then carries `Accept: application/vnd.permetix.v{version}+json`.
+    /// A server that can't serve that version answers `406`, reported as
+    /// `UnsupportedApiVersion`. Status responses are understood in both the
+    /// v1 and v2 shapes either way.
+    pub fn api_version(mut self, version: u32) -> Self {
+        self.api_version = Some(version);
+        self
+    }
+    
+    /// Coordinate borrows with other processes on this host through lock files
+    ///
+    /// Before borrowing, the client takes an exclusive advisory lock on
+    /// `<dir>/<tool>.lock` and holds it until the license is returned, so a
+    /// host holds at most one seat per tool: concurrent borrows from other
+    /// processes (or clients) using the same directory wait their turn
+    /// instead of taking another seat. The lock is local only and released
+    /// by the OS if the process dies.
+    pub fn host_lock_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.host_lock_dir = Some(dir.into());
+        self
+    }
+    
+    /// Cap concurrent borrows across all processes on this host at `permits`
+    ///
+    /// Every client configured with the same `dir` shares the limit: a
+    /// borrow first takes one of `permits` lock files in `dir` (polling
+    /// every `poll_interval` while all are taken) and holds it until the
+    /// license is returned. Permits of a process that dies are released by
+    /// the OS.
+    pub fn host_semaphore(mut self, dir: impl Into<PathBuf>, permits: usize) -> Self {
+        self.host_semaphore = Some(HostSemaphore::new(dir.into(), permits));
+        self
+    }
+    
+    /// Journal returns in `dir` so they survive abrupt termination
+    ///
+    /// Each return is written to the journal before it is sent and removed
+    /// once the server has answered it. Call
+    /// [`LicenseClient::replay_pending_returns`] at startup to send any
+    /// returns a previous process didn't finish.
+    pub fn return_journal(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.return_journal = Some(dir.into());
+        self
+    }
+    
+    /// Cache offline grants in `dir` and use them while the server is down
+    ///
+    /// When a borrow response carries an `offline_grant` object (a signed
+    /// `token` and its `expires_at`), it is stored per tool and user. If a
+    /// later borrow can't connect to the server, a handle backed by that
+    /// grant is returned instead, as long as it hasn't expired; see
+    /// [`LicenseHandle::offline_grant`](crate::LicenseHandle::offline_grant).
+    /// Each grant backs at most one such handle, and returning it doesn't
+    /// contact the server.
+    ///
+    /// Grant signatures are not verified by the client, so any grant file in
+    /// `dir` is used; keep the directory writable by its user only.
+    pub fn offline_grants(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.offline_grants = Some(dir.into());
+        self
+    }
+    
+    /// Limit how many bytes of a response body the client will buffer
+    ///
+    /// Responses larger than this fail with `LicenseError::ResponseTooLarge`
+    /// instead of being deserialized.
+    pub fn max_response_bytes(mut self, limit: usize) -> Self {
+        self.max_response_bytes = limit;
+        self
+    }
+    
+    /// Set the delay between attempts of the waiting borrow methods
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+    
+    /// Answer repeated status requests from memory for `ttl` (disabled by default)
+    ///
+    /// [`LicenseClient::get_status`] and [`LicenseClient::get_all_statuses`]
+    /// return the last fetched value while it is younger than `ttl`, which
+    /// takes load off the server when dashboards poll frequently. A tool's
+    /// status from the full list also serves `get_status`. The cache is
+    /// shared by clones of the client, and a tool's entry (and the full
+    /// list) is dropped when this client borrows or returns one of its
+    /// seats. Commit checks always ask the server.
+    /// `Duration::ZERO` disables caching.
+    pub fn status_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.status_cache_ttl = ttl;
+        self
+    }
+    
+    /// Record user names as `<redacted>` in tracing spans (disabled by default)
+    ///
+    /// Only available with the `tracing` feature.
+    #[cfg(feature = "tracing")]
+    pub fn redact_user_in_traces(mut self, redact: bool) -> Self {
+        self.redact_user = redact;
+        self
+    }
+    
+    /// Fail borrows locally when the pool is known to be empty (disabled by default)
+    ///
+    /// Statuses fetched through [`LicenseClient::get_status`] or
+    /// [`LicenseClient::get_all_statuses`] are remembered; a borrow whose
+    /// tool had no available seat and no overage headroom in a snapshot
+    /// younger than `max_age` returns `NoLicensesAvailable` without asking
+    /// the server. Older or missing snapshots never fail a borrow.
+    pub fn fast_fail(mut self, max_age: Duration) -> Self {
+        self.fast_fail = Some(max_age);
+        self
+    }
+    
+    /// Track this client's in-flight and held borrows (disabled by default)
+    ///
+    /// Managed mode is what [`LicenseClient::effective_available`] and the
+    /// [`watchdog`](Self::watchdog) are built on. Every borrow the client
+    /// or a clone starts is tracked until it fails, or until its license is
+    /// returned or dropped.
+    pub fn managed(mut self, managed: bool) -> Self {
+        self.managed = managed;
+        self
+    }
+    
+    /// Return licenses whose handles are dropped unreturned (enabled by default)
+    ///
+    /// A handle created within a tokio runtime spawns a detached task on
+    /// that runtime to return its license when dropped. This is best
+    /// effort: the task isn't awaited, so the return is lost if the
+    /// runtime shuts down first, and failures can only be logged. Call
+    /// [`LicenseHandle::return_license`](crate::LicenseHandle::return_license)
+    /// where that matters. When disabled, or outside a runtime, dropping an
+    /// unreturned handle only logs a warning.
+    pub fn auto_return_on_drop(mut self, enabled: bool) -> Self {
+        self.auto_return_on_drop = enabled;
+        self
+    }
+    
+    /// Report licenses held longer than `threshold` (disabled by default)
+    ///
+    /// Switches the client to [managed mode](Self::managed), where every
+    /// handle it (or a clone) issues is tracked. A background task
+    /// calls `on_long_hold` once for each handle that is still held after
+    /// `threshold`, which surfaces stuck tasks hoarding seats. The callback
+    /// runs on the runtime, so it should be quick (e.g. log a warning).
+    pub fn watchdog<F>(mut self, threshold: Duration, on_long_hold: F) -> Self
+    where
+        F: Fn(HeldLicense) + Send + Sync + 'static,
+    {
+        self.watchdog = Some(Watchdog::new(threshold, Arc::new(on_long_hold)));
+        self
+    }
+    
+    /// Hedge slow borrows by sending a duplicate request (disabled by default)
+    ///
+    /// The duplicate reuses the original idempotency key, so a server that
+    /// honours `Idempotency-Key` grants at most one seat.
+    pub fn hedge(mut self, policy: HedgePolicy) -> Self {
+        self.hedge = Some(policy);
+        self
+    }
+    
+    /// Retry transient failures according to `policy` (disabled by default)
+    ///
+    /// Retried borrows resend the original idempotency key, so a borrow that
+    /// timed out after the server granted it doesn't take a second seat. The
+    /// policy's [`RetryBudget`](crate::RetryBudget) is shared by all clones
+    /// of the built client.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+    
+    /// Store idempotency keys for [`LicenseClient::borrow_idempotent`] in `store`
+    ///
+    /// Defaults to a [`MemoryIdempotencyStore`](crate::MemoryIdempotencyStore),
+    /// which only deduplicates borrows within this process. Only
+    /// `borrow_idempotent` consults the store: [`borrow_with_key`](crate::LicenseClient::borrow_with_key)
+    /// sends the caller's key as-is, and the other borrow methods generate a
+    /// fresh key per call.
+    pub fn idempotency_store(mut self, store: Arc<dyn IdempotencyStore>) -> Self {
+        self.idempotency_store = SharedStore(store);
+        self
+    }
+    
+    /// Build the configured client
+    pub fn build(self) -> LicenseClient {
+        let client = match self.http_client {
+            Some(client) => client,
+            None => {
+                let mut http = reqwest::Client::builder()
+                    .default_headers(self.default_headers)
+                    .pool_max_idle_per_host(self.pool_max_idle_per_host)
+                    .pool_idle_timeout(self.pool_idle_timeout)
+                    .tcp_keepalive(self.tcp_keepalive);
+                if let Some(timeout) = self.timeout {
+                    http = http.timeout(timeout);
+                }
+                if let Some(timeout) = self.connect_timeout {
+                    http = http.connect_timeout(timeout);
+                }
+                // Only fails if the TLS backend can't be initialized, in
+                // which case `reqwest::Client::new()` panics as well
+                http.build().expect("failed to build HTTP client")
+            }
+        };
+        
+        LicenseClient {
+            client: Arc::new(client),
+            base_url: self.base_url,
+            auth: match (self.enable_security, self.bearer_token) {
+                (false, _) => AuthMode::None,
+                (true, Some(token)) => AuthMode::Bearer(token),
+                (true, None) => AuthMode::Hmac {
+                    vendor_id: self.vendor_id,
+                    secret: self.vendor_secret,
+                },
+            },
+            api_key: self.api_key,
+            signer: self.signer,
+            clock: Arc::new(SkewedClock::new(self.clock, self.clock_offset)),
+            correct_clock_skew: self.correct_clock_skew,
+            tool_prefix: self.tool_prefix,
+            paths: Arc::new(self.api_paths),
+            content_type: self.content_type,
+            api_version: self.api_version,
+            host_lock_dir: self.host_lock_dir,
+            host_semaphore: self.host_semaphore,
+            return_journal: self.return_journal,
+            offline_grants: self.offline_grants,
+            security_downgrade: self.security_downgrade,
+            max_response_bytes: self.max_response_bytes,
+            poll_interval: self.poll_interval,
+            hedger: self.hedge.map(|policy| Arc::new(Hedger::new(policy))),
+            retrier: self.retry.map(|policy| Arc::new(Retrier::new(policy))),
+            counters: Arc::default(),
+            hold_times: Arc::default(),
+            status_cache: Arc::default(),
+            status_ttl: self.status_cache_ttl,
+            #[cfg(feature = "tracing")]
+            redact_user: self.redact_user,
+            fast_fail: self.fast_fail,
+            managed: (self.managed || self.watchdog.is_some())
+                .then(|| Arc::new(ManagedHandles::new(self.watchdog))),
+            auto_return: self.auto_return_on_drop,
+            pending_returns: Arc::default(),
+            idempotency_store: self.idempotency_store,
+            commit_only_enforced: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! The clock signature timestamps are taken from, with skew compensation
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Utc};
+
+use crate::retry::Attempt;
+use crate::{LicenseClient, LicenseError, Result};
+
+/// How far the server's clock may be off before a rejection counts as skew
+///
+/// Well above network latency and the `Date` header's one second
+/// resolution, and well below the server's signature window.
+const SKEW_TOLERANCE_SECS: i64 = 30;
+
+/// The time in a response's `Date` header
+pub(crate) fn response_date(headers: &reqwest::header::HeaderMap) -> Option<DateTime<Utc>> {
+    let date = headers.get(reqwest::header::DATE)?.to_str().ok()?;
+    DateTime::parse_from_rfc2822(date).ok().map(|date| date.with_timezone(&Utc))
+}
+
+/// A function returning the current time, in place of [`SystemTime::now`]
+///
+/// See [`LicenseClientBuilder::clock`](crate::LicenseClientBuilder::clock).
+pub type Clock = Arc<dyn Fn() -> SystemTime + Send + Sync>;
+
+/// Shared clock handle, so the builder can stay `Clone` + `Debug`
+#[derive(Clone)]
+pub(crate) struct SharedClock(pub(crate) Clock);
+
+impl std::fmt::Debug for SharedClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Clock")
+    }
+}
+
+/// The configured clock and the offset applied to it
+pub(crate) struct SkewedClock {
+    clock: Option<SharedClock>,
+    // Seconds added to the clock's time, shared by all clones of a client
+    offset: AtomicI64,
+}
+
+impl SkewedClock {
+    pub(crate) fn new(clock: Option<SharedClock>, offset: chrono::Duration) -> Self {
+        Self {
+            clock,
+            offset: AtomicI64::new(offset.num_seconds()),
+        }
+    }
+    
+    fn local_now(&self) -> SystemTime {
+        self.clock.as_ref().map_or_else(SystemTime::now, |clock| (clock.0)())
+    }
+    
+    /// Unix seconds by the local clock, without the offset
+    ///
+    /// A clock set before 1970 can't produce a valid signature timestamp.
+    fn local_secs(&self) -> Result<i64> {
+        self.local_now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as i64)
+            .map_err(|_| LicenseError::SigningError("system clock is before the Unix epoch".to_string()))
+    }
+}
+
+impl std::fmt::Debug for SkewedClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SkewedClock")
+            .field("offset", &self.offset.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+impl LicenseClient {
+    /// Current Unix timestamp for signatures, with the clock offset applied
+    pub(crate) fn timestamp(&self) -> Result<String> {
+        let offset = self.clock.offset.load(Ordering::Relaxed);
+        Ok((self.clock.local_secs()? + offset).to_string())
+    }
+    
+    /// Current time by the configured clock, with the offset applied
+    ///
+    /// Lease expiries are the server's times, so they are compared with this.
+    pub(crate) fn now(&self) -> DateTime<Utc> {
+        let offset = chrono::Duration::seconds(self.clock.offset.load(Ordering::Relaxed));
+        DateTime::<Utc>::from(self.clock.local_now()) + offset
+    }
+    
+    /// The offset currently added to the local clock when signing
+    ///
+    /// Starts at the configured [`clock_offset`](crate::LicenseClientBuilder::clock_offset)
+    /// and is updated when [clock skew correction](crate::LicenseClientBuilder::correct_clock_skew)
+    /// kicks in.
+    pub fn clock_offset(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.clock.offset.load(Ordering::Relaxed))
+    }
+    
+    /// The server's time, if `response` rejected a timestamp that is off by
+    /// more than the tolerance and skew correction is enabled
+    pub(crate) fn skewed_server_time<T: Attempt>(&self, response: &T) -> Option<DateTime<Utc>> {
+        if !self.correct_clock_skew {
+            return None;
+        }
+        let server_time = response.rejected_at()?;
+        let signed_at = self.timestamp().ok()?.parse::<i64>().ok()?;
+        ((server_time.timestamp() - signed_at).abs() > SKEW_TOLERANCE_SECS).then_some(server_time)
+    }
+    
+    /// Align signature timestamps with the server's clock
+    pub(crate) fn correct_clock(&self, server_time: DateTime<Utc>) -> Result<()> {
+        let offset = server_time.timestamp() - self.clock.local_secs()?;
+        eprintln!(
+            "Warning: local clock is {}s off from the server's; adjusting signature timestamps",
+            -offset
+        );
+        self.clock.offset.store(offset, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_signed_timestamp_follows_offset_and_server_time() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .and(header("x-timestamp", "1000030"))
+            .respond_with(ResponseTemplate::new(403).set_body_json(serde_json::json!({
+                "code": "TIMESTAMP_SKEW", "server_time": 1_000_100
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .and(header("x-timestamp", "1000100"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .clock(Arc::new(|| UNIX_EPOCH + Duration::from_secs(1_000_000)))
+            .clock_offset(chrono::Duration::seconds(30))
+            .correct_clock_skew(true)
+            .build();
+        assert_eq!(client.timestamp().unwrap(), "1000030");
+        
+        let license = client.borrow("cad_tool", "alice").await.unwrap();
+        assert_eq!(client.clock_offset(), chrono::Duration::seconds(100));
+        license.return_license().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_return_corrects_skew_from_date_header() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .mount(&server)
+            .await;
+        // No skew details in the body, only the server's `Date`
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .and(header("x-timestamp", "1000000"))
+            .respond_with(
+                ResponseTemplate::new(403)
+                    .insert_header("Date", "Mon, 12 Jan 1970 13:48:20 GMT")
+                    .set_body_json(serde_json::json!({ "detail": "Security validation failed" })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .and(header("x-timestamp", "1000100"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .clock(Arc::new(|| UNIX_EPOCH + Duration::from_secs(1_000_000)))
+            .correct_clock_skew(true)
+            .build();
+        let license = client.borrow("cad_tool", "alice").await.unwrap();
+        license.return_license().await.unwrap();
+        assert_eq!(client.clock_offset(), chrono::Duration::seconds(100));
+    }
+    
+    #[tokio::test]
+    async fn test_auth_failure_without_skew_is_not_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(
+                ResponseTemplate::new(403)
+                    .insert_header("Date", "Mon, 12 Jan 1970 13:46:45 GMT")
+                    .set_body_json(serde_json::json!({ "detail": "Security validation failed" })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .clock(Arc::new(|| UNIX_EPOCH + Duration::from_secs(1_000_000)))
+            .correct_clock_skew(true)
+            .build();
+        let result = client.borrow("cad_tool", "alice").await;
+        assert!(matches!(result, Err(LicenseError::AuthenticationFailed { .. })));
+        assert_eq!(client.clock_offset(), chrono::Duration::zero());
+    }
+}
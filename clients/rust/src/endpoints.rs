@@ -0,0 +1,229 @@
+//! The URLs a client sends its requests to
+
+use urlencoding::encode;
+
+use crate::LicenseClient;
+
+/// The route of every request, relative to the base URL
+///
+/// Defaults to the server's standard routes. Set a [`prefix`](Self::prefix)
+/// for servers mounted under a sub-path (e.g. `/api/v1`) or behind a proxy
+/// that namespaces routes, and override single routes as needed. Routes for
+/// one tool or license contain a `{tool}` or `{id}` placeholder, replaced by
+/// the URL-encoded value. See [`LicenseClientBuilder::api_paths`](crate::LicenseClientBuilder::api_paths).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiPaths {
+    /// Prepended to every route, including [`LicenseClient::raw_request`] paths
+    pub prefix: String,
+    /// Borrow requests
+    pub borrow: String,
+    /// Return requests
+    pub return_license: String,
+    /// Lease renewals
+    pub renew: String,
+    /// Status of all tools
+    pub all_statuses: String,
+    /// Status of `{tool}`
+    pub status: String,
+    /// Event stream of `{tool}`'s status
+    pub status_stream: String,
+    /// Usage history of `{tool}`
+    pub history: String,
+    /// Whether the server still holds license `{id}`
+    pub valid: String,
+    /// Queue joins; tickets live below it
+    pub queue: String,
+    /// Outstanding borrows, filtered by query
+    pub borrows: String,
+    /// Every outstanding borrow
+    pub borrowed: String,
+    /// Seat reservations; commits and cancels live below it
+    pub reserve: String,
+    /// Credential checks
+    pub verify_auth: String,
+    /// Server-Sent Events of borrows and returns
+    pub events: String,
+    /// WebSocket sessions (`ws` feature)
+    pub ws: String,
+}
+
+impl Default for ApiPaths {
+    fn default() -> Self {
+        Self {
+            prefix: String::new(),
+            borrow: "/licenses/borrow".to_string(),
+            return_license: "/licenses/return".to_string(),
+            renew: "/licenses/renew".to_string(),
+            all_statuses: "/licenses/status".to_string(),
+            status: "/licenses/{tool}/status".to_string(),
+            status_stream: "/licenses/{tool}/status/stream".to_string(),
+            history: "/licenses/{tool}/history".to_string(),
+            valid: "/licenses/{id}/valid".to_string(),
+            queue: "/licenses/queue".to_string(),
+            borrows: "/borrows".to_string(),
+            borrowed: "/licenses/borrowed".to_string(),
+            reserve: "/licenses/reserve".to_string(),
+            verify_auth: "/auth/verify".to_string(),
+            events: "/events".to_string(),
+            ws: "/ws".to_string(),
+        }
+    }
+}
+
+impl ApiPaths {
+    /// The standard routes below `prefix`
+    pub fn with_prefix(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            ..Self::default()
+        }
+    }
+    
+    /// `base_url` joined with the prefix, without a trailing slash
+    pub(crate) fn root(&self, base_url: &str) -> String {
+        let prefix = self.prefix.trim_matches('/');
+        let base_url = base_url.trim_end_matches('/');
+        if prefix.is_empty() {
+            base_url.to_string()
+        } else {
+            format!("{}/{}", base_url, prefix)
+        }
+    }
+}
+
+/// Every URL a [`LicenseClient`] may call, built from its base URL
+///
+/// Useful for proxy allow-lists and for debugging base URL problems.
+/// Per-tool and per-ticket URLs are built by the methods; tool names get
+/// the client's tool prefix applied and are URL-encoded, exactly as in
+/// requests. [`LicenseClient::raw_request`] can reach any other path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    /// `POST` borrow requests
+    pub borrow: String,
+    /// `POST` return requests
+    pub return_license: String,
+    /// `POST` lease renewals
+    pub renew: String,
+    /// `GET` status of all tools
+    pub all_statuses: String,
+    /// `POST` queue joins
+    pub queue: String,
+    /// `GET` outstanding borrows, optionally with a `tool` or `user` query
+    pub borrows: String,
+    /// `GET` every outstanding borrow
+    pub borrowed: String,
+    /// `POST` seat reservations
+    pub reserve: String,
+    /// `POST` credential checks
+    pub verify_auth: String,
+    /// `GET` borrow and return events
+    pub events: String,
+    /// WebSocket upgrade for sessions (`ws` feature)
+    pub ws: String,
+    root: String,
+    paths: ApiPaths,
+    tool_prefix: Option<String>,
+}
+
+impl Endpoints {
+    fn tool_url(&self, route: &str, tool: &str) -> String {
+        let tool = format!("{}{}", self.tool_prefix.as_deref().unwrap_or_default(), tool);
+        format!("{}{}", self.root, route.replace("{tool}", &encode(&tool)))
+    }
+    
+    /// `GET` status of `tool`
+    pub fn status(&self, tool: &str) -> String {
+        self.tool_url(&self.paths.status, tool)
+    }
+    
+    /// `GET` server-sent status events of `tool`
+    pub fn status_stream(&self, tool: &str) -> String {
+        self.tool_url(&self.paths.status_stream, tool)
+    }
+    
+    /// `GET` usage history of `tool`, without the `from`/`to` query
+    pub fn history(&self, tool: &str) -> String {
+        self.tool_url(&self.paths.history, tool)
+    }
+    
+    /// `GET` whether the server still holds the license `id`
+    pub fn valid(&self, id: &str) -> String {
+        format!("{}{}", self.root, self.paths.valid.replace("{id}", &encode(id)))
+    }
+    
+    /// `POST` to turn reservation `id` into a license
+    pub fn reservation_commit(&self, id: &str) -> String {
+        format!("{}/{}/commit", self.reserve, encode(id))
+    }
+    
+    /// `POST` to release reservation `id`
+    pub fn reservation_cancel(&self, id: &str) -> String {
+        format!("{}/{}/cancel", self.reserve, encode(id))
+    }
+    
+    /// `GET` state of the queue ticket `id`
+    pub fn queue_ticket(&self, id: &str) -> String {
+        format!("{}/{}", self.queue, encode(id))
+    }
+}
+
+impl LicenseClient {
+    /// The URLs this client sends its requests to
+    pub fn endpoints(&self) -> Endpoints {
+        let paths = &self.paths;
+        let root = paths.root(&self.base_url);
+        let url = |path: &str| format!("{}{}", root, path);
+        Endpoints {
+            borrow: url(&paths.borrow),
+            return_license: url(&paths.return_license),
+            renew: url(&paths.renew),
+            all_statuses: url(&paths.all_statuses),
+            queue: url(&paths.queue),
+            borrows: url(&paths.borrows),
+            borrowed: url(&paths.borrowed),
+            reserve: url(&paths.reserve),
+            verify_auth: url(&paths.verify_auth),
+            events: url(&paths.events),
+            ws: url(&paths.ws),
+            paths: ApiPaths::clone(paths),
+            tool_prefix: self.tool_prefix.clone(),
+            root,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_endpoints() {
+        let client = LicenseClient::builder("http://licenses:8000")
+            .tool_prefix("team a/")
+            .build();
+        let endpoints = client.endpoints();
+        
+        assert_eq!(endpoints.borrow, "http://licenses:8000/licenses/borrow");
+        assert_eq!(endpoints.status("cad_tool"), "http://licenses:8000/licenses/team%20a%2Fcad_tool/status");
+        assert_eq!(endpoints.queue_ticket("t/1"), "http://licenses:8000/licenses/queue/t%2F1");
+    }
+    
+    #[test]
+    fn test_api_paths_prefix_and_overrides() {
+        let paths = ApiPaths {
+            status: "/tools/{tool}".to_string(),
+            ..ApiPaths::with_prefix("/api/v1/")
+        };
+        let client = LicenseClient::builder("http://proxy/").api_paths(paths).build();
+        let endpoints = client.endpoints();
+        
+        assert_eq!(endpoints.borrow, "http://proxy/api/v1/licenses/borrow");
+        assert_eq!(endpoints.status("cad tool"), "http://proxy/api/v1/tools/cad%20tool");
+        assert_eq!(endpoints.valid("abc"), "http://proxy/api/v1/licenses/abc/valid");
+        assert_eq!(endpoints.reservation_commit("r-1"), "http://proxy/api/v1/licenses/reserve/r-1/commit");
+        
+        let client = LicenseClient::builder("http://proxy").api_prefix("api").build();
+        assert_eq!(client.endpoints().verify_auth, "http://proxy/api/auth/verify");
+    }
+}
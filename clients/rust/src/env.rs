@@ -0,0 +1,88 @@
+//! Client configuration from environment variables
+
+use crate::{LicenseClient, LicenseClientBuilder, LicenseError, Result};
+
+/// Base URL used when `PERMETIX_BASE_URL` is not set
+const DEFAULT_BASE_URL: &str = "http://localhost:8000";
+
+impl LicenseClient {
+    /// Create a client with vendor credentials from the environment
+    ///
+    /// Reads:
+    ///
+    /// * `PERMETIX_VENDOR_ID` - Vendor ID (required)
+    /// * `PERMETIX_VENDOR_SECRET` - Vendor secret, or
+    ///   `PERMETIX_VENDOR_SECRET_FILE` - path of a file holding it, e.g. a
+    ///   mounted Kubernetes secret (trailing whitespace is ignored)
+    /// * `PERMETIX_BASE_URL` - Server URL (defaults to `http://localhost:8000`)
+    ///
+    /// Security is enabled, and the API key is read from `LICENSE_API_KEY`
+    /// as usual. Keeping the secret out of code and process arguments means
+    /// it never shows up in `ps` output or shell history.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LicenseError::Configuration` if the vendor ID or secret is
+    /// missing, or the secret file can't be read.
+    pub fn from_env() -> Result<Self> {
+        Ok(builder_from(|name| std::env::var(name).ok())?.build())
+    }
+}
+
+/// A builder configured from the variables `lookup` returns
+fn builder_from(lookup: impl Fn(&str) -> Option<String>) -> Result<LicenseClientBuilder> {
+    let vendor_id = lookup("PERMETIX_VENDOR_ID")
+        .ok_or_else(|| LicenseError::Configuration("PERMETIX_VENDOR_ID is not set".to_string()))?;
+    let vendor_secret = match (lookup("PERMETIX_VENDOR_SECRET"), lookup("PERMETIX_VENDOR_SECRET_FILE")) {
+        (Some(secret), _) => secret,
+        (None, Some(path)) => std::fs::read_to_string(&path)
+            .map(|secret| secret.trim_end().to_string())
+            .map_err(|e| LicenseError::Configuration(format!("failed to read vendor secret from {}: {}", path, e)))?,
+        (None, None) => {
+            return Err(LicenseError::Configuration(
+                "neither PERMETIX_VENDOR_SECRET nor PERMETIX_VENDOR_SECRET_FILE is set".to_string(),
+            ))
+        }
+    };
+    let base_url = lookup("PERMETIX_BASE_URL").unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+    
+    Ok(LicenseClient::builder(base_url).credentials(vendor_id, vendor_secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AuthMode;
+    use std::collections::HashMap;
+    
+    #[test]
+    fn test_credentials_from_env_and_secret_file() {
+        let secret_file = std::env::temp_dir().join(format!("vendor-secret-{}", LicenseClient::generate_idempotency_key()));
+        std::fs::write(&secret_file, "file_secret\n").unwrap();
+        let mut vars = HashMap::from([
+            ("PERMETIX_VENDOR_ID", "acme".to_string()),
+            ("PERMETIX_VENDOR_SECRET_FILE", secret_file.display().to_string()),
+        ]);
+        
+        let client = builder_from(|name| vars.get(name).cloned()).unwrap().build();
+        assert_eq!(client.base_url, DEFAULT_BASE_URL);
+        assert_eq!(
+            client.auth,
+            AuthMode::Hmac {
+                vendor_id: "acme".to_string(),
+                secret: "file_secret".to_string()
+            }
+        );
+        std::fs::remove_file(&secret_file).unwrap();
+        
+        vars.insert("PERMETIX_BASE_URL", "https://licenses.example.com".to_string());
+        vars.insert("PERMETIX_VENDOR_SECRET", "env_secret".to_string());
+        let client = builder_from(|name| vars.get(name).cloned()).unwrap().build();
+        assert_eq!(client.base_url, "https://licenses.example.com");
+        assert!(matches!(client.auth, AuthMode::Hmac { secret, .. } if secret == "env_secret"));
+        
+        vars.remove("PERMETIX_VENDOR_ID");
+        let result = builder_from(|name| vars.get(name).cloned());
+        assert!(matches!(result, Err(LicenseError::Configuration(message)) if message.contains("PERMETIX_VENDOR_ID")));
+    }
+}
@@ -0,0 +1,172 @@
+//! Real-time borrow and return notifications over Server-Sent Events
+
+use std::time::Duration;
+
+use futures_util::{stream, Stream};
+use serde::Deserialize;
+
+use crate::sse::{SseEvent, SseParser};
+use crate::{parse_json, LicenseClient, LicenseError, Result};
+
+/// Longest pause between reconnection attempts of an event stream
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// What happened to a license
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LicenseEventKind {
+    Borrowed,
+    Returned,
+    /// An event kind this client doesn't know yet
+    #[serde(other)]
+    Other,
+}
+
+/// A borrow or return pushed by the server
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct LicenseEvent {
+    /// What happened, from the payload or else the SSE event name
+    pub kind: LicenseEventKind,
+    /// Tool name, as reported by the server
+    pub tool: String,
+    /// User who borrowed or returned the seat
+    #[serde(default)]
+    pub user: String,
+    /// License ID
+    #[serde(default)]
+    pub id: String,
+    /// When it happened, as reported by the server (empty if not)
+    #[serde(default)]
+    pub timestamp: String,
+}
+
+impl LicenseEvent {
+    fn decode(event: &SseEvent) -> Result<Self> {
+        let mut value: serde_json::Value = parse_json(event.data.as_bytes())?;
+        if let (Some(fields), Some(name)) = (value.as_object_mut(), &event.event) {
+            fields.entry("kind").or_insert_with(|| name.clone().into());
+        }
+        serde_json::from_value(value).map_err(|e| LicenseError::InvalidResponse(e.to_string()))
+    }
+}
+
+struct Subscription {
+    client: LicenseClient,
+    url: String,
+    events: Option<(Box<reqwest::Response>, SseParser)>,
+    // Connection attempts since the last event, for the backoff
+    failures: u32,
+    finished: bool,
+}
+
+impl Subscription {
+    async fn next(&mut self) -> Option<Result<LicenseEvent>> {
+        loop {
+            if self.finished {
+                return None;
+            }
+            let Some((response, parser)) = &mut self.events else {
+                if self.failures > 0 {
+                    tokio::time::sleep(self.reconnect_delay()).await;
+                }
+                self.failures += 1;
+                match self.client.open_event_stream(&self.url).await {
+                    Ok(Some(response)) => self.events = Some((Box::new(response), SseParser::default())),
+                    Ok(None) => {
+                        self.finished = true;
+                        return Some(Err(LicenseError::UnsupportedEndpoint(self.url.clone())));
+                    }
+                    Err(e) => return Some(Err(e)),
+                }
+                continue;
+            };
+            
+            if let Some(event) = parser.next_event() {
+                self.failures = 0;
+                return Some(LicenseEvent::decode(&event));
+            }
+            match response.chunk().await {
+                Ok(Some(chunk)) => parser.push(&chunk),
+                Ok(None) => self.events = None,
+                Err(e) => {
+                    self.events = None;
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+    }
+    
+    /// The poll interval, doubled for every failed attempt in a row
+    fn reconnect_delay(&self) -> Duration {
+        let doublings = self.failures.saturating_sub(1).min(16);
+        self.client.poll_interval.saturating_mul(1 << doublings).min(MAX_RECONNECT_DELAY)
+    }
+}
+
+impl LicenseClient {
+    /// Subscribe to the server's borrow and return notifications
+    ///
+    /// Connects to the `/events` Server-Sent Events endpoint and yields each
+    /// event as it arrives. When the connection drops, the stream yields the
+    /// error (if any) and reconnects, waiting
+    /// [`poll_interval`](crate::LicenseClientBuilder::poll_interval) and
+    /// doubling that up to 30s while attempts keep failing. Dropping the
+    /// stream closes the connection.
+    ///
+    /// # Errors
+    ///
+    /// Yields `UnsupportedEndpoint` and ends if the server has no event
+    /// stream, and `InvalidResponse` for events that can't be decoded.
+    pub fn event_stream(&self) -> impl Stream<Item = Result<LicenseEvent>> {
+        let subscription = Subscription {
+            client: self.clone(),
+            url: self.endpoints().events,
+            events: None,
+            failures: 0,
+            finished: false,
+        };
+        stream::unfold(subscription, |mut subscription| async move {
+            let event = subscription.next().await?;
+            Some((event, subscription))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_event_stream_decodes_and_reconnects() {
+        let server = MockServer::start().await;
+        let body = concat!(
+            "event: borrowed\n",
+            "data: {\"tool\": \"cad_tool\", \"user\": \"alice\", \"id\": \"abc-123\", \"timestamp\": \"2025-03-01T12:00:00Z\"}\n\n",
+            ": keep-alive\n\n",
+            "data: {\"kind\": \"returned\", \"tool\": \"cad_tool\", \"user\": \"alice\", \"id\": \"abc-123\"}\n\n",
+        );
+        Mock::given(method("GET"))
+            .and(path("/events"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .expect(2)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .poll_interval(Duration::from_millis(10))
+            .build();
+        let events: Vec<_> = client.event_stream().take(3).map(|event| event.unwrap()).collect().await;
+        
+        assert_eq!(events[0].kind, LicenseEventKind::Borrowed);
+        assert_eq!(events[0].user, "alice");
+        assert_eq!(events[0].timestamp, "2025-03-01T12:00:00Z");
+        assert_eq!(events[1].kind, LicenseEventKind::Returned);
+        assert_eq!(events[1].id, "abc-123");
+        // The canned body ends, so the third event comes from a new connection
+        assert_eq!(events[2], events[0]);
+    }
+}
@@ -0,0 +1,191 @@
+//! Hedged borrow requests
+//!
+//! When a borrow hasn't answered within a delay derived from recent borrow
+//! latencies, a second identical request (same idempotency key) is sent and
+//! whichever responds first is used.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Settings for hedged borrow requests
+#[derive(Debug, Clone)]
+pub struct HedgePolicy {
+    /// Latency percentile (0.0 - 1.0) of recent borrows after which the hedge is sent
+    pub percentile: f64,
+    /// Hedge delay used until `min_samples` latencies have been recorded
+    pub initial_delay: Duration,
+    /// Lower bound for the hedge delay
+    pub min_delay: Duration,
+    /// Number of recorded latencies required before the percentile is used
+    pub min_samples: usize,
+    /// Number of most recent borrow latencies kept
+    pub window: usize,
+}
+
+impl Default for HedgePolicy {
+    fn default() -> Self {
+        Self {
+            percentile: 0.95,
+            initial_delay: Duration::from_millis(500),
+            min_delay: Duration::from_millis(10),
+            min_samples: 20,
+            window: 200,
+        }
+    }
+}
+
+/// Hedge policy plus the latency samples it is computed from
+///
+/// Shared between clones of a client so all of them learn from the same
+/// borrow latencies.
+#[derive(Debug)]
+pub(crate) struct Hedger {
+    policy: HedgePolicy,
+    samples: Mutex<VecDeque<Duration>>,
+}
+
+impl Hedger {
+    pub(crate) fn new(policy: HedgePolicy) -> Self {
+        Self {
+            samples: Mutex::new(VecDeque::with_capacity(policy.window)),
+            policy,
+        }
+    }
+    
+    /// How long to wait for the primary request before hedging
+    pub(crate) fn delay(&self) -> Duration {
+        let samples = self.samples.lock().unwrap();
+        if samples.is_empty() || samples.len() < self.policy.min_samples {
+            return self.policy.initial_delay.max(self.policy.min_delay);
+        }
+        
+        let mut sorted: Vec<Duration> = samples.iter().copied().collect();
+        sorted.sort();
+        let percentile = self.policy.percentile.clamp(0.0, 1.0);
+        let rank = (percentile * sorted.len() as f64).ceil() as usize;
+        let index = rank.saturating_sub(1).min(sorted.len() - 1);
+        sorted[index].max(self.policy.min_delay)
+    }
+    
+    /// Record the latency of a completed borrow
+    pub(crate) fn record(&self, latency: Duration) {
+        let mut samples = self.samples.lock().unwrap();
+        if samples.len() >= self.policy.window.max(1) {
+            samples.pop_front();
+        }
+        samples.push_back(latency);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LicenseClient;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    async fn mock_borrow(server: &MockServer, id: &str, delay: Duration) {
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "id": id }))
+                    .set_delay(delay),
+            )
+            .up_to_n_times(1)
+            .mount(server)
+            .await;
+    }
+    
+    fn idempotency_keys(requests: &[wiremock::Request]) -> Vec<String> {
+        requests
+            .iter()
+            .filter(|request| request.url.path() == "/licenses/borrow")
+            .map(|request| request.headers.get("idempotency-key").unwrap().to_str().unwrap().to_string())
+            .collect()
+    }
+    
+    #[test]
+    fn test_delay_uses_initial_until_enough_samples() {
+        let hedger = Hedger::new(HedgePolicy {
+            min_samples: 3,
+            ..HedgePolicy::default()
+        });
+        hedger.record(Duration::from_millis(5));
+        assert_eq!(hedger.delay(), Duration::from_millis(500));
+    }
+    
+    #[test]
+    fn test_delay_tracks_percentile() {
+        let hedger = Hedger::new(HedgePolicy {
+            percentile: 0.9,
+            min_samples: 10,
+            min_delay: Duration::ZERO,
+            ..HedgePolicy::default()
+        });
+        for ms in 1..=10 {
+            hedger.record(Duration::from_millis(ms * 10));
+        }
+        assert_eq!(hedger.delay(), Duration::from_millis(90));
+    }
+    
+    #[tokio::test]
+    async fn test_slow_borrow_is_hedged_with_same_key() {
+        let server = MockServer::start().await;
+        mock_borrow(&server, "primary", Duration::from_secs(2)).await;
+        mock_borrow(&server, "hedge", Duration::ZERO).await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .hedge(HedgePolicy {
+                initial_delay: Duration::from_millis(50),
+                ..HedgePolicy::default()
+            })
+            .build();
+        let license = client.borrow("cad_tool", "alice").await.unwrap();
+        assert_eq!(license.id(), "hedge");
+        license.return_license().await.unwrap();
+        
+        let keys = idempotency_keys(&server.received_requests().await.unwrap());
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0], keys[1]);
+    }
+    
+    #[tokio::test]
+    async fn test_hedge_falls_back_when_first_request_fails() {
+        let server = MockServer::start().await;
+        // The primary times out at 600ms, before the hedge (sent at 200ms)
+        // answers at 700ms; the hedge's own timeout would be at 800ms
+        mock_borrow(&server, "primary", Duration::from_secs(2)).await;
+        mock_borrow(&server, "hedge", Duration::from_millis(500)).await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .timeout(Duration::from_millis(600))
+            .hedge(HedgePolicy {
+                initial_delay: Duration::from_millis(200),
+                ..HedgePolicy::default()
+            })
+            .build();
+        let license = client.borrow("cad_tool", "alice").await.unwrap();
+        assert_eq!(license.id(), "hedge");
+        license.return_license().await.unwrap();
+        
+        let keys = idempotency_keys(&server.received_requests().await.unwrap());
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0], keys[1]);
+    }
+}
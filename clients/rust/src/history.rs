@@ -0,0 +1,117 @@
+//! Historical seat utilization, for servers that record it
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Deserialize;
+use urlencoding::encode;
+
+use crate::{parse_json, LicenseClient, Result, ServerTimestamp};
+
+/// Utilization of a tool at one point in time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageSample {
+    /// When the sample was taken
+    pub timestamp: DateTime<Utc>,
+    /// Seats borrowed at that time
+    pub borrowed: i32,
+    /// Seats available at that time
+    pub available: i32,
+}
+
+impl LicenseClient {
+    /// Get a tool's recorded utilization between `from` and `to`
+    ///
+    /// # Arguments
+    /// * `tool` - Name of the tool
+    /// * `from` - Start of the time range
+    /// * `to` - End of the time range
+    ///
+    /// # Errors
+    /// Returns `UnsupportedEndpoint` if the server doesn't record history,
+    /// `HttpError` for other failures (including an unknown tool), and
+    /// `InvalidResponse` if a sample can't be parsed.
+    pub async fn get_usage_history(
+        &self,
+        tool: impl Into<String>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<UsageSample>> {
+        #[derive(Deserialize)]
+        struct RawSample {
+            timestamp: String,
+            borrowed: i32,
+            available: i32,
+        }
+        
+        let endpoint = self.endpoints().history(&tool.into());
+        let url = format!(
+            "{}?from={}&to={}",
+            endpoint,
+            encode(&from.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            encode(&to.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        );
+        
+        let response = self
+            .fetch(|| self.authorized(self.client.get(&url)))
+            .await?
+            .error_for_endpoint(&endpoint)?;
+        
+        let samples: Vec<RawSample> = parse_json(&response.body)?;
+        samples
+            .into_iter()
+            .map(|sample| {
+                Ok(UsageSample {
+                    timestamp: ServerTimestamp::parse(&sample.timestamp)?.utc(),
+                    borrowed: sample.borrowed,
+                    available: sample.available,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LicenseError;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_get_usage_history() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad%20tool/history"))
+            .and(query_param("from", "2025-01-01T00:00:00Z"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "timestamp": "2025-01-01T00:00:00Z", "borrowed": 3, "available": 7 },
+                { "timestamp": "2025-01-01T01:00:00+01:00", "borrowed": 5, "available": 5 }
+            ])))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        let from = DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let samples = client
+            .get_usage_history("cad tool", from, from + chrono::Duration::hours(1))
+            .await
+            .unwrap();
+        
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[1].timestamp, from);
+        assert_eq!(samples[1].borrowed, 5);
+    }
+    
+    #[tokio::test]
+    async fn test_get_usage_history_unsupported() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({ "detail": "Not Found" })))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        let result = client.get_usage_history("cad_tool", Utc::now(), Utc::now()).await;
+        
+        assert!(matches!(result, Err(LicenseError::UnsupportedEndpoint(_))));
+    }
+}
@@ -0,0 +1,60 @@
+//! Client-side tracking of how long seats are held per tool
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::LicenseClient;
+
+/// Weight of the newest sample in the moving average
+const SMOOTHING: f64 = 0.2;
+
+/// Exponentially weighted average hold time per tool
+///
+/// Fed by licenses returned through this client (and its clones).
+#[derive(Debug, Default)]
+pub(crate) struct HoldTimes {
+    averages: Mutex<HashMap<String, Duration>>,
+}
+
+impl HoldTimes {
+    pub(crate) fn record(&self, tool: &str, held: Duration) {
+        let mut averages = self.averages.lock().unwrap();
+        averages
+            .entry(tool.to_string())
+            .and_modify(|average| {
+                *average = average.mul_f64(1.0 - SMOOTHING) + held.mul_f64(SMOOTHING);
+            })
+            .or_insert(held);
+    }
+    
+    pub(crate) fn average(&self, tool: &str) -> Option<Duration> {
+        self.averages.lock().unwrap().get(tool).copied()
+    }
+}
+
+impl LicenseClient {
+    /// Average time this client has held a seat of `tool` before returning it
+    ///
+    /// `None` until at least one license for the tool has been returned.
+    pub fn average_hold_time(&self, tool: &str) -> Option<Duration> {
+        self.hold_times.average(tool)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_moving_average() {
+        let hold_times = HoldTimes::default();
+        assert_eq!(hold_times.average("cad"), None);
+        
+        hold_times.record("cad", Duration::from_secs(10));
+        assert_eq!(hold_times.average("cad"), Some(Duration::from_secs(10)));
+        
+        hold_times.record("cad", Duration::from_secs(20));
+        assert_eq!(hold_times.average("cad"), Some(Duration::from_secs(12)));
+    }
+}
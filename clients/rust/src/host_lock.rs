@@ -0,0 +1,111 @@
+//! Per-host advisory locks so one machine holds at most one seat per tool
+
+use std::fs::{self, File, OpenOptions};
+use std::path::Path;
+
+use fs2::FileExt;
+use urlencoding::encode;
+
+use crate::{LicenseClient, LicenseError, Result};
+
+/// An exclusive lock on a tool's lock file, released when dropped
+#[derive(Debug)]
+pub(crate) struct HostLock {
+    // Closing the file releases the lock, including if the process dies
+    _file: File,
+}
+
+impl HostLock {
+    /// Block until the lock for `tool` in `dir` is ours
+    fn acquire(dir: &Path, tool: &str) -> std::io::Result<Self> {
+        let file = Self::open(&dir.join(format!("{}.lock", encode(tool))))?;
+        file.lock_exclusive()?;
+        Ok(Self { _file: file })
+    }
+    
+    /// Take the lock on `path` if no one else holds it
+    pub(crate) fn try_acquire(path: &Path) -> std::io::Result<Option<Self>> {
+        let file = Self::open(path)?;
+        match file.try_lock_exclusive() {
+            Ok(()) => Ok(Some(Self { _file: file })),
+            Err(e) if e.kind() == fs2::lock_contended_error().kind() => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    
+    fn open(path: &Path) -> std::io::Result<File> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        OpenOptions::new().create(true).truncate(false).write(true).open(path)
+    }
+}
+
+impl LicenseClient {
+    /// Take the host lock for `tool`, if host locking is configured
+    ///
+    /// Waits (without blocking the runtime) while another process on this
+    /// host holds the lock.
+    pub(crate) async fn acquire_host_lock(&self, tool: &str) -> Result<Option<HostLock>> {
+        let dir = match &self.host_lock_dir {
+            Some(dir) => dir.clone(),
+            None => return Ok(None),
+        };
+        let tool = tool.to_string();
+        
+        let lock = tokio::task::spawn_blocking(move || HostLock::acquire(&dir, &tool))
+            .await
+            .map_err(|e| LicenseError::LockFailed(e.to_string()))?
+            .map_err(|e| LicenseError::LockFailed(e.to_string()))?;
+        Ok(Some(lock))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_host_lock_serializes_borrows() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let dir = std::env::temp_dir().join(format!("license-lock-{}", LicenseClient::generate_idempotency_key()));
+        // Separate clients stand in for separate processes on the host
+        let client = || {
+            LicenseClient::builder(server.uri())
+                .enable_security(false)
+                .host_lock_dir(&dir)
+                .build()
+        };
+        let (first, second) = (client(), client());
+        
+        let license = first.borrow("cad_tool", "alice").await.unwrap();
+        let waiting = tokio::spawn(async move { second.borrow("cad_tool", "bob").await });
+        
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!waiting.is_finished());
+        
+        license.return_license().await.unwrap();
+        let license = tokio::time::timeout(Duration::from_secs(5), waiting)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        license.return_license().await.unwrap();
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
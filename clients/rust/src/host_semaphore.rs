@@ -0,0 +1,102 @@
+//! Host-wide cap on concurrent borrows, shared by all processes
+
+use std::path::PathBuf;
+
+use crate::host_lock::HostLock;
+use crate::{LicenseClient, LicenseError, Result};
+
+/// A set of lock files acting as a counting semaphore between processes
+///
+/// Each permit is an exclusive lock on one of `permits` slot files, so
+/// permits held by a process that dies are released by the OS.
+#[derive(Debug, Clone)]
+pub(crate) struct HostSemaphore {
+    dir: PathBuf,
+    permits: usize,
+}
+
+impl HostSemaphore {
+    pub(crate) fn new(dir: PathBuf, permits: usize) -> Self {
+        // Zero permits would block every borrow forever
+        Self { dir, permits: permits.max(1) }
+    }
+    
+    fn try_acquire(&self) -> std::io::Result<Option<HostLock>> {
+        for slot in 0..self.permits {
+            if let Some(lock) = HostLock::try_acquire(&self.dir.join(format!("slot-{}.lock", slot)))? {
+                return Ok(Some(lock));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl LicenseClient {
+    /// Take a host-wide borrow permit, if a host semaphore is configured
+    ///
+    /// Polls every `poll_interval` while all permits are held.
+    pub(crate) async fn acquire_host_permit(&self) -> Result<Option<HostLock>> {
+        let semaphore = match &self.host_semaphore {
+            Some(semaphore) => semaphore,
+            None => return Ok(None),
+        };
+        
+        loop {
+            match semaphore.try_acquire() {
+                Ok(Some(permit)) => return Ok(Some(permit)),
+                Ok(None) => tokio::time::sleep(self.poll_interval).await,
+                Err(e) => return Err(LicenseError::LockFailed(e.to_string())),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_host_semaphore_caps_borrows_across_clients() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let dir = std::env::temp_dir().join(format!("license-semaphore-{}", LicenseClient::generate_idempotency_key()));
+        // Separate clients stand in for separate processes on the host
+        let client = || {
+            LicenseClient::builder(server.uri())
+                .enable_security(false)
+                .poll_interval(Duration::from_millis(20))
+                .host_semaphore(&dir, 1)
+                .build()
+        };
+        let (first, second) = (client(), client());
+        
+        let license = first.borrow("cad_tool", "alice").await.unwrap();
+        let waiting = tokio::spawn(async move { second.borrow("ide_tool", "bob").await });
+        
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!waiting.is_finished());
+        
+        license.return_license().await.unwrap();
+        let license = tokio::time::timeout(Duration::from_secs(5), waiting)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        license.return_license().await.unwrap();
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
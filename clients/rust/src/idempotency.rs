@@ -0,0 +1,164 @@
+//! Idempotency keys that outlive the process that created them
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::{LicenseClient, LicenseError, LicenseHandle, Result};
+
+/// Storage for the idempotency keys of in-progress borrows
+///
+/// Maps a caller-chosen operation ID to the idempotency key sent for it.
+/// Back this with durable storage (Redis, a database, a file) so a borrow
+/// retried after a crash reuses its original key and the server doesn't
+/// grant a second seat. Implementations must be cheap enough to call on
+/// every [`LicenseClient::borrow_idempotent`].
+pub trait IdempotencyStore: Send + Sync {
+    /// Get the key stored for `operation`, if any
+    fn get(&self, operation: &str) -> Option<String>;
+    
+    /// Store `key` for `operation`, replacing any previous key
+    fn put(&self, operation: &str, key: &str);
+    
+    /// Forget the key for `operation` once the borrow is settled
+    fn remove(&self, operation: &str);
+}
+
+/// The default [`IdempotencyStore`], which does not survive restarts
+#[derive(Debug, Default)]
+pub struct MemoryIdempotencyStore {
+    keys: Mutex<HashMap<String, String>>,
+}
+
+impl IdempotencyStore for MemoryIdempotencyStore {
+    fn get(&self, operation: &str) -> Option<String> {
+        self.keys.lock().unwrap().get(operation).cloned()
+    }
+    
+    fn put(&self, operation: &str, key: &str) {
+        self.keys.lock().unwrap().insert(operation.to_string(), key.to_string());
+    }
+    
+    fn remove(&self, operation: &str) {
+        self.keys.lock().unwrap().remove(operation);
+    }
+}
+
+/// Shared store handle, so the client and builder can stay `Clone` + `Debug`
+#[derive(Clone)]
+pub(crate) struct SharedStore(pub(crate) Arc<dyn IdempotencyStore>);
+
+impl Default for SharedStore {
+    fn default() -> Self {
+        Self(Arc::new(MemoryIdempotencyStore::default()))
+    }
+}
+
+impl std::fmt::Debug for SharedStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("IdempotencyStore")
+    }
+}
+
+impl LicenseClient {
+    /// Borrow a license at most once for `operation`, even across restarts
+    ///
+    /// The idempotency key for `operation` is looked up in the client's
+    /// [`IdempotencyStore`], and generated and stored before sending if
+    /// missing. Calling this again with the same `operation` after a crash
+    /// or an unknown outcome therefore yields the original seat rather than
+    /// a second one. The key is forgotten when the license is returned, or
+    /// when the server definitively refuses the borrow.
+    ///
+    /// # Arguments
+    /// * `tool` - Tool name
+    /// * `user` - Username
+    /// * `operation` - Caller-chosen ID, unique per logical borrow
+    ///
+    /// # Errors
+    /// Same as [`borrow`](Self::borrow).
+    pub async fn borrow_idempotent(
+        &self,
+        tool: impl Into<String>,
+        user: impl Into<String>,
+        operation: impl Into<String>,
+    ) -> Result<LicenseHandle> {
+        let operation = operation.into();
+        let store = &self.idempotency_store.0;
+        
+        let key = match store.get(&operation) {
+            Some(key) => key,
+            None => {
+                let key = Self::generate_idempotency_key();
+                store.put(&operation, &key);
+                key
+            }
+        };
+        
+        match self.borrow_with_key(tool, user, key).await {
+            Ok(mut handle) => {
+                handle.operation = Some(operation);
+                Ok(handle)
+            }
+            Err(e) => {
+                // Keep the key while the outcome is unknown (transport
+                // failures, 5xx, unreadable responses) so a retry can reuse it
+                let refused = matches!(
+                    e,
+                    LicenseError::NoLicensesAvailable(_)
+                        | LicenseError::WouldExceedCommit(_)
+                        | LicenseError::PolicyDenied { .. }
+                        | LicenseError::TimestampSkew { .. }
+                        | LicenseError::AuthenticationFailed { .. }
+                        | LicenseError::GroupQuotaExhausted(_)
+                        | LicenseError::Validation(_)
+                        | LicenseError::InvalidInput(_)
+                        | LicenseError::SigningError(_)
+                        | LicenseError::HttpError(400..=499, _)
+                );
+                if refused {
+                    store.remove(&operation);
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_borrow_idempotent_reuses_stored_key() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .and(header_exists("idempotency-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        // A key left behind by a previous process that crashed mid-borrow
+        let store = Arc::new(MemoryIdempotencyStore::default());
+        store.put("job-42", "key-from-last-run");
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .idempotency_store(store.clone())
+            .build();
+        let license = client.borrow_idempotent("cad_tool", "alice", "job-42").await.unwrap();
+        
+        assert_eq!(license.idempotency_key(), "key-from-last-run");
+        assert_eq!(store.get("job-42").as_deref(), Some("key-from-last-run"));
+        
+        license.return_license().await.unwrap();
+        assert_eq!(store.get("job-42"), None);
+    }
+}
@@ -0,0 +1,391 @@
+//! Optional `tracing` spans and `metrics` around network operations
+//!
+//! With the `tracing` feature, borrows, returns, renewals, status requests
+//! and waiting borrows each run in a `license` span recording the operation,
+//! tool, user, license ID and elapsed time, and failures emit a `WARN` event.
+//! Waiting borrows that get a seat also record `wait_ms`.
+//!
+//! With the `metrics` feature, each of them counts towards
+//! `permetix_<operation>_total{tool, outcome}` (`outcome` is `success` or
+//! `error`) and records its latency in
+//! `permetix_request_duration_seconds{operation, tool}`. Granted waits are
+//! also recorded in `permetix_wait_seconds{tool}`.
+//!
+//! Without either feature, [`LicenseClient::instrumented`] just awaits the
+//! operation.
+
+use std::future::Future;
+use std::time::Duration;
+
+use crate::{LicenseClient, LicenseHandle, LicenseStatus, Result};
+
+/// What an instrumented operation is about; fields that don't apply are `None`
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+pub(crate) struct Subject<'a> {
+    pub(crate) tool: Option<&'a str>,
+    pub(crate) user: Option<&'a str>,
+    pub(crate) id: Option<&'a str>,
+}
+
+impl<'a> Subject<'a> {
+    /// A waiting borrow by `user`, for `tool` unless it picks among several
+    pub(crate) fn waiting(tool: Option<&'a str>, user: &'a str) -> Self {
+        Self {
+            tool,
+            user: Some(user),
+            id: None,
+        }
+    }
+}
+
+/// Results of instrumented operations
+pub(crate) trait Outcome {
+    /// The license the operation produced, recorded on its span
+    #[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+    fn license_id(&self) -> Option<&str> {
+        None
+    }
+    
+    /// The tool a waiting borrow got a seat for, and how long that took
+    #[cfg_attr(not(any(feature = "tracing", feature = "metrics")), allow(dead_code))]
+    fn waited(&self) -> Option<(&str, Duration)> {
+        None
+    }
+}
+
+impl Outcome for LicenseHandle {
+    fn license_id(&self) -> Option<&str> {
+        Some(&self.id)
+    }
+    
+    fn waited(&self) -> Option<(&str, Duration)> {
+        self.grant.as_ref().map(|grant| (self.tool.as_str(), grant.waited))
+    }
+}
+
+impl Outcome for () {}
+impl Outcome for LicenseStatus {}
+impl Outcome for Vec<LicenseStatus> {}
+
+impl LicenseClient {
+    /// Run `operation`, called `name`, in a span and/or with metrics
+    #[cfg_attr(not(any(feature = "tracing", feature = "metrics")), allow(unused_variables))]
+    pub(crate) async fn instrumented<T, F>(&self, name: &'static str, subject: Subject<'_>, operation: F) -> Result<T>
+    where
+        T: Outcome,
+        F: Future<Output = Result<T>>,
+    {
+        #[cfg(feature = "metrics")]
+        let start = std::time::Instant::now();
+        
+        #[cfg(feature = "tracing")]
+        let result = self.traced(name, subject, operation).await;
+        #[cfg(not(feature = "tracing"))]
+        let result = operation.await;
+        
+        #[cfg(feature = "metrics")]
+        {
+            record_metrics(name, subject.tool, result.is_ok(), start.elapsed());
+            if let Some((tool, waited)) = result.as_ref().ok().and_then(Outcome::waited) {
+                record_wait(tool, waited);
+            }
+        }
+        result
+    }
+    
+    #[cfg(feature = "tracing")]
+    async fn traced<T, F>(&self, name: &'static str, subject: Subject<'_>, operation: F) -> Result<T>
+    where
+        T: Outcome,
+        F: Future<Output = Result<T>>,
+    {
+        use tracing::field::Empty;
+        use tracing::Instrument;
+        
+        let span = tracing::info_span!(
+            "license",
+            operation = name,
+            tool = subject.tool,
+            user = Empty,
+            license_id = subject.id,
+            elapsed_ms = Empty,
+            wait_ms = Empty,
+        );
+        if let Some(user) = subject.user {
+            span.record("user", if self.redact_user { "<redacted>" } else { user });
+        }
+        
+        let start = std::time::Instant::now();
+        let result = operation.instrument(span.clone()).await;
+        span.record("elapsed_ms", start.elapsed().as_millis() as u64);
+        match &result {
+            Ok(value) => {
+                if let Some(id) = value.license_id() {
+                    span.record("license_id", id);
+                }
+                if let Some((_, waited)) = value.waited() {
+                    span.record("wait_ms", waited.as_millis() as u64);
+                }
+            }
+            Err(e) => span.in_scope(|| tracing::warn!(error = %e, "license {} failed", name)),
+        }
+        result
+    }
+}
+
+/// Count one `name` operation and record its latency
+#[cfg(feature = "metrics")]
+fn record_metrics(name: &'static str, tool: Option<&str>, success: bool, elapsed: Duration) {
+    let tool = tool.unwrap_or_default().to_string();
+    let outcome = if success { "success" } else { "error" };
+    metrics::counter!(format!("permetix_{}_total", name), "tool" => tool.clone(), "outcome" => outcome).increment(1);
+    metrics::histogram!("permetix_request_duration_seconds", "operation" => name, "tool" => tool)
+        .record(elapsed.as_secs_f64());
+}
+
+/// Record how long a waiting borrow took to get a seat for `tool`
+#[cfg(feature = "metrics")]
+fn record_wait(tool: &str, waited: Duration) {
+    metrics::histogram!("permetix_wait_seconds", "tool" => tool.to_string()).record(waited.as_secs_f64());
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod metrics_tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    
+    use metrics::{
+        Counter, CounterFn, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+    };
+    
+    use super::{record_metrics, record_wait};
+    
+    /// Counters, and how many samples each histogram got, by name and
+    /// labels; gauges are dropped
+    #[derive(Default)]
+    struct Counters(Mutex<HashMap<String, Arc<Count>>>);
+    
+    #[derive(Default)]
+    struct Count(AtomicU64);
+    
+    impl CounterFn for Count {
+        fn increment(&self, value: u64) {
+            self.0.fetch_add(value, Ordering::Relaxed);
+        }
+        
+        fn absolute(&self, value: u64) {
+            self.0.store(value, Ordering::Relaxed);
+        }
+    }
+    
+    impl HistogramFn for Count {
+        fn record(&self, _: f64) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    
+    impl Counters {
+        fn entry(&self, key: &Key) -> Arc<Count> {
+            let labels: Vec<String> = key.labels().map(|label| format!("{}={}", label.key(), label.value())).collect();
+            let name = format!("{}{{{}}}", key.name(), labels.join(","));
+            self.0.lock().unwrap().entry(name).or_default().clone()
+        }
+    }
+    
+    impl Recorder for Counters {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::from_arc(self.entry(key))
+        }
+        
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+        
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::from_arc(self.entry(key))
+        }
+    }
+    
+    #[test]
+    fn test_outcomes_are_counted_by_tool() {
+        let recorder = Counters::default();
+        metrics::with_local_recorder(&recorder, || {
+            record_metrics("borrow", Some("cad_tool"), true, Duration::from_millis(5));
+            record_metrics("borrow", Some("cad_tool"), true, Duration::from_millis(5));
+            record_metrics("borrow", Some("cad_tool"), false, Duration::from_millis(5));
+        });
+        
+        let counters = recorder.0.lock().unwrap();
+        let count = |name: &str| counters[name].0.load(Ordering::Relaxed);
+        assert_eq!(count("permetix_borrow_total{tool=cad_tool,outcome=success}"), 2);
+        assert_eq!(count("permetix_borrow_total{tool=cad_tool,outcome=error}"), 1);
+    }
+    
+    #[test]
+    fn test_waits_are_recorded_by_tool() {
+        let recorder = Counters::default();
+        metrics::with_local_recorder(&recorder, || {
+            record_wait("cad_tool", Duration::from_secs(3));
+            record_wait("cad_tool", Duration::from_secs(1));
+        });
+        
+        let counters = recorder.0.lock().unwrap();
+        assert_eq!(counters["permetix_wait_seconds{tool=cad_tool}"].0.load(Ordering::Relaxed), 2);
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::collections::HashMap;
+    use std::fmt::Debug;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    use crate::LicenseClient;
+    
+    /// Collects the fields recorded on every span
+    #[derive(Default)]
+    struct Recorder {
+        next_id: AtomicU64,
+        spans: Arc<Mutex<HashMap<u64, HashMap<String, String>>>>,
+    }
+    
+    struct Fields<'a>(&'a mut HashMap<String, String>);
+    
+    impl Visit for Fields<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0.insert(field.name().to_string(), format!("{:?}", value));
+        }
+        
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+        
+        fn record_u64(&mut self, field: &Field, value: u64) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+    }
+    
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+            let mut fields = HashMap::new();
+            span.record(&mut Fields(&mut fields));
+            self.spans.lock().unwrap().insert(id, fields);
+            Id::from_u64(id)
+        }
+        
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            if let Some(fields) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+                values.record(&mut Fields(fields));
+            }
+        }
+        
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_and_return_spans() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let recorder = Recorder::default();
+        let spans = recorder.spans.clone();
+        let _guard = tracing::subscriber::set_default(recorder);
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .redact_user_in_traces(true)
+            .build();
+        let license = client.borrow("cad_tool", "alice").await.unwrap();
+        license.return_license().await.unwrap();
+        
+        let spans = spans.lock().unwrap();
+        let operation = |name: &str| {
+            spans
+                .values()
+                .find(|fields| fields.get("operation").map(String::as_str) == Some(name))
+                .unwrap_or_else(|| panic!("no {} span", name))
+                .clone()
+        };
+        for name in ["borrow", "return"] {
+            let fields = operation(name);
+            assert_eq!(fields["tool"], "cad_tool");
+            assert_eq!(fields["user"], "<redacted>");
+            assert_eq!(fields["license_id"], "abc-123");
+            assert!(fields.contains_key("elapsed_ms"));
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_wait_span_records_wait_time() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(409))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let recorder = Recorder::default();
+        let spans = recorder.spans.clone();
+        let _guard = tracing::subscriber::set_default(recorder);
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .poll_interval(Duration::from_millis(10))
+            .build();
+        let license = client.borrow_wait("cad_tool", "alice", Duration::from_secs(5)).await.unwrap();
+        let waited = license.grant_info().unwrap().waited;
+        license.return_license().await.unwrap();
+        
+        let spans = spans.lock().unwrap();
+        let fields = spans
+            .values()
+            .find(|fields| fields.get("operation").map(String::as_str) == Some("wait"))
+            .expect("no wait span");
+        assert_eq!(fields["tool"], "cad_tool");
+        assert_eq!(fields["license_id"], "abc-123");
+        assert_eq!(fields["wait_ms"], (waited.as_millis() as u64).to_string());
+    }
+}
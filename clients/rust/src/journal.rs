@@ -0,0 +1,133 @@
+//! On-disk journal of pending returns, so a crash can't leak a seat
+
+use std::fs;
+use std::path::PathBuf;
+
+use urlencoding::{decode, encode};
+
+use crate::{LicenseClient, LicenseError, Result, ReturnOptions};
+
+const ENTRY_EXTENSION: &str = "return";
+
+impl LicenseClient {
+    fn journal_entry(&self, id: &str) -> Option<PathBuf> {
+        self.return_journal
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.{}", encode(id), ENTRY_EXTENSION)))
+    }
+    
+    /// Record that license `id` is about to be returned
+    ///
+    /// Written via a temporary file and rename, so a crash never leaves a
+    /// half-written entry behind.
+    pub(crate) fn journal_return(&self, id: &str) -> Result<()> {
+        let entry = match self.journal_entry(id) {
+            Some(entry) => entry,
+            None => return Ok(()),
+        };
+        let write = || -> std::io::Result<()> {
+            if let Some(dir) = entry.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let temp = entry.with_extension("tmp");
+            fs::write(&temp, id)?;
+            fs::rename(&temp, &entry)
+        };
+        write().map_err(|e| LicenseError::JournalFailed(e.to_string()))
+    }
+    
+    /// Drop the journal entry for a return the server has settled
+    pub(crate) fn settle_return(&self, id: &str) {
+        if let Some(entry) = self.journal_entry(id) {
+            if let Err(e) = fs::remove_file(&entry) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    eprintln!("Warning: failed to remove return journal entry {}: {}", entry.display(), e);
+                }
+            }
+        }
+    }
+    
+    /// Send the returns that were journaled but never completed
+    ///
+    /// Call this at startup when a return journal is configured (see
+    /// [`return_journal`](crate::LicenseClientBuilder::return_journal)).
+    /// Entries are removed once the server accepts the return, or rejects it
+    /// with a 4xx (e.g. the license already expired); others are kept for
+    /// the next replay.
+    ///
+    /// Returns the number of entries that were settled.
+    ///
+    /// # Errors
+    /// Returns `JournalFailed` if the journal directory can't be read.
+    /// Failures of individual returns are logged and skipped.
+    pub async fn replay_pending_returns(&self) -> Result<usize> {
+        let dir = match &self.return_journal {
+            Some(dir) => dir,
+            None => return Ok(0),
+        };
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(LicenseError::JournalFailed(e.to_string())),
+        };
+        
+        let mut settled = 0;
+        for entry in entries {
+            let path = entry.map_err(|e| LicenseError::JournalFailed(e.to_string()))?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(ENTRY_EXTENSION) {
+                continue;
+            }
+            let id = match path.file_stem().and_then(|stem| stem.to_str()).map(decode) {
+                Some(Ok(id)) => id.into_owned(),
+                _ => {
+                    eprintln!("Warning: skipping unreadable return journal entry {}", path.display());
+                    continue;
+                }
+            };
+            
+            match self.send_return(&id, &ReturnOptions::default()).await {
+                Ok(response) if response.status().is_success() || response.status().is_client_error() => {
+                    self.settle_return(&id);
+                    settled += 1;
+                }
+                Ok(response) => {
+                    eprintln!("Warning: replayed return of {} failed: HTTP {}", id, response.status().as_u16());
+                }
+                Err(e) => eprintln!("Warning: replayed return of {} failed: {}", id, e),
+            }
+        }
+        Ok(settled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_replay_pending_returns() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .and(body_json(serde_json::json!({ "id": "abc/123" })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let dir = std::env::temp_dir().join(format!("license-journal-{}", LicenseClient::generate_idempotency_key()));
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .return_journal(&dir)
+            .build();
+        
+        // A return that was journaled but cut off before completing
+        client.journal_return("abc/123").unwrap();
+        assert_eq!(client.replay_pending_returns().await.unwrap(), 1);
+        assert_eq!(client.replay_pending_returns().await.unwrap(), 0);
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -32,13 +32,104 @@
 //! }
 //! ```
 
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime};
 use thiserror::Error;
 use hmac::{Hmac, Mac};
-use sha2::Sha256;
-use urlencoding::encode;
+use sha2::{Digest, Sha256};
+
+mod api_version;
+mod auto_return;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod builder;
+mod clock;
+mod endpoints;
+mod env;
+mod events;
+mod hedge;
+mod history;
+mod hold_times;
+mod host_lock;
+mod host_semaphore;
+mod idempotency;
+mod instrument;
+mod journal;
+mod managed;
+mod offline;
+mod pool;
+mod prepare;
+mod queue;
+mod raw;
+mod release;
+mod renewal;
+mod reservation;
+mod retry;
+mod return_options;
+mod run;
+mod sharded;
+mod signing;
+mod sse;
+mod stats;
+mod status_cache;
+mod status_filter;
+mod timestamp;
+mod tool;
+mod trace;
+mod usage;
+mod verify;
+mod wait;
+mod watch;
+#[cfg(feature = "ws")]
+mod ws;
+
+pub use auto_return::LicenseGuard;
+pub use builder::LicenseClientBuilder;
+pub use clock::Clock;
+pub use endpoints::{ApiPaths, Endpoints};
+pub use events::{LicenseEvent, LicenseEventKind};
+pub use hedge::HedgePolicy;
+pub use history::UsageSample;
+pub use idempotency::{IdempotencyStore, MemoryIdempotencyStore};
+pub use managed::HeldLicense;
+pub use prepare::SignedBorrow;
+pub use queue::{BorrowFuture, QueueTicket, QueueUpdate, TicketState};
+pub use reqwest::Method;
+pub use reservation::Reservation;
+pub use retry::{RetryBudget, RetryPolicy};
+pub use return_options::ReturnOptions;
+pub use sharded::ShardedLicenseClient;
+pub use signing::{AuthMode, SignContext, SignedHeaders, Signer};
+pub use stats::PoolStats;
+pub use status_filter::StatusFilter;
+pub use timestamp::ServerTimestamp;
+pub use tool::Tool;
+pub use trace::HttpTrace;
+pub use usage::BorrowedLicense;
+pub use wait::{GrantInfo, WaitProgress};
+#[cfg(feature = "ws")]
+pub use ws::WsSession;
+
+use auto_return::PendingReturns;
+use clock::SkewedClock;
+use hedge::Hedger;
+use hold_times::HoldTimes;
+use host_lock::HostLock;
+use host_semaphore::HostSemaphore;
+use idempotency::SharedStore;
+use instrument::Subject;
+use managed::{ManagedHandles, Registration};
+use renewal::Renewer;
+use retry::Retrier;
+use signing::SharedSigner;
+use status_cache::StatusCache;
+use stats::RequestCounters;
 
 /// Custom error type for license operations
 #[derive(Error, Debug)]
@@ -54,43 +145,373 @@ pub enum LicenseError {
     
     #[error("Invalid response: {0}")]
     InvalidResponse(String),
+    
+    #[error("Response body exceeded the {0} byte limit")]
+    ResponseTooLarge(usize),
+    
+    #[error("Borrowing {0} would exceed its commit")]
+    WouldExceedCommit(String),
+    
+    #[error("Borrow denied by policy: {reason}")]
+    PolicyDenied { reason: String },
+    
+    #[error("Request timestamp outside the server's window (server time: {server_time:?})")]
+    TimestampSkew { server_time: Option<chrono::DateTime<chrono::Utc>> },
+    
+    #[error("Failed to take host lock: {0}")]
+    LockFailed(String),
+    
+    #[error("Timed out after {0:?}")]
+    Timeout(Duration),
+    
+    #[error("Reserved seats exhausted for group: {0}")]
+    GroupQuotaExhausted(String),
+    
+    #[error("Server does not support request signing: {0}")]
+    SecurityNotSupported(String),
+    
+    #[error("Server does not support endpoint: {0}")]
+    UnsupportedEndpoint(String),
+    
+    #[error("Return journal error: {0}")]
+    JournalFailed(String),
+    
+    #[error("Connection closed mid-response: {0}")]
+    IncompleteResponse(String),
+    
+    #[error("Failed to run tool process: {0}")]
+    ProcessFailed(String),
+    
+    #[error("Invalid request: {0:?}")]
+    Validation(HashMap<String, String>),
+    
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    
+    #[error("Lease expired for license: {0}")]
+    LeaseExpired(String),
+    
+    #[error("Authentication failed (HTTP {status}): {message}")]
+    AuthenticationFailed { status: u16, message: String },
+    
+    #[error("Invalid configuration: {0}")]
+    Configuration(String),
+    
+    #[error("Failed to sign request: {0}")]
+    SigningError(String),
+    
+    #[error("License {0} is not held (already returned or unknown)")]
+    NotHeld(String),
+    
+    #[error("Reservation {0} has expired")]
+    ReservationExpired(String),
+    
+    #[error("Server does not support API version {0}")]
+    UnsupportedApiVersion(u32),
+    
+    #[error("WebSocket transport failed: {0}")]
+    WebSocket(String),
+}
+
+impl LicenseError {
+    /// Whether retrying the request that produced this error may succeed
+    ///
+    /// True for transport failures such as connection errors, timeouts and
+    /// responses cut off by the server.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            LicenseError::RequestFailed(e) => e.is_connect() || e.is_timeout(),
+            LicenseError::IncompleteResponse(_) => true,
+            _ => false,
+        }
+    }
 }
 
 /// Result type for license operations
 pub type Result<T> = std::result::Result<T, LicenseError>;
 
 /// License status information
+///
+/// Parsed from both the v1 field names and their v2 counterparts
+/// (`name`, `total_seats`, `borrowed_seats`, ...).
 #[derive(Debug, Clone, Deserialize)]
 pub struct LicenseStatus {
+    #[serde(alias = "name")]
     pub tool: String,
+    #[serde(alias = "total_seats")]
     pub total: i32,
+    #[serde(alias = "borrowed_seats")]
     pub borrowed: i32,
+    #[serde(alias = "available_seats")]
     pub available: i32,
-    #[serde(default)]
+    #[serde(default, alias = "commit_seats")]
     pub commit: i32,
-    #[serde(default)]
+    #[serde(default, alias = "max_overage_seats")]
     pub max_overage: i32,
-    #[serde(default)]
+    #[serde(default, alias = "overage_seats")]
     pub overage: i32,
     #[serde(default = "default_true")]
     pub in_commit: bool,
 }
 
+impl LicenseStatus {
+    /// Overage seats that can still be borrowed before `max_overage` is hit
+    pub fn overage_remaining(&self) -> i32 {
+        (self.max_overage - self.overage).max(0)
+    }
+    
+    /// Whether the next borrow would be billed as overage
+    ///
+    /// The server counts a borrow as overage once the committed seats are
+    /// all in use. Tools without overage seats (`max_overage` of 0) never
+    /// incur it, which includes servers that don't report commit figures.
+    pub fn will_incur_overage(&self) -> bool {
+        self.max_overage > 0 && self.borrowed >= self.commit
+    }
+    
+    /// Whether the server reported commit figures at all
+    ///
+    /// Every tool has at least one committed or overage seat, so both being
+    /// 0 means the fields were missing (older servers).
+    pub(crate) fn reports_commit(&self) -> bool {
+        self.commit > 0 || self.max_overage > 0
+    }
+}
+
 fn default_true() -> bool {
     true
 }
 
+/// Structured error details from a non-success response body
+///
+/// Accepts both `{"code": ..., "detail": "..."}` and FastAPI-style
+/// `{"detail": {"code": ...}}` shapes. Missing fields are `None`; bodies
+/// that aren't JSON produce an empty `ErrorBody`.
+#[derive(Debug, Default)]
+struct ErrorBody {
+    code: Option<String>,
+    // `reason` or `message`, falling back to a plain-string `detail`
+    message: Option<String>,
+    // The server's clock, as RFC 3339 or Unix seconds
+    server_time: Option<chrono::DateTime<chrono::Utc>>,
+    // Field-level validation messages from `{"errors": {"field": "message"}}`
+    errors: Option<HashMap<String, String>>,
+}
+
+impl ErrorBody {
+    fn parse(body: &[u8]) -> Self {
+        let value: serde_json::Value = match serde_json::from_slice(body) {
+            Ok(value) => value,
+            Err(_) => return Self::default(),
+        };
+        let detail = value.get("detail");
+        let field = |name: &str| {
+            value
+                .get(name)
+                .or_else(|| detail.and_then(|d| d.get(name)))
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        };
+        
+        Self {
+            code: field("code"),
+            message: field("reason")
+                .or_else(|| field("message"))
+                .or_else(|| detail.and_then(|d| d.as_str()).map(str::to_string)),
+            server_time: value
+                .get("server_time")
+                .or_else(|| detail.and_then(|d| d.get("server_time")))
+                .and_then(|v| match v {
+                    serde_json::Value::String(text) => ServerTimestamp::parse(text).ok().map(|t| t.utc()),
+                    serde_json::Value::Number(secs) => {
+                        chrono::DateTime::from_timestamp(secs.as_f64()? as i64, 0)
+                    }
+                    _ => None,
+                }),
+            errors: value
+                .get("errors")
+                .or_else(|| detail.and_then(|d| d.get("errors")))
+                .and_then(|v| serde_json::from_value(v.clone()).ok()),
+        }
+    }
+    
+    /// Whether the server rejected the request's `X-Timestamp` as out of window
+    ///
+    /// Older servers only say so in the message of a 403 (`Request expired`).
+    fn is_timestamp_skew(&self, status: reqwest::StatusCode) -> bool {
+        self.code.as_deref() == Some("TIMESTAMP_SKEW")
+            || (status.as_u16() == 403
+                && self.message.as_deref().is_some_and(|m| m.contains("Request expired")))
+    }
+}
+
+/// A borrow that is about to be sent, with everything needed to resend it
+#[derive(Debug, Clone)]
+struct PendingBorrow {
+    tool: String,
+    user: String,
+    idempotency_key: String,
+    commit_only: bool,
+    policy_context: Option<HashMap<String, String>>,
+    group: Option<String>,
+    // Sent without security headers after a downgrade
+    unsigned: bool,
+    // Signed with this Unix timestamp instead of the current time
+    timestamp: Option<i64>,
+}
+
+impl PendingBorrow {
+    fn new(tool: impl Into<String>, user: impl Into<String>, idempotency_key: impl Into<String>) -> Self {
+        Self {
+            tool: tool.into(),
+            user: user.into(),
+            idempotency_key: idempotency_key.into(),
+            commit_only: false,
+            policy_context: None,
+            group: None,
+            unsigned: false,
+            timestamp: None,
+        }
+    }
+    
+    /// Reject requests the server would refuse anyway, without a round trip
+    fn validate(&self) -> Result<()> {
+        if self.tool.trim().is_empty() {
+            return Err(LicenseError::InvalidInput("tool must not be empty".to_string()));
+        }
+        if self.user.trim().is_empty() {
+            return Err(LicenseError::InvalidInput("user must not be empty".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// A response read in full
+#[derive(Debug)]
+pub(crate) struct Buffered {
+    status: reqwest::StatusCode,
+    body: Vec<u8>,
+    // The response's `Date` header, for clock skew correction
+    date: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Buffered {
+    /// Turn a non-success status into `HttpError` with the body as text
+    fn error_for_status(self) -> Result<Self> {
+        if self.status.is_success() {
+            return Ok(self);
+        }
+        Err(http_error(self.status, &self.body))
+    }
+    
+    /// Like [`error_for_status`](Self::error_for_status), but a server
+    /// without the route at `url` yields `UnsupportedEndpoint`
+    ///
+    /// FastAPI answers unknown routes with a bare "Not Found", while missing
+    /// resources get a more specific message; some servers answer 501.
+    fn error_for_endpoint(self, url: &str) -> Result<Self> {
+        let route_missing = self.status.as_u16() == 404
+            && ErrorBody::parse(&self.body).message.as_deref() == Some("Not Found");
+        if route_missing || self.status.as_u16() == 501 {
+            return Err(LicenseError::UnsupportedEndpoint(url.to_string()));
+        }
+        self.error_for_status()
+    }
+}
+
+/// Error for a non-success response without a more specific mapping
+///
+/// `401` and `403` become `AuthenticationFailed` (with the server's message
+/// when the body has one), anything else `HttpError` with the body as text.
+fn http_error(status: reqwest::StatusCode, body: &[u8]) -> LicenseError {
+    if matches!(status.as_u16(), 401 | 403) {
+        return LicenseError::AuthenticationFailed {
+            status: status.as_u16(),
+            message: ErrorBody::parse(body)
+                .message
+                .unwrap_or_else(|| String::from_utf8_lossy(body).into_owned()),
+        };
+    }
+    LicenseError::HttpError(status.as_u16(), String::from_utf8_lossy(body).into_owned())
+}
+
+/// The `|`-separated string an HMAC signature is computed over
+///
+/// The signed `fields`, then the timestamp, then the API key when one is
+/// sent, matching the server's validation.
+fn signature_payload(fields: &[&str], timestamp: &str, api_key: Option<&str>) -> String {
+    let mut parts = fields.to_vec();
+    parts.push(timestamp);
+    parts.extend(api_key);
+    parts.join("|")
+}
+
+/// Map an error reading a response body
+///
+/// A body that breaks off (connection reset, fewer bytes than announced)
+/// is reported as `IncompleteResponse` so it can be retried.
+fn body_error(e: reqwest::Error) -> LicenseError {
+    if e.is_body() || e.is_decode() {
+        LicenseError::IncompleteResponse(e.to_string())
+    } else {
+        LicenseError::RequestFailed(e)
+    }
+}
+
+/// Deserialize a buffered JSON body, reporting failures as `InvalidResponse`
+fn parse_json<T: DeserializeOwned>(body: &[u8]) -> Result<T> {
+    serde_json::from_slice(body).map_err(|e| LicenseError::InvalidResponse(e.to_string()))
+}
+
+/// Locks and managed-mode registration held by a borrow until it is granted
+struct BorrowGuards {
+    registration: Option<Registration>,
+    host_lock: Option<HostLock>,
+    host_permit: Option<HostLock>,
+}
+
 /// License handle with RAII semantics
 ///
 /// The license is automatically returned when this handle is dropped.
-#[derive(Debug)]
 pub struct LicenseHandle {
     id: String,
     tool: String,
     user: String,
-    client: Arc<reqwest::Client>,
-    base_url: String,
+    idempotency_key: String,
+    borrowed_at: Option<ServerTimestamp>,
+    expires_at: Option<ServerTimestamp>,
+    acquired: Instant,
+    client: LicenseClient,
     returned: bool,
+    expiry_warning: Option<tokio::task::JoinHandle<()>>,
+    renewer: Option<Renewer>,
+    // Set by `borrow_idempotent`, whose stored key is cleared on return
+    operation: Option<String>,
+    grant: Option<GrantInfo>,
+    host_lock: Option<HostLock>,
+    host_permit: Option<HostLock>,
+    // Present while a managed-mode client tracks this handle
+    registration: Option<Registration>,
+    // Token of the cached grant backing a handle issued while offline
+    offline_grant: Option<String>,
+    // Where to return the license from if the handle is dropped unreturned
+    runtime: Option<tokio::runtime::Handle>,
+    // Session the license was borrowed over, and is returned over
+    #[cfg(feature = "ws")]
+    ws: Option<ws::WsSession>,
+}
+
+impl std::fmt::Debug for LicenseHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LicenseHandle")
+            .field("id", &self.id)
+            .field("tool", &self.tool)
+            .field("user", &self.user)
+            .field("borrowed_at", &self.borrowed_at)
+            .field("expires_at", &self.expires_at)
+            .field("returned", &self.returned)
+            .finish()
+    }
 }
 
 impl LicenseHandle {
@@ -109,44 +530,154 @@ impl LicenseHandle {
         &self.user
     }
     
+    /// Get the idempotency key the license was borrowed with
+    pub fn idempotency_key(&self) -> &str {
+        &self.idempotency_key
+    }
+    
+    /// Get the signed offline grant backing this handle, if any
+    ///
+    /// Only set when the server was unreachable and the borrow was served
+    /// from a cached grant (see
+    /// [`offline_grants`](crate::LicenseClientBuilder::offline_grants)).
+    /// Such handles have a local [`id`](Self::id) the server doesn't know.
+    /// The client doesn't verify the grant's signature; the licensed tool
+    /// must check the token before trusting it.
+    pub fn offline_grant(&self) -> Option<&str> {
+        self.offline_grant.as_deref()
+    }
+    
+    /// Get when the server granted the license, if it said so
+    ///
+    /// Older servers don't send `borrowed_at`; see
+    /// [`borrowed_at_timestamp`](Self::borrowed_at_timestamp) for the value
+    /// as sent.
+    pub fn borrowed_at(&self) -> Option<SystemTime> {
+        self.borrowed_at.as_ref().map(|timestamp| timestamp.utc().into())
+    }
+    
+    /// Get when the lease expires on the server, if it has an expiry
+    ///
+    /// Useful for scheduling a [`renew`](Self::renew) ahead of time.
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.expires_at.as_ref().map(|timestamp| timestamp.utc().into())
+    }
+    
+    /// Get the server's `borrowed_at` timestamp, if it sent a parseable one
+    pub fn borrowed_at_timestamp(&self) -> Option<&ServerTimestamp> {
+        self.borrowed_at.as_ref()
+    }
+    
+    /// Get the server's `expires_at` timestamp, if it sent a parseable one
+    pub fn expires_at_timestamp(&self) -> Option<&ServerTimestamp> {
+        self.expires_at.as_ref()
+    }
+    
+    /// Call `callback` once, `lead` before the lease expires on the server
+    ///
+    /// Gives long-running work a chance to save state, renew or release
+    /// before the seat is reclaimed. The timer runs as a Tokio task and is
+    /// cancelled when the license is returned or the handle dropped;
+    /// registering a new callback replaces the previous one. If the lease is
+    /// already within `lead` of expiry the callback fires immediately.
+    ///
+    /// Returns `false` (and schedules nothing) if the server didn't report
+    /// an expiry for this license.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a Tokio runtime.
+    pub fn on_expiry_warning<F>(&mut self, lead: Duration, callback: F) -> bool
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let expires_at = match &self.expires_at {
+            Some(expires_at) => expires_at.utc(),
+            None => return false,
+        };
+        
+        let delay = (expires_at - chrono::Utc::now())
+            .to_std()
+            .unwrap_or_default()
+            .saturating_sub(lead);
+        let timer = tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            callback();
+        });
+        
+        if let Some(previous) = self.expiry_warning.replace(timer) {
+            previous.abort();
+        }
+        true
+    }
+    
     /// Explicitly return the license
     ///
     /// This is called automatically when the handle is dropped.
-    pub async fn return_license(mut self) -> Result<()> {
-        self.return_impl().await?;
-        self.returned = true;
-        Ok(())
+    pub async fn return_license(self) -> Result<()> {
+        self.return_with(ReturnOptions::default()).await
     }
     
-    async fn return_impl(&self) -> Result<()> {
-        #[derive(Serialize)]
-        struct ReturnRequest {
-            id: String,
+    fn cancel_expiry_warning(&mut self) {
+        if let Some(timer) = self.expiry_warning.take() {
+            timer.abort();
+        }
+    }
+    
+    /// What traces of operations on this license describe
+    fn subject(&self) -> Subject<'_> {
+        Subject {
+            tool: Some(&self.tool),
+            user: Some(&self.user),
+            id: Some(&self.id),
+        }
+    }
+    
+    /// Send the return over the transport the license was borrowed with
+    async fn send_return(&self, options: &ReturnOptions) -> Result<Buffered> {
+        #[cfg(feature = "ws")]
+        if let Some(session) = &self.ws {
+            return session.send_return(&self.id, options).await;
+        }
+        let response = self.client.send_return(&self.id, options).await?;
+        self.client.buffer(response).await
+    }
+    
+    async fn return_impl(&self, options: &ReturnOptions) -> Result<()> {
+        self.client.instrumented("return", self.subject(), self.return_untraced(options)).await
+    }
+    
+    async fn return_untraced(&self, options: &ReturnOptions) -> Result<()> {
+        // The server never issued an offline handle, so there's nothing to return
+        if self.offline_grant.is_some() {
+            return Ok(());
         }
         
-        let url = format!("{}/licenses/return", self.base_url);
-        let response = self.client
-            .post(&url)
-            .json(&ReturnRequest { id: self.id.clone() })
-            .send()
-            .await?;
+        self.client.journal_return(&self.id)?;
+        let response = self.send_return(options).await?;
+        
+        // A 4xx won't succeed on replay either, so only keep 5xx entries
+        if response.status.is_success() || response.status.is_client_error() {
+            self.client.settle_return(&self.id);
+        }
         
-        if !response.status().is_success() {
-            return Err(LicenseError::HttpError(
-                response.status().as_u16(),
-                response.text().await.unwrap_or_default(),
-            ));
+        if !response.status.is_success() {
+            return Err(http_error(response.status, &response.body));
         }
         
+        self.client.hold_times.record(&self.tool, self.acquired.elapsed());
+        self.client.status_cache.invalidate(&self.client.qualified_tool(&self.tool));
+        if let Some(operation) = &self.operation {
+            self.client.idempotency_store.0.remove(operation);
+        }
         Ok(())
     }
 }
 
 impl Drop for LicenseHandle {
     fn drop(&mut self) {
-        if !self.returned {
-            // Note: Can't use async in Drop, would need a runtime handle
-            // In production, you might want to use a separate cleanup task
+        self.cancel_expiry_warning();
+        if !self.returned && !self.spawn_return() {
             eprintln!("Warning: License {} dropped without explicit return", self.id);
         }
     }
@@ -157,18 +688,72 @@ impl Drop for LicenseHandle {
 pub struct LicenseClient {
     client: Arc<reqwest::Client>,
     base_url: String,
-    enable_security: bool,
+    auth: AuthMode,
     api_key: Option<String>,
+    signer: Option<SharedSigner>,
+    clock: Arc<SkewedClock>,
+    correct_clock_skew: bool,
+    tool_prefix: Option<String>,
+    paths: Arc<ApiPaths>,
+    content_type: Option<String>,
+    api_version: Option<u32>,
+    host_lock_dir: Option<PathBuf>,
+    host_semaphore: Option<HostSemaphore>,
+    return_journal: Option<PathBuf>,
+    offline_grants: Option<PathBuf>,
+    security_downgrade: bool,
+    max_response_bytes: usize,
+    poll_interval: Duration,
+    hedger: Option<Arc<Hedger>>,
+    retrier: Option<Arc<Retrier>>,
+    counters: Arc<RequestCounters>,
+    hold_times: Arc<HoldTimes>,
+    status_cache: Arc<StatusCache>,
+    status_ttl: Duration,
+    #[cfg(feature = "tracing")]
+    redact_user: bool,
+    fast_fail: Option<Duration>,
+    managed: Option<Arc<ManagedHandles>>,
+    auto_return: bool,
+    pending_returns: Arc<PendingReturns>,
+    idempotency_store: SharedStore,
+    // Set once the server has shown it enforces `commit_only` itself
+    commit_only_enforced: Arc<AtomicBool>,
+}
+
+// Secrets are redacted so the client can be `dbg!`ed or logged safely
+impl std::fmt::Debug for LicenseClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LicenseClient")
+            .field("base_url", &self.base_url)
+            .field("security_downgrade", &self.security_downgrade)
+            .field("auth", &self.auth)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .field("tool_prefix", &self.tool_prefix)
+            .finish_non_exhaustive()
+    }
 }
 
-// Vendor secret - embedded in the client library binary
-// In production, this would be obfuscated/encrypted
+/// Default cap on response bodies read by the client (4 MiB)
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 4 * 1024 * 1024;
+
+/// Default limit on how long a single request may take
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default delay between attempts of the waiting borrow methods
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Demo vendor credentials, used unless others are configured
+// In production, these would be obfuscated/encrypted
 const VENDOR_SECRET: &str = "techvendor_secret_ecu_2025_demo_xyz789abc123def456";
 const VENDOR_ID: &str = "techvendor";
 
 impl LicenseClient {
     /// Create a new license client with security enabled by default
     ///
+    /// Requests time out after [`DEFAULT_TIMEOUT`]; use
+    /// [`builder`](Self::builder) for other settings.
+    ///
     /// # Arguments
     ///
     /// * `base_url` - Base URL of the license server (e.g., "http://localhost:8000")
@@ -185,44 +770,172 @@ impl LicenseClient {
     pub fn with_security(base_url: impl Into<String>, enable_security: bool) -> Self {
         Self::with_security_and_key(base_url, enable_security, std::env::var("LICENSE_API_KEY").ok())
     }
-
+    
     /// Create a new license client with configurable security and API key
     pub fn with_security_and_key(base_url: impl Into<String>, enable_security: bool, api_key: Option<String>) -> Self {
-        Self {
-            client: Arc::new(reqwest::Client::new()),
-            base_url: base_url.into(),
-            enable_security,
-            api_key,
+        Self::builder(base_url)
+            .enable_security(enable_security)
+            .api_key(api_key)
+            .build()
+    }
+    
+    /// Create a license client signing with the given vendor credentials
+    ///
+    /// Security is enabled; see [`LicenseClientBuilder::credentials`].
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - Base URL of the license server
+    /// * `vendor_id` - Vendor ID, sent as `X-Vendor-ID`
+    /// * `vendor_secret` - Secret keying the HMAC signature
+    pub fn with_credentials(
+        base_url: impl Into<String>,
+        vendor_id: impl Into<String>,
+        vendor_secret: impl Into<String>,
+    ) -> Self {
+        Self::builder(base_url).credentials(vendor_id, vendor_secret).build()
+    }
+    
+    /// Create a license client that sends its requests through `client`
+    ///
+    /// Use this to share an existing connection pool (and its proxy and
+    /// user agent settings) across license clients; see
+    /// [`LicenseClientBuilder::http_client`].
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - Base URL of the license server
+    /// * `client` - HTTP client to send requests with
+    /// * `enable_security` - Whether to enable HMAC signature authentication
+    pub fn with_client(base_url: impl Into<String>, client: reqwest::Client, enable_security: bool) -> Self {
+        Self::builder(base_url)
+            .http_client(client)
+            .enable_security(enable_security)
+            .build()
+    }
+    
+    /// Start building a client with non-default settings
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - Base URL of the license server
+    pub fn builder(base_url: impl Into<String>) -> LicenseClientBuilder {
+        LicenseClientBuilder::new(base_url)
+    }
+    
+    /// The tool name as sent to the server, with the configured prefix applied
+    ///
+    /// Call sites always use bare tool names; the prefix is only added on the
+    /// wire (request bodies, signatures and URLs).
+    pub(crate) fn qualified_tool<'a>(&self, tool: &'a str) -> Cow<'a, str> {
+        match &self.tool_prefix {
+            Some(prefix) => Cow::Owned(format!("{}{}", prefix, tool)),
+            None => Cow::Borrowed(tool),
         }
     }
     
+    /// Stable fingerprint of this client's non-secret configuration
+    ///
+    /// A hex SHA-256 over the base URL, security flag, vendor ID and other
+    /// settings, suitable for logging to spot configuration drift across a
+    /// fleet. Secrets are deliberately left out of the hash input: the vendor
+    /// secret is never included and the API key only contributes whether one
+    /// is set.
+    pub fn config_fingerprint(&self) -> String {
+        let input = format!(
+            "base_url={}\nsecurity={}\nsecurity_downgrade={}\nvendor_id={}\napi_key_set={}\ntool_prefix={}\ncontent_type={}\nmax_response_bytes={}\npoll_interval_ms={}\nhedging={}\nretries={}",
+            self.base_url,
+            self.auth.is_enabled(),
+            self.security_downgrade,
+            self.auth.vendor_id().unwrap_or_default(),
+            self.api_key.is_some(),
+            self.tool_prefix.as_deref().unwrap_or_default(),
+            self.content_type.as_deref().unwrap_or("application/json"),
+            self.max_response_bytes,
+            self.poll_interval.as_millis(),
+            self.hedger.is_some(),
+            self.retrier.is_some(),
+        );
+        hex::encode(Sha256::digest(input.as_bytes()))
+    }
+    
     /// Generate HMAC signature for request authentication
-    fn generate_signature(&self, tool: &str, user: &str, timestamp: &str) -> String {
+    ///
+    /// Covers `fields` (`tool`, `user` for borrows, `id` for returns) and
+    /// the timestamp. An empty vendor secret is refused rather than signed
+    /// with, since the server would reject such a signature anyway.
+    fn generate_signature(&self, fields: &[&str], timestamp: &str) -> Result<String> {
         type HmacSha256 = Hmac<Sha256>;
-        // Include API key in payload when present to match server-side validation
-        let payload = match &self.api_key {
-            Some(k) => format!("{}|{}|{}|{}", tool, user, timestamp, k),
-            None => format!("{}|{}|{}", tool, user, timestamp),
+        let secret = match &self.auth {
+            AuthMode::Hmac { secret, .. } => secret,
+            _ => return Err(LicenseError::SigningError("HMAC signing is not configured".to_string())),
         };
-        let mut mac = HmacSha256::new_from_slice(VENDOR_SECRET.as_bytes())
-            .expect("HMAC can take key of any size");
+        if secret.is_empty() {
+            return Err(LicenseError::SigningError("vendor secret is empty".to_string()));
+        }
+        let payload = signature_payload(fields, timestamp, self.api_key.as_deref());
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+            .map_err(|e| LicenseError::SigningError(e.to_string()))?;
         mac.update(payload.as_bytes());
         
         let result = mac.finalize();
-        hex::encode(result.into_bytes())
+        Ok(hex::encode(result.into_bytes()))
+    }
+    
+    /// Read a response body, refusing to buffer more than `max_response_bytes`
+    ///
+    /// A `Content-Length` above the limit is rejected before any of the body
+    /// is read; otherwise the body is streamed and the limit enforced as the
+    /// chunks arrive.
+    async fn read_body_capped(&self, mut response: reqwest::Response) -> Result<Vec<u8>> {
+        let limit = self.max_response_bytes;
+        
+        if let Some(length) = response.content_length() {
+            if length > limit as u64 {
+                return Err(LicenseError::ResponseTooLarge(limit));
+            }
+        }
+        
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(body_error)? {
+            if body.len() + chunk.len() > limit {
+                return Err(LicenseError::ResponseTooLarge(limit));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        
+        Ok(body)
+    }
+    
+    /// Send requests built by `build`, retrying, and read the whole response
+    ///
+    /// Unlike [`with_retries`](Self::with_retries) around a bare send, this
+    /// also retries responses cut off mid-body.
+    pub(crate) async fn fetch<F>(&self, build: F) -> Result<Buffered>
+    where
+        F: Fn() -> reqwest::RequestBuilder,
+    {
+        self.with_retries(|| async {
+            let response = self.send(build()).await?;
+            self.buffer(response).await
+        })
+        .await
     }
     
-    /// Get current Unix timestamp as string
-    fn get_timestamp() -> String {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_secs()
-            .to_string()
+    /// Read a response's status and body within the configured size limit
+    pub(crate) async fn buffer(&self, response: reqwest::Response) -> Result<Buffered> {
+        let status = response.status();
+        let date = clock::response_date(response.headers());
+        let body = self.read_body_capped(response).await?;
+        Ok(Buffered { status, body, date })
     }
     
     /// Borrow a license for a specific tool
     ///
+    /// A fresh idempotency key is generated for the call and sent as the
+    /// `Idempotency-Key` header, so the server can recognise repeated
+    /// attempts of the same logical borrow.
+    ///
     /// # Arguments
     ///
     /// * `tool` - Tool name (e.g., "cad_tool")
@@ -235,123 +948,1599 @@ impl LicenseClient {
     /// # Errors
     ///
     /// Returns `LicenseError::NoLicensesAvailable` if no licenses are available.
+    ///
+    /// Returns `LicenseError::InvalidInput`, without contacting the server,
+    /// if `tool` or `user` is empty or consists only of whitespace. The
+    /// other borrow methods apply the same rules.
     pub async fn borrow(&self, tool: impl Into<String>, user: impl Into<String>) -> Result<LicenseHandle> {
-        let tool = tool.into();
-        let user = user.into();
-        
-        #[derive(Serialize)]
-        struct BorrowRequest {
-            tool: String,
-            user: String,
-        }
-        
-        #[derive(Deserialize)]
-        struct BorrowResponse {
-            id: String,
-        }
-        
-        let url = format!("{}/licenses/borrow", self.base_url);
-        
-        // Build request with optional security headers
-        let mut request = self.client
-            .post(&url)
-            .json(&BorrowRequest {
-                tool: tool.clone(),
-                user: user.clone(),
-            });
-        
-        // Add security headers if enabled
-        if self.enable_security {
-            let timestamp = Self::get_timestamp();
-            let signature = self.generate_signature(&tool, &user, &timestamp);
-            
-            request = request
-                .header("X-Signature", signature)
-                .header("X-Timestamp", timestamp)
-                .header("X-Vendor-ID", VENDOR_ID);
-
-            // Send API key if available
-            if let Some(k) = &self.api_key {
-                request = request.header("Authorization", format!("Bearer {}", k));
-            }
-        }
-        
-        let response = request.send().await?;
-        
-        let status = response.status();
-        
-        if status.as_u16() == 409 {
-            return Err(LicenseError::NoLicensesAvailable(tool));
-        }
-        
-        if !status.is_success() {
-            return Err(LicenseError::HttpError(
-                status.as_u16(),
-                response.text().await.unwrap_or_default(),
-            ));
-        }
-        
-        let data: BorrowResponse = response.json().await?;
-        
-        Ok(LicenseHandle {
-            id: data.id,
-            tool,
-            user,
-            client: self.client.clone(),
-            base_url: self.base_url.clone(),
-            returned: false,
-        })
+        self.borrow_with_key(tool, user, Self::generate_idempotency_key()).await
     }
     
-    /// Get status for a specific tool
+    /// Borrow a license, passing context for the server's policy rules
+    ///
+    /// `context` is sent as the `policy_context` object of the borrow
+    /// request (e.g. `department`, `project`) for the server to evaluate.
     ///
     /// # Arguments
     ///
     /// * `tool` - Tool name
-    pub async fn get_status(&self, tool: impl Into<String>) -> Result<LicenseStatus> {
-        let tool = tool.into();
-        let encoded_tool = encode(&tool);
-        let url = format!("{}/licenses/{}/status", self.base_url, encoded_tool);
-        
-        let response = self.client.get(&url).send().await?;
-        
-        if !response.status().is_success() {
-            return Err(LicenseError::HttpError(
-                response.status().as_u16(),
-                response.text().await.unwrap_or_default(),
-            ));
-        }
-        
-        let status: LicenseStatus = response.json().await?;
-        Ok(status)
+    /// * `user` - Username
+    /// * `context` - Key/value pairs for policy evaluation
+    ///
+    /// # Errors
+    ///
+    /// Returns `LicenseError::PolicyDenied` with the server's explanation if
+    /// a policy rule rejects the request; otherwise same as
+    /// [`borrow`](Self::borrow).
+    pub async fn borrow_with_context(
+        &self,
+        tool: impl Into<String>,
+        user: impl Into<String>,
+        context: HashMap<String, String>,
+    ) -> Result<LicenseHandle> {
+        let mut pending = PendingBorrow::new(tool, user, Self::generate_idempotency_key());
+        pending.policy_context = Some(context);
+        self.execute_borrow(pending).await
     }
     
-    /// Get status for all tools
-    pub async fn get_all_statuses(&self) -> Result<Vec<LicenseStatus>> {
-        let url = format!("{}/licenses/status", self.base_url);
-        
-        let response = self.client.get(&url).send().await?;
-        
-        if !response.status().is_success() {
-            return Err(LicenseError::HttpError(
-                response.status().as_u16(),
-                response.text().await.unwrap_or_default(),
-            ));
-        }
-        
-        let statuses: Vec<LicenseStatus> = response.json().await?;
-        Ok(statuses)
-    }
-}
+    /// Borrow a license from a group's reserved block of seats
+    ///
+    /// Sends `group_id` so the server grants from the group's reservation
+    /// first; whether it then falls back to the shared pool is decided by
+    /// the server's configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - Group ID the reservation belongs to
+    /// * `tool` - Tool name
+    /// * `user` - Username
+    ///
+    /// # Errors
+    ///
+    /// Returns `LicenseError::GroupQuotaExhausted` if the group's reserved
+    /// seats are used up and it may not spill over; otherwise same as
+    /// [`borrow`](Self::borrow).
+    pub async fn borrow_in_group(
+        &self,
+        group: impl Into<String>,
+        tool: impl Into<String>,
+        user: impl Into<String>,
+    ) -> Result<LicenseHandle> {
+        let mut pending = PendingBorrow::new(tool, user, Self::generate_idempotency_key());
+        pending.group = Some(group.into());
+        self.execute_borrow(pending).await
+    }
+    
+    /// Borrow a license using a caller-supplied idempotency key
+    ///
+    /// Reuse the same key when re-issuing a borrow whose outcome is unknown
+    /// (e.g. after a timeout, or from another process) so the server grants
+    /// at most one seat for it. The key is not recorded in the
+    /// [`idempotency_store`](crate::LicenseClientBuilder::idempotency_store);
+    /// use [`borrow_idempotent`](Self::borrow_idempotent) to have the client
+    /// keep track of it.
+    ///
+    /// # Arguments
+    ///
+    /// * `tool` - Tool name
+    /// * `user` - Username
+    /// * `idempotency_key` - Key identifying this logical borrow
+    pub async fn borrow_with_key(
+        &self,
+        tool: impl Into<String>,
+        user: impl Into<String>,
+        idempotency_key: impl Into<String>,
+    ) -> Result<LicenseHandle> {
+        self.execute_borrow(PendingBorrow::new(tool, user, idempotency_key)).await
+    }
+    
+    /// Borrow the first of several interchangeable tools that has a seat
+    ///
+    /// Tries `tools` in order, one borrow each, and returns the first seat
+    /// granted; `handle.tool()` tells which one. Errors other than
+    /// `NoLicensesAvailable` are returned immediately.
+    ///
+    /// # Arguments
+    ///
+    /// * `tools` - Acceptable tools, most preferred first
+    /// * `user` - Username
+    ///
+    /// # Errors
+    ///
+    /// Returns `LicenseError::NoLicensesAvailable` with the comma-separated
+    /// tool names if every tool is exhausted (or `tools` is empty).
+    pub async fn borrow_first_available(&self, tools: &[&str], user: impl Into<String>) -> Result<LicenseHandle> {
+        let user = user.into();
+        for tool in tools {
+            match self.borrow(*tool, user.clone()).await {
+                Err(LicenseError::NoLicensesAvailable(_)) => continue,
+                result => return result,
+            }
+        }
+        Err(LicenseError::NoLicensesAvailable(tools.join(", ")))
+    }
+    
+    /// Borrow a license only if it stays within the tool's commit
+    ///
+    /// Sends `commit_only: true` so a supporting server refuses atomically
+    /// with `LicenseError::WouldExceedCommit` instead of granting an overage
+    /// seat.
+    ///
+    /// **Warning:** older servers ignore the flag. Until the server has
+    /// confirmed it enforces it (by echoing `commit_only` in a borrow
+    /// response), the client also checks `get_status` before borrowing. That
+    /// check is racy: a concurrent borrow between the status call and the
+    /// borrow can still push the tool into overage. If the status carries no
+    /// commit figures the check can't tell and the borrow goes ahead; see
+    /// [`borrow_within_commit`](Self::borrow_within_commit) to refuse then.
+    pub async fn borrow_commit_only(&self, tool: impl Into<String>, user: impl Into<String>) -> Result<LicenseHandle> {
+        self.borrow_checking_commit(tool, user, false).await
+    }
+    
+    /// Borrow a license only if a committed seat is known to be free
+    ///
+    /// The fail-closed counterpart of
+    /// [`borrow_commit_only`](Self::borrow_commit_only), for batch jobs that
+    /// must never incur overage charges: the borrow is also refused when a
+    /// server that doesn't enforce the flag reports no commit figures, and
+    /// refusals are reported as `LicenseError::NoLicensesAvailable` so they
+    /// can be handled like any other shortage. The status check races other
+    /// borrows in the same way.
+    pub async fn borrow_within_commit(&self, tool: impl Into<String>, user: impl Into<String>) -> Result<LicenseHandle> {
+        match self.borrow_checking_commit(tool, user, true).await {
+            Err(LicenseError::WouldExceedCommit(tool)) => Err(LicenseError::NoLicensesAvailable(tool)),
+            result => result,
+        }
+    }
+    
+    /// Send a `commit_only` borrow, checking the status first until the
+    /// server is known to enforce the flag
+    async fn borrow_checking_commit(
+        &self,
+        tool: impl Into<String>,
+        user: impl Into<String>,
+        require_commit: bool,
+    ) -> Result<LicenseHandle> {
+        let mut pending = PendingBorrow::new(tool, user, Self::generate_idempotency_key());
+        pending.commit_only = true;
+        
+        // A cached status may predate other borrows, so always ask the server
+        if !self.commit_only_enforced.load(Ordering::Relaxed) {
+            let status = self.fetch_status(&pending.tool).await?;
+            if status.will_incur_overage() || (require_commit && !status.reports_commit()) {
+                return Err(LicenseError::WouldExceedCommit(pending.tool));
+            }
+        }
+        
+        self.execute_borrow(pending).await
+    }
+    
+    /// Send a borrow request and turn the response into a handle
+    async fn execute_borrow(&self, pending: PendingBorrow) -> Result<LicenseHandle> {
+        let (tool, user) = (pending.tool.clone(), pending.user.clone());
+        let subject = Subject {
+            tool: Some(&tool),
+            user: Some(&user),
+            id: None,
+        };
+        self.instrumented("borrow", subject, self.execute_borrow_untraced(pending)).await
+    }
+    
+    async fn execute_borrow_untraced(&self, pending: PendingBorrow) -> Result<LicenseHandle> {
+        let guards = self.guard_borrow(&pending).await?;
+        
+        let mut handle = match self.send_borrow(pending.clone()).await {
+            Err(LicenseError::SecurityNotSupported(reason)) if self.security_downgrade && !pending.unsigned => {
+                eprintln!(
+                    "Warning: server does not support request signing ({}); retrying borrow of {} without security headers",
+                    reason, pending.tool
+                );
+                let mut pending = pending;
+                pending.unsigned = true;
+                self.send_borrow(pending).await?
+            }
+            Err(e) if offline::is_unreachable(&e) => match self.offline_handle(&pending) {
+                Some(handle) => handle,
+                None => return Err(e),
+            },
+            result => result?,
+        };
+        self.finish_borrow(&mut handle, guards);
+        Ok(handle)
+    }
+    
+    /// Validate `pending` and take what a borrow holds while it is in flight
+    async fn guard_borrow(&self, pending: &PendingBorrow) -> Result<BorrowGuards> {
+        pending.validate()?;
+        if self.fast_fail(&pending.tool) {
+            return Err(LicenseError::NoLicensesAvailable(pending.tool.clone()));
+        }
+        
+        let registration = self.begin_borrow(&pending.tool, &pending.user);
+        let host_lock = self.acquire_host_lock(&pending.tool).await?;
+        let host_permit = self.acquire_host_permit().await?;
+        Ok(BorrowGuards { registration, host_lock, host_permit })
+    }
+    
+    /// Hand a granted borrow's locks to its handle and start tracking it
+    ///
+    /// The tool's cached status no longer counts the seat, so it is dropped.
+    fn finish_borrow(&self, handle: &mut LicenseHandle, guards: BorrowGuards) {
+        self.status_cache.invalidate(&self.qualified_tool(&handle.tool));
+        handle.host_lock = guards.host_lock;
+        handle.host_permit = guards.host_permit;
+        self.register_handle(handle, guards.registration);
+    }
+    
+    async fn send_borrow(&self, pending: PendingBorrow) -> Result<LicenseHandle> {
+        let response = self
+            .with_retries(|| async {
+                let response = match &self.hedger {
+                    Some(hedger) => self.send_hedged_borrow(hedger, &pending).await?,
+                    None => self.send(self.borrow_request(&pending)?).await?,
+                };
+                self.buffer(response).await
+            })
+            .await?;
+        self.handle_from_borrow_body(response.status, &response.body, pending)
+    }
+    
+    /// Send a return request for a license id
+    async fn send_return(&self, id: &str, options: &ReturnOptions) -> Result<reqwest::Response> {
+        let url = self.endpoints().return_license;
+        let body = options.body(id);
+        self.with_retries(|| async {
+            let mut request = self.json_body(self.client.post(&url), &body);
+            for (name, value) in self.return_headers(id)?.headers {
+                request = request.header(name, value);
+            }
+            self.send(request).await
+        })
+        .await
+    }
+    
+    /// Send a borrow, racing a second identical request if the first is slow
+    ///
+    /// Both requests carry the same idempotency key, so the server treats
+    /// them as one logical borrow. The first successful response wins and
+    /// the other request is dropped (cancelled).
+    async fn send_hedged_borrow(&self, hedger: &Hedger, pending: &PendingBorrow) -> Result<reqwest::Response> {
+        let start = Instant::now();
+        let primary = self.send(self.borrow_request(pending)?);
+        tokio::pin!(primary);
+        
+        let response = tokio::select! {
+            response = &mut primary => response,
+            _ = tokio::time::sleep(hedger.delay()) => {
+                let hedge = match self.borrow_request(pending) {
+                    Ok(request) => self.send(request),
+                    Err(e) => return Err(e),
+                };
+                tokio::pin!(hedge);
+                
+                // Only fall back to the other request if the first one to
+                // finish failed outright
+                tokio::select! {
+                    response = &mut primary => match response {
+                        Ok(response) => Ok(response),
+                        Err(_) => hedge.await,
+                    },
+                    response = &mut hedge => match response {
+                        Ok(response) => Ok(response),
+                        Err(_) => primary.await,
+                    },
+                }
+            }
+        }?;
+        
+        hedger.record(start.elapsed());
+        Ok(response)
+    }
+    
+    /// Generate a new random idempotency key
+    pub fn generate_idempotency_key() -> String {
+        uuid::Uuid::new_v4().to_string()
+    }
+    
+    /// Build a borrow request, including security headers when enabled
+    fn borrow_request(&self, pending: &PendingBorrow) -> Result<reqwest::RequestBuilder> {
+        #[derive(Serialize)]
+        struct BorrowRequest<'a> {
+            tool: &'a str,
+            user: &'a str,
+            #[serde(skip_serializing_if = "std::ops::Not::not")]
+            commit_only: bool,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            policy_context: Option<&'a HashMap<String, String>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            group_id: Option<&'a str>,
+        }
+        
+        let url = self.endpoints().borrow;
+        
+        let tool = self.qualified_tool(&pending.tool);
+        
+        // Build request with optional security headers
+        let request = self.client
+            .post(&url)
+            .header("Idempotency-Key", &pending.idempotency_key);
+        let request = self.json_body(request, &BorrowRequest {
+            tool: &tool,
+            user: &pending.user,
+            commit_only: pending.commit_only,
+            policy_context: pending.policy_context.as_ref(),
+            group_id: pending.group.as_deref(),
+        });
+        
+        if pending.unsigned {
+            return Ok(request);
+        }
+        match pending.timestamp {
+            Some(timestamp) => self.signed_at(request, &tool, &pending.user, timestamp.to_string()),
+            None => self.signed(request, &tool, &pending.user),
+        }
+    }
+    
+    /// Attach `body` as JSON, using the configured content type if any
+    pub(crate) fn json_body<T: Serialize>(
+        &self,
+        request: reqwest::RequestBuilder,
+        body: &T,
+    ) -> reqwest::RequestBuilder {
+        match (&self.content_type, serde_json::to_vec(body)) {
+            (Some(content_type), Ok(bytes)) => request
+                .header(reqwest::header::CONTENT_TYPE, content_type)
+                .body(bytes),
+            // Let reqwest report serialization errors when the request is sent
+            _ => request.json(body),
+        }
+    }
+    
+    /// Add the security headers for a `tool`/`user` request, if enabled
+    ///
+    /// `tool` must already be qualified with the configured prefix, since it
+    /// is part of the signature.
+    pub(crate) fn signed(
+        &self,
+        request: reqwest::RequestBuilder,
+        tool: &str,
+        user: &str,
+    ) -> Result<reqwest::RequestBuilder> {
+        self.signed_at(request, tool, user, self.timestamp()?)
+    }
+    
+    /// Add the bearer token to a request that isn't signed, in bearer mode
+    ///
+    /// HMAC signatures only cover requests for a tool and user, but a
+    /// static token authenticates every request.
+    pub(crate) fn authorized(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.auth {
+            AuthMode::Bearer(token) => request.bearer_auth(token),
+            _ => request,
+        }
+    }
+    
+    /// Add the security headers, signed with `timestamp` (Unix seconds)
+    fn signed_at(
+        &self,
+        mut request: reqwest::RequestBuilder,
+        tool: &str,
+        user: &str,
+        timestamp: String,
+    ) -> Result<reqwest::RequestBuilder> {
+        for (name, value) in self.security_headers(tool, user, timestamp)?.headers {
+            request = request.header(name, value);
+        }
+        Ok(request)
+    }
+    
+    /// The security headers for a `tool`/`user` request, per the auth mode
+    pub(crate) fn security_headers(&self, tool: &str, user: &str, timestamp: String) -> Result<SignedHeaders> {
+        let vendor_id = match &self.auth {
+            AuthMode::None => return Ok(SignedHeaders::new()),
+            AuthMode::Bearer(token) => {
+                return Ok(SignedHeaders::new().header("Authorization", format!("Bearer {}", token)))
+            }
+            AuthMode::Hmac { vendor_id, .. } => vendor_id,
+        };
+        
+        let context = SignContext {
+            tool,
+            user,
+            timestamp: &timestamp,
+            api_key: self.api_key.as_deref(),
+        };
+        match &self.signer {
+            Some(signer) => Ok((signer.0)(&context)),
+            None => self.default_signature(vendor_id, &context),
+        }
+    }
+    
+    /// The built-in HMAC-SHA256 security headers
+    fn default_signature(&self, vendor_id: &str, context: &SignContext) -> Result<SignedHeaders> {
+        self.hmac_headers(vendor_id, &[context.tool, context.user], context.timestamp)
+    }
+    
+    /// `X-Signature` over `fields`, with the timestamp, vendor and API key headers
+    fn hmac_headers(&self, vendor_id: &str, fields: &[&str], timestamp: &str) -> Result<SignedHeaders> {
+        let signature = self.generate_signature(fields, timestamp)?;
+        let signed = SignedHeaders::new()
+            .header("X-Signature", signature)
+            .header("X-Timestamp", timestamp)
+            .header("X-Vendor-ID", vendor_id);
+        
+        // Send API key if available
+        Ok(match &self.api_key {
+            Some(k) => signed.header("Authorization", format!("Bearer {}", k)),
+            None => signed,
+        })
+    }
+    
+    /// The security headers for returning license `id`, per the auth mode
+    ///
+    /// HMAC returns are signed over `id` and the timestamp. A custom
+    /// [`Signer`] only signs tool/user requests, so returns go unsigned
+    /// with one.
+    pub(crate) fn return_headers(&self, id: &str) -> Result<SignedHeaders> {
+        match &self.auth {
+            AuthMode::Hmac { vendor_id, .. } if self.signer.is_none() => {
+                self.hmac_headers(vendor_id, &[id], &self.timestamp()?)
+            }
+            AuthMode::Bearer(token) => Ok(SignedHeaders::new().header("Authorization", format!("Bearer {}", token))),
+            _ => Ok(SignedHeaders::new()),
+        }
+    }
+    
+    /// Map a buffered borrow response body to a `LicenseHandle`
+    pub(crate) fn handle_from_borrow_body(
+        &self,
+        status: reqwest::StatusCode,
+        body: &[u8],
+        pending: PendingBorrow,
+    ) -> Result<LicenseHandle> {
+        #[derive(Deserialize)]
+        struct BorrowResponse {
+            id: String,
+            #[serde(default)]
+            borrowed_at: Option<String>,
+            #[serde(default)]
+            expires_at: Option<String>,
+            #[serde(default)]
+            commit_only: bool,
+            #[serde(default)]
+            offline_grant: Option<offline::OfflineGrant>,
+        }
+        
+        if !status.is_success() {
+            let error = ErrorBody::parse(body);
+            match error.code.as_deref() {
+                Some("WOULD_EXCEED_COMMIT") => return Err(LicenseError::WouldExceedCommit(pending.tool)),
+                Some("GROUP_QUOTA_EXHAUSTED") => {
+                    return Err(LicenseError::GroupQuotaExhausted(pending.group.unwrap_or_default()))
+                }
+                Some("SECURITY_NOT_SUPPORTED") => {
+                    return Err(LicenseError::SecurityNotSupported(error.message.unwrap_or_default()))
+                }
+                Some("POLICY_DENIED") => {
+                    return Err(LicenseError::PolicyDenied {
+                        reason: error.message.unwrap_or_default(),
+                    })
+                }
+                _ if error.is_timestamp_skew(status) => {
+                    return Err(LicenseError::TimestampSkew {
+                        server_time: error.server_time,
+                    })
+                }
+                _ if status.as_u16() == 400 && error.errors.is_some() => {
+                    return Err(LicenseError::Validation(error.errors.unwrap_or_default()))
+                }
+                _ => {}
+            }
+        }
+        
+        if status.as_u16() == 409 {
+            return Err(LicenseError::NoLicensesAvailable(pending.tool));
+        }
+        
+        if !status.is_success() {
+            return Err(http_error(status, body));
+        }
+        
+        let data: BorrowResponse = parse_json(body)?;
+        
+        if pending.commit_only && data.commit_only {
+            self.commit_only_enforced.store(true, Ordering::Relaxed);
+        }
+        if let Some(grant) = data.offline_grant {
+            self.store_offline_grant(&pending, grant);
+        }
+        
+        // The seat is already granted at this point, so a malformed timestamp
+        // must not turn the borrow into an error
+        let parse_timestamp = |field: &str, value: Option<&str>| {
+            value.and_then(|value| {
+                ServerTimestamp::parse(value)
+                    .map_err(|e| eprintln!("Warning: ignoring {} for license {}: {}", field, data.id, e))
+                    .ok()
+            })
+        };
+        let borrowed_at = parse_timestamp("borrowed_at", data.borrowed_at.as_deref());
+        let expires_at = parse_timestamp("expires_at", data.expires_at.as_deref());
+        
+        Ok(LicenseHandle {
+            id: data.id,
+            tool: pending.tool,
+            user: pending.user,
+            idempotency_key: pending.idempotency_key,
+            borrowed_at,
+            expires_at,
+            acquired: Instant::now(),
+            client: self.clone(),
+            returned: false,
+            expiry_warning: None,
+            renewer: None,
+            operation: None,
+            grant: None,
+            host_lock: None,
+            host_permit: None,
+            registration: None,
+            offline_grant: None,
+            runtime: self.drop_runtime(),
+            #[cfg(feature = "ws")]
+            ws: None,
+        })
+    }
+    
+    /// Get status for a specific tool
+    ///
+    /// # Arguments
+    ///
+    /// * `tool` - Tool name
+    pub async fn get_status(&self, tool: impl Into<String>) -> Result<LicenseStatus> {
+        let tool = tool.into();
+        let subject = Subject {
+            tool: Some(&tool),
+            ..Subject::default()
+        };
+        self.instrumented("status", subject, async {
+            if let Some(status) = self.status_cache.get(&self.qualified_tool(&tool), self.status_ttl) {
+                return Ok(status);
+            }
+            self.fetch_status(&tool).await
+        })
+        .await
+    }
+    
+    /// Get `tool`'s status from the server, bypassing (but refreshing) the cache
+    async fn fetch_status(&self, tool: &str) -> Result<LicenseStatus> {
+        let url = self.endpoints().status(tool);
+        let response = self.fetch(|| self.authorized(self.client.get(&url))).await?.error_for_status()?;
+        let status: LicenseStatus = parse_json(&response.body)?;
+        self.status_cache.record(std::slice::from_ref(&status));
+        Ok(status)
+    }
+    
+    /// Get status for all tools
+    pub async fn get_all_statuses(&self) -> Result<Vec<LicenseStatus>> {
+        self.instrumented("all_statuses", Subject::default(), async {
+            if let Some(statuses) = self.status_cache.get_all(self.status_ttl) {
+                return Ok(statuses);
+            }
+            let url = self.endpoints().all_statuses;
+            
+            let response = self.fetch(|| self.authorized(self.client.get(&url))).await?.error_for_status()?;
+            let statuses: Vec<LicenseStatus> = parse_json(&response.body)?;
+            self.status_cache.record_all(&statuses);
+            Ok(statuses)
+        })
+        .await
+    }
+    
+    /// Get status for every tool currently in overage
+    ///
+    /// Returns the full status of each tool with `overage > 0`, so callers
+    /// can see how far past commit each one is.
+    pub async fn tools_in_overage(&self) -> Result<Vec<LicenseStatus>> {
+        let statuses = self.get_all_statuses().await?;
+        Ok(statuses.into_iter().filter(|s| s.overage > 0).collect())
+    }
+    
+    /// Get the number of overage seats currently held across all tools
+    ///
+    /// This is the billable count: multiply by the per-seat overage cost for
+    /// the current spend rate.
+    pub async fn current_overage_seats(&self) -> Result<i32> {
+        let statuses = self.get_all_statuses().await?;
+        Ok(statuses.iter().map(|s| s.overage.max(0)).sum())
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    fn test_client(server: &MockServer) -> LicenseClient {
+        LicenseClient::with_security(server.uri(), false)
+    }
+    
+    async fn mock_borrow(server: &MockServer, response: ResponseTemplate) {
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(response)
+            .mount(server)
+            .await;
+    }
+    
+    async fn mock_return(server: &MockServer, response: ResponseTemplate) {
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(response)
+            .mount(server)
+            .await;
+    }
+    
+    fn borrow_ok(id: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": id,
+            "tool": "cad_tool",
+            "user": "alice",
+            "borrowed_at": "2025-03-01T12:00:00+00:00",
+        }))
+    }
     
     #[tokio::test]
     async fn test_client_creation() {
         let client = LicenseClient::new("http://localhost:8000");
         assert_eq!(client.base_url, "http://localhost:8000");
     }
+    
+    #[tokio::test]
+    async fn test_builder_response_limit() {
+        let client = LicenseClient::new("http://localhost:8000");
+        assert_eq!(client.max_response_bytes, DEFAULT_MAX_RESPONSE_BYTES);
+        
+        let client = LicenseClient::builder("http://localhost:8000")
+            .max_response_bytes(1024)
+            .build();
+        assert_eq!(client.max_response_bytes, 1024);
+    }
+    
+    #[tokio::test]
+    async fn test_with_client_uses_injected_client() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/status"))
+            .and(wiremock::matchers::header("user-agent", "ecu-bench/1.0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let http = reqwest::Client::builder().user_agent("ecu-bench/1.0").build().unwrap();
+        let client = LicenseClient::with_client(server.uri(), http, false);
+        assert!(client.get_all_statuses().await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_builder_timeout_and_default_headers() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/status"))
+            .and(wiremock::matchers::header("x-team", "ecu"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+            .mount(&server)
+            .await;
+        
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-team", "ecu".parse().unwrap());
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .timeout(Duration::from_millis(200))
+            .default_headers(headers)
+            .build();
+        
+        assert!(client.get_all_statuses().await.unwrap().is_empty());
+        let err = client.get_status("cad_tool").await.unwrap_err();
+        assert!(matches!(&err, LicenseError::RequestFailed(e) if e.is_timeout()));
+    }
+    
+    #[tokio::test]
+    async fn test_builder_pool_settings_control_connection_reuse() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        // Keep-alive server counting the connections it accepts
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = connections.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                accepted.fetch_add(1, Ordering::Relaxed);
+                tokio::spawn(async move {
+                    let body = r#"{"tool": "cad_tool", "total": 5, "borrowed": 1, "available": 4}"#;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let mut request = [0u8; 4096];
+                    while matches!(socket.read(&mut request).await, Ok(n) if n > 0) {
+                        if socket.write_all(response.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        let connections_for = |builder: LicenseClientBuilder, pause: Duration| {
+            let connections = connections.clone();
+            async move {
+                let before = connections.load(Ordering::Relaxed);
+                let client = builder.enable_security(false).build();
+                for _ in 0..3 {
+                    client.get_status("cad_tool").await.unwrap();
+                    tokio::time::sleep(pause).await;
+                }
+                connections.load(Ordering::Relaxed) - before
+            }
+        };
+        
+        let builder = || LicenseClient::builder(base_url.clone());
+        assert_eq!(connections_for(builder(), Duration::ZERO).await, 1);
+        assert_eq!(connections_for(builder().pool_max_idle_per_host(0), Duration::ZERO).await, 3);
+        let expiring = builder()
+            .pool_idle_timeout(Duration::from_millis(20))
+            .tcp_keepalive(Duration::from_secs(30));
+        assert_eq!(connections_for(expiring, Duration::from_millis(200)).await, 3);
+    }
+    
+    #[test]
+    fn test_config_fingerprint_excludes_secrets() {
+        let base = LicenseClient::with_security_and_key("http://localhost:8000", true, Some("key-a".into()));
+        let other_key = LicenseClient::with_security_and_key("http://localhost:8000", true, Some("key-b".into()));
+        let other_url = LicenseClient::with_security_and_key("http://license:8000", true, Some("key-a".into()));
+        
+        assert_eq!(base.config_fingerprint(), other_key.config_fingerprint());
+        assert_ne!(base.config_fingerprint(), other_url.config_fingerprint());
+    }
+    
+    #[test]
+    fn test_error_body_code() {
+        let flat = ErrorBody::parse(br#"{"code": "WOULD_EXCEED_COMMIT", "detail": "over commit"}"#);
+        assert_eq!(flat.code.as_deref(), Some("WOULD_EXCEED_COMMIT"));
+        
+        let nested = ErrorBody::parse(br#"{"detail": {"code": "WOULD_EXCEED_COMMIT"}}"#);
+        assert_eq!(nested.code.as_deref(), Some("WOULD_EXCEED_COMMIT"));
+        
+        assert!(ErrorBody::parse(b"Internal Server Error").code.is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_success() {
+        let server = MockServer::start().await;
+        mock_borrow(&server, borrow_ok("abc-123")).await;
+        mock_return(&server, ResponseTemplate::new(200)).await;
+        
+        let license = test_client(&server).borrow("cad_tool", "alice").await.unwrap();
+        assert_eq!(license.id(), "abc-123");
+        assert_eq!(license.tool(), "cad_tool");
+        assert!(license.borrowed_at_timestamp().is_some());
+        assert_eq!(license.borrowed_at(), Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_740_830_400)));
+        assert_eq!(license.expires_at(), None);
+        license.return_license().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_timestamps_parsed_or_ignored() {
+        let server = MockServer::start().await;
+        mock_borrow(&server, ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "abc-123",
+            "borrowed_at": "yesterday",
+            "expires_at": "2025-03-01T14:00:00+02:00",
+        }))).await;
+        mock_return(&server, ResponseTemplate::new(200)).await;
+        
+        // A malformed timestamp doesn't fail the already granted borrow
+        let license = test_client(&server).borrow("cad_tool", "alice").await.unwrap();
+        assert_eq!(license.borrowed_at(), None);
+        assert_eq!(license.expires_at(), Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_740_830_400)));
+        license.return_license().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_expiry_warning_fires_before_expiry_unless_returned() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        
+        let server = MockServer::start().await;
+        let expiring_in = |lease: chrono::Duration| {
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "abc-123",
+                "expires_at": (chrono::Utc::now() + lease).to_rfc3339(),
+            }))
+        };
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(expiring_in(chrono::Duration::seconds(1)))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(expiring_in(chrono::Duration::hours(1)))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        mock_borrow(&server, borrow_ok("abc-123")).await;
+        mock_return(&server, ResponseTemplate::new(200)).await;
+        
+        let client = test_client(&server);
+        let warnings = Arc::new(AtomicUsize::new(0));
+        let warn = || {
+            let warnings = warnings.clone();
+            move || {
+                warnings.fetch_add(1, Ordering::Relaxed);
+            }
+        };
+        
+        let mut license = client.borrow("cad_tool", "alice").await.unwrap();
+        assert!(license.on_expiry_warning(Duration::from_millis(900), warn()));
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(warnings.load(Ordering::Relaxed), 1);
+        license.return_license().await.unwrap();
+        
+        // Returning cancels the timer
+        let mut license = client.borrow("cad_tool", "alice").await.unwrap();
+        assert!(license.on_expiry_warning(Duration::from_secs(3599), warn()));
+        license.return_license().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(1200)).await;
+        assert_eq!(warnings.load(Ordering::Relaxed), 1);
+        
+        // Nothing to warn about without an expiry
+        let mut license = client.borrow("cad_tool", "alice").await.unwrap();
+        assert!(!license.on_expiry_warning(Duration::from_secs(1), warn()));
+        license.return_license().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_409_maps_to_no_licenses() {
+        let server = MockServer::start().await;
+        mock_borrow(&server, ResponseTemplate::new(409).set_body_json(serde_json::json!({
+            "detail": "No licenses available for cad_tool"
+        }))).await;
+        
+        let err = test_client(&server).borrow("cad_tool", "alice").await.unwrap_err();
+        assert!(matches!(err, LicenseError::NoLicensesAvailable(tool) if tool == "cad_tool"));
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_500_maps_to_http_error() {
+        let server = MockServer::start().await;
+        mock_borrow(&server, ResponseTemplate::new(500).set_body_string("boom")).await;
+        
+        let err = test_client(&server).borrow("cad_tool", "alice").await.unwrap_err();
+        assert!(matches!(err, LicenseError::HttpError(500, body) if body == "boom"));
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_rejects_blank_input_locally() {
+        let server = MockServer::start().await;
+        mock_borrow(&server, borrow_ok("abc-123")).await;
+        
+        let client = test_client(&server);
+        assert!(matches!(client.borrow("", "alice").await, Err(LicenseError::InvalidInput(_))));
+        assert!(matches!(client.borrow("cad_tool", " \t").await, Err(LicenseError::InvalidInput(_))));
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_400_validation_errors() {
+        let server = MockServer::start().await;
+        mock_borrow(&server, ResponseTemplate::new(400).set_body_json(serde_json::json!({
+            "errors": { "tool": "tool must not be empty" }
+        }))).await;
+        
+        let err = test_client(&server).borrow("cad_tool", "alice").await.unwrap_err();
+        assert!(matches!(err, LicenseError::Validation(errors) if errors["tool"] == "tool must not be empty"));
+        
+        // Any other 400 body stays an HttpError
+        server.reset().await;
+        mock_borrow(&server, ResponseTemplate::new(400).set_body_string("bad request")).await;
+        let err = test_client(&server).borrow("cad_tool", "alice").await.unwrap_err();
+        assert!(matches!(err, LicenseError::HttpError(400, _)));
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_malformed_body_maps_to_invalid_response() {
+        let server = MockServer::start().await;
+        mock_borrow(&server, ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "license": "abc-123"
+        }))).await;
+        
+        let err = test_client(&server).borrow("cad_tool", "alice").await.unwrap_err();
+        assert!(matches!(err, LicenseError::InvalidResponse(_)));
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_empty_body_maps_to_invalid_response() {
+        let server = MockServer::start().await;
+        mock_borrow(&server, ResponseTemplate::new(200)).await;
+        
+        let err = test_client(&server).borrow("cad_tool", "alice").await.unwrap_err();
+        assert!(matches!(err, LicenseError::InvalidResponse(_)));
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_html_body_maps_to_invalid_response() {
+        let server = MockServer::start().await;
+        mock_borrow(&server, ResponseTemplate::new(200).set_body_raw("<html>proxy login</html>", "text/html")).await;
+        
+        let err = test_client(&server).borrow("cad_tool", "alice").await.unwrap_err();
+        assert!(matches!(err, LicenseError::InvalidResponse(_)));
+    }
+    
+    #[tokio::test]
+    async fn test_return_errors_map_to_http_error() {
+        let server = MockServer::start().await;
+        mock_borrow(&server, borrow_ok("abc-123")).await;
+        mock_return(&server, ResponseTemplate::new(404).set_body_json(serde_json::json!({
+            "detail": "Borrow record not found"
+        }))).await;
+        
+        let license = test_client(&server).borrow("cad_tool", "alice").await.unwrap();
+        let err = license.return_license().await.unwrap_err();
+        assert!(matches!(err, LicenseError::HttpError(404, body) if body.contains("Borrow record not found")));
+    }
+    
+    #[tokio::test]
+    async fn test_get_status_mappings() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tool": "cad_tool", "total": 5, "borrowed": 2, "available": 3
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/missing/status"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({
+                "detail": "Tool not found"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/empty/status"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let client = test_client(&server);
+        
+        let status = client.get_status("cad_tool").await.unwrap();
+        assert_eq!(status.available, 3);
+        assert!(status.in_commit);
+        
+        let err = client.get_status("missing").await.unwrap_err();
+        assert!(matches!(err, LicenseError::HttpError(404, _)));
+        
+        let err = client.get_status("empty").await.unwrap_err();
+        assert!(matches!(err, LicenseError::InvalidResponse(_)));
+    }
+    
+    #[tokio::test]
+    async fn test_api_version_negotiation() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .and(header("accept", "application/vnd.permetix.v2+json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": "cad_tool", "total_seats": 5, "borrowed_seats": 4, "available_seats": 1,
+                "commit_seats": 3, "max_overage_seats": 2, "overage_seats": 1, "in_commit": false
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .and(header("accept", "application/vnd.permetix.v3+json"))
+            .respond_with(ResponseTemplate::new(406))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tool": "cad_tool", "total": 5, "borrowed": 4, "available": 1,
+                "commit": 3, "max_overage": 2, "overage": 1, "in_commit": false
+            })))
+            .mount(&server)
+            .await;
+        
+        let v1 = test_client(&server).get_status("cad_tool").await.unwrap();
+        let v2 = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .api_version(2)
+            .build()
+            .get_status("cad_tool")
+            .await
+            .unwrap();
+        for status in [v1, v2] {
+            assert_eq!(status.tool, "cad_tool");
+            assert_eq!((status.total, status.borrowed, status.available), (5, 4, 1));
+            assert_eq!((status.commit, status.max_overage, status.overage), (3, 2, 1));
+            assert!(!status.in_commit);
+        }
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).api_version(3).build();
+        let err = client.get_status("cad_tool").await.unwrap_err();
+        assert!(matches!(err, LicenseError::UnsupportedApiVersion(3)));
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_within_commit_refuses_overage() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tool": "cad_tool", "total": 5, "borrowed": 2, "available": 3,
+                "commit": 2, "max_overage": 3, "overage": 0
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(borrow_ok("abc-123"))
+            .expect(0)
+            .mount(&server)
+            .await;
+        
+        let client = test_client(&server);
+        let result = client.borrow_within_commit("cad_tool", "batch").await;
+        assert!(matches!(result, Err(LicenseError::NoLicensesAvailable(tool)) if tool == "cad_tool"));
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_within_commit_refuses_unknown_commit() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tool": "cad_tool", "total": 5, "borrowed": 0, "available": 5
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(borrow_ok("abc-123"))
+            .expect(0)
+            .mount(&server)
+            .await;
+        
+        let client = test_client(&server);
+        let result = client.borrow_within_commit("cad_tool", "batch").await;
+        assert!(matches!(result, Err(LicenseError::NoLicensesAvailable(tool)) if tool == "cad_tool"));
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_commit_only_against_v1_and_enforcing_servers() {
+        use wiremock::matchers::body_partial_json;
+        
+        // A v1 status has no commit, so the client-side check lets it through
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tool": "cad_tool", "total": 5, "borrowed": 5, "available": 0
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .and(body_partial_json(serde_json::json!({ "commit_only": true })))
+            .respond_with(borrow_ok("abc-123"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(409).set_body_json(serde_json::json!({
+                "detail": { "code": "WOULD_EXCEED_COMMIT" }
+            })))
+            .mount(&server)
+            .await;
+        mock_return(&server, ResponseTemplate::new(200)).await;
+        
+        let client = test_client(&server);
+        let license = client.borrow_commit_only("cad_tool", "batch").await.unwrap();
+        license.return_license().await.unwrap();
+        
+        // The server's own refusal maps to WouldExceedCommit
+        let result = client.borrow_commit_only("cad_tool", "batch").await;
+        assert!(matches!(result, Err(LicenseError::WouldExceedCommit(tool)) if tool == "cad_tool"));
+    }
+    
+    #[test]
+    fn test_overage_helpers() {
+        let status: LicenseStatus = serde_json::from_value(serde_json::json!({
+            "tool": "cad_tool", "total": 5, "borrowed": 3, "available": 2,
+            "commit": 2, "max_overage": 3, "overage": 1, "in_commit": false
+        }))
+        .unwrap();
+        assert_eq!(status.overage_remaining(), 2);
+        assert!(status.will_incur_overage());
+        
+        let status = LicenseStatus { borrowed: 1, overage: 0, ..status };
+        assert_eq!(status.overage_remaining(), 3);
+        assert!(!status.will_incur_overage());
+        
+        // v1 servers don't report commit figures, which never means overage
+        let status: LicenseStatus = serde_json::from_value(serde_json::json!({
+            "tool": "cad_tool", "total": 5, "borrowed": 5, "available": 0
+        }))
+        .unwrap();
+        assert_eq!((status.commit, status.max_overage), (0, 0));
+        assert!(!status.will_incur_overage());
+    }
+    
+    #[tokio::test]
+    async fn test_tool_prefix_applied_on_the_wire() {
+        use wiremock::matchers::body_partial_json;
+        
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .and(body_partial_json(serde_json::json!({ "tool": "team-a/cad_tool" })))
+            .respond_with(borrow_ok("abc-123"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/team-a%2Fcad_tool/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tool": "team-a/cad_tool", "total": 5, "borrowed": 1, "available": 4
+            })))
+            .mount(&server)
+            .await;
+        mock_return(&server, ResponseTemplate::new(200)).await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .tool_prefix("team-a/")
+            .build();
+        
+        let status = client.get_status("cad_tool").await.unwrap();
+        assert_eq!(status.available, 4);
+        
+        let license = client.borrow("cad_tool", "alice").await.unwrap();
+        assert_eq!(license.tool(), "cad_tool");
+        license.return_license().await.unwrap();
+    }
+    
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let client = LicenseClient::builder("http://localhost:8000")
+            .api_key(Some("super-secret-key".to_string()))
+            .build();
+        let debug = format!("{:?}", client);
+        
+        assert!(debug.contains("http://localhost:8000"));
+        assert!(debug.contains(VENDOR_ID));
+        assert!(debug.contains("<redacted>"));
+        assert!(!debug.contains("super-secret-key"));
+        assert!(!debug.contains(VENDOR_SECRET));
+    }
+    
+    #[test]
+    fn test_builder_debug_redacts_secrets() {
+        let builder = LicenseClient::builder("http://localhost:8000")
+            .api_key(Some("super-secret-key".to_string()))
+            .credentials("acme", "hmac-secret");
+        let debug = format!("{:?}", builder);
+        assert!(debug.contains("acme"));
+        assert!(!debug.contains("super-secret-key"));
+        assert!(!debug.contains("hmac-secret"));
+        
+        let debug = format!("{:?}", builder.auth(AuthMode::Bearer("bearer-secret".to_string())));
+        assert!(debug.contains("<redacted>"));
+        assert!(!debug.contains("bearer-secret"));
+    }
+    
+    #[test]
+    fn test_signature_uses_configured_credentials() {
+        let demo = LicenseClient::builder("http://localhost:8000").api_key(None).build();
+        let custom = LicenseClient::builder("http://localhost:8000")
+            .api_key(None)
+            .credentials("othervendor", "other_secret")
+            .build();
+        
+        assert_ne!(
+            demo.generate_signature(&["cad_tool", "alice"], "1700000000").unwrap(),
+            custom.generate_signature(&["cad_tool", "alice"], "1700000000").unwrap()
+        );
+        let headers = custom.security_headers("cad_tool", "alice", "1700000000".to_string()).unwrap().headers;
+        assert!(headers.contains(&("X-Vendor-ID".to_string(), "othervendor".to_string())));
+    }
+    
+    #[tokio::test]
+    async fn test_return_is_signed_over_id() {
+        use wiremock::matchers::header;
+        
+        let client = LicenseClient::builder("http://localhost:8000")
+            .api_key(None)
+            .credentials("acme", "secret")
+            .clock(Arc::new(|| SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)))
+            .build();
+        let signature = client.generate_signature(&["abc-123"], "1700000000").unwrap();
+        
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(borrow_ok("abc-123"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .and(header("x-signature", signature.as_str()))
+            .and(header("x-timestamp", "1700000000"))
+            .and(header("x-vendor-id", "acme"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .api_key(None)
+            .credentials("acme", "secret")
+            .clock(Arc::new(|| SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)))
+            .build();
+        client.borrow("cad_tool", "alice").await.unwrap().return_license().await.unwrap();
+        
+        // Without security, returns stay unsigned
+        mock_return(&server, ResponseTemplate::new(200)).await;
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        client.borrow("cad_tool", "alice").await.unwrap().return_license().await.unwrap();
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests.last().unwrap().headers.get("x-signature").is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_empty_secret_fails_cleanly() {
+        let server = MockServer::start().await;
+        let client = LicenseClient::builder(server.uri()).credentials("acme", "").build();
+        
+        let result = client.borrow("cad_tool", "alice").await;
+        assert!(matches!(result, Err(LicenseError::SigningError(_))));
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_custom_content_type_for_borrow_and_return() {
+        use wiremock::matchers::{body_json, header};
+        
+        const CONTENT_TYPE: &str = "application/vnd.license.v2+json";
+        
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .and(header("content-type", CONTENT_TYPE))
+            .and(body_json(serde_json::json!({ "tool": "cad_tool", "user": "alice" })))
+            .respond_with(borrow_ok("abc-123"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .and(header("content-type", CONTENT_TYPE))
+            .and(body_json(serde_json::json!({ "id": "abc-123" })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .content_type(CONTENT_TYPE)
+            .build();
+        
+        let license = client.borrow("cad_tool", "alice").await.unwrap();
+        license.return_license().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_current_overage_seats_sums_all_tools() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "tool": "cad_tool", "total": 10, "borrowed": 10, "available": 0, "commit": 7, "overage": 3 },
+                { "tool": "ide_tool", "total": 5, "borrowed": 2, "available": 3, "commit": 4, "overage": 0 },
+                { "tool": "sim_tool", "total": 4, "borrowed": 4, "available": 0, "commit": 3, "overage": 1 }
+            ])))
+            .mount(&server)
+            .await;
+        
+        let client = test_client(&server);
+        assert_eq!(client.current_overage_seats().await.unwrap(), 4);
+    }
+    
+    #[tokio::test]
+    async fn test_tools_in_overage() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "tool": "cad_tool", "total": 10, "borrowed": 10, "available": 0, "commit": 7, "overage": 3 },
+                { "tool": "ide_tool", "total": 5, "borrowed": 2, "available": 3, "commit": 4, "overage": 0 }
+            ])))
+            .mount(&server)
+            .await;
+        
+        let overage = test_client(&server).tools_in_overage().await.unwrap();
+        assert_eq!(overage.len(), 1);
+        assert_eq!((overage[0].tool.as_str(), overage[0].overage), ("cad_tool", 3));
+        
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/status"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("boom"))
+            .mount(&server)
+            .await;
+        let err = test_client(&server).tools_in_overage().await.unwrap_err();
+        assert!(matches!(err, LicenseError::HttpError(500, body) if body == "boom"));
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_with_context_policy_denied() {
+        use wiremock::matchers::body_partial_json;
+        
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .and(body_partial_json(serde_json::json!({ "policy_context": { "department": "finance" } })))
+            .respond_with(ResponseTemplate::new(403).set_body_json(serde_json::json!({
+                "detail": { "code": "POLICY_DENIED", "reason": "department not allowed" }
+            })))
+            .mount(&server)
+            .await;
+        
+        let client = test_client(&server);
+        let context = HashMap::from([("department".to_string(), "finance".to_string())]);
+        let result = client.borrow_with_context("cad_tool", "alice", context).await;
+        
+        match result {
+            Err(LicenseError::PolicyDenied { reason }) => assert_eq!(reason, "department not allowed"),
+            other => panic!("expected PolicyDenied, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_error_body_timestamp_skew() {
+        let forbidden = reqwest::StatusCode::FORBIDDEN;
+        
+        let coded = ErrorBody::parse(br#"{"code": "TIMESTAMP_SKEW", "server_time": "2025-01-01T12:00:00Z"}"#);
+        assert!(coded.is_timestamp_skew(forbidden));
+        assert_eq!(coded.server_time.unwrap().to_rfc3339(), "2025-01-01T12:00:00+00:00");
+        
+        let epoch = ErrorBody::parse(br#"{"detail": {"code": "TIMESTAMP_SKEW", "server_time": 1735732800}}"#);
+        assert_eq!(epoch.server_time, coded.server_time);
+        
+        let legacy = ErrorBody::parse(
+            br#"{"detail": "Security validation failed: Request expired (timestamp difference: 412s)"}"#,
+        );
+        assert!(legacy.is_timestamp_skew(forbidden));
+        assert!(legacy.server_time.is_none());
+        
+        let other = ErrorBody::parse(br#"{"detail": "Security validation failed: Invalid signature"}"#);
+        assert!(!other.is_timestamp_skew(forbidden));
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_timestamp_skew() {
+        let server = MockServer::start().await;
+        mock_borrow(&server, ResponseTemplate::new(403).set_body_json(serde_json::json!({
+            "detail": "Security validation failed: Request expired (timestamp difference: 412s)"
+        }))).await;
+        
+        let result = test_client(&server).borrow("cad_tool", "alice").await;
+        assert!(matches!(result, Err(LicenseError::TimestampSkew { server_time: None })));
+    }
+    
+    #[tokio::test]
+    async fn test_auth_rejections_map_to_authentication_failed() {
+        let server = MockServer::start().await;
+        mock_borrow(&server, ResponseTemplate::new(403).set_body_json(serde_json::json!({
+            "detail": "Security validation failed: Invalid signature"
+        }))).await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("Missing API key"))
+            .mount(&server)
+            .await;
+        
+        let client = test_client(&server);
+        let result = client.borrow("cad_tool", "alice").await;
+        assert!(matches!(
+            result,
+            Err(LicenseError::AuthenticationFailed { status: 403, message })
+                if message == "Security validation failed: Invalid signature"
+        ));
+        
+        let result = client.get_status("cad_tool").await;
+        assert!(matches!(result, Err(LicenseError::AuthenticationFailed { status: 401, .. })));
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_first_available_skips_exhausted_tools() {
+        use wiremock::matchers::body_partial_json;
+        
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .and(body_partial_json(serde_json::json!({ "tool": "cad_tool" })))
+            .respond_with(ResponseTemplate::new(409))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .and(body_partial_json(serde_json::json!({ "tool": "ide_tool" })))
+            .respond_with(borrow_ok("abc-123"))
+            .mount(&server)
+            .await;
+        mock_return(&server, ResponseTemplate::new(200)).await;
+        
+        let client = test_client(&server);
+        let license = client
+            .borrow_first_available(&["cad_tool", "ide_tool"], "alice")
+            .await
+            .unwrap();
+        assert_eq!(license.tool(), "ide_tool");
+        license.return_license().await.unwrap();
+        
+        let result = client.borrow_first_available(&["cad_tool"], "alice").await;
+        assert!(matches!(result, Err(LicenseError::NoLicensesAvailable(tool)) if tool == "cad_tool"));
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_in_group() {
+        use wiremock::matchers::body_partial_json;
+        
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .and(body_partial_json(serde_json::json!({ "group_id": "team-a" })))
+            .respond_with(borrow_ok("abc-123"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .and(body_partial_json(serde_json::json!({ "group_id": "team-b" })))
+            .respond_with(ResponseTemplate::new(409).set_body_json(serde_json::json!({
+                "detail": { "code": "GROUP_QUOTA_EXHAUSTED" }
+            })))
+            .mount(&server)
+            .await;
+        mock_return(&server, ResponseTemplate::new(200)).await;
+        
+        let client = test_client(&server);
+        let license = client.borrow_in_group("team-a", "cad_tool", "alice").await.unwrap();
+        license.return_license().await.unwrap();
+        
+        let result = client.borrow_in_group("team-b", "cad_tool", "bob").await;
+        assert!(matches!(result, Err(LicenseError::GroupQuotaExhausted(group)) if group == "team-b"));
+    }
+    
+    #[tokio::test]
+    async fn test_security_downgrade_retries_unsigned() {
+        use wiremock::matchers::header_exists;
+        
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .and(header_exists("x-signature"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(serde_json::json!({
+                "code": "SECURITY_NOT_SUPPORTED", "message": "signatures are not supported"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(borrow_ok("abc-123"))
+            .mount(&server)
+            .await;
+        mock_return(&server, ResponseTemplate::new(200)).await;
+        
+        let strict = LicenseClient::builder(server.uri()).build();
+        let result = strict.borrow("cad_tool", "alice").await;
+        assert!(matches!(result, Err(LicenseError::SecurityNotSupported(_))));
+        
+        let lenient = LicenseClient::builder(server.uri())
+            .allow_security_downgrade(true)
+            .build();
+        let license = lenient.borrow("cad_tool", "alice").await.unwrap();
+        license.return_license().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_truncated_response_is_incomplete_and_retried() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        let body = r#"{"tool": "cad_tool", "total": 5, "borrowed": 1, "available": 4}"#;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        
+        // The first two responses are cut off after a few bytes of the body
+        tokio::spawn(async move {
+            for connection in 0.. {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = [0u8; 4096];
+                let _ = socket.read(&mut request).await;
+                let sent = if connection < 2 { &body[..10] } else { body };
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    sent
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        
+        let client = LicenseClient::builder(base_url.clone()).enable_security(false).build();
+        let result = client.get_status("cad_tool").await;
+        assert!(matches!(result, Err(LicenseError::IncompleteResponse(_))));
+        
+        let retrying = LicenseClient::builder(base_url)
+            .enable_security(false)
+            .retry_policy(RetryPolicy::default())
+            .build();
+        assert_eq!(retrying.get_status("cad_tool").await.unwrap().available, 4);
+    }
+    
+    #[tokio::test]
+    async fn test_http10_response_without_content_length_is_read_to_eof() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        
+        // No Content-Length and no chunking: the body ends when the connection closes
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = [0u8; 4096];
+                let n = socket.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..n]);
+                let body = if request.starts_with("POST /licenses/borrow") {
+                    r#"{"id": "abc-123"}"#.to_string()
+                } else if request.starts_with("GET /licenses/big_tool/status") {
+                    format!(r#"{{"tool": "big_tool", "padding": "{}"}}"#, "x".repeat(4096))
+                } else if request.starts_with("GET") {
+                    r#"{"tool": "cad_tool", "total": 5, "borrowed": 1, "available": 4}"#.to_string()
+                } else {
+                    String::new()
+                };
+                let response = format!("HTTP/1.0 200 OK\r\ncontent-type: application/json\r\n\r\n{}", body);
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+        
+        let client = LicenseClient::builder(base_url)
+            .enable_security(false)
+            .max_response_bytes(1024)
+            .build();
+        assert_eq!(client.get_status("cad_tool").await.unwrap().available, 4);
+        
+        let license = client.borrow("cad_tool", "alice").await.unwrap();
+        assert_eq!(license.id(), "abc-123");
+        license.return_license().await.unwrap();
+        
+        // The size limit still applies without a length to check up front
+        let result = client.get_status("big_tool").await;
+        assert!(matches!(result, Err(LicenseError::ResponseTooLarge(1024))));
+    }
 }
-
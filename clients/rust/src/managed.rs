@@ -0,0 +1,312 @@
+//! Tracking of the borrows a client has in flight or issued (managed mode)
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{LicenseClient, LicenseHandle, Result};
+
+/// A license that has been held longer than the watchdog threshold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeldLicense {
+    /// License ID
+    pub id: String,
+    /// Tool name, as passed to the borrow
+    pub tool: String,
+    /// User holding the seat
+    pub user: String,
+    /// How long the seat has been held so far
+    pub held: Duration,
+}
+
+/// Threshold and callback of the long-hold watchdog
+#[derive(Clone)]
+pub(crate) struct Watchdog {
+    threshold: Duration,
+    callback: Arc<dyn Fn(HeldLicense) + Send + Sync>,
+}
+
+impl Watchdog {
+    pub(crate) fn new(threshold: Duration, callback: Arc<dyn Fn(HeldLicense) + Send + Sync>) -> Self {
+        Self { threshold, callback }
+    }
+    
+    /// How often held handles are checked against the threshold
+    fn interval(&self) -> Duration {
+        (self.threshold / 4).clamp(Duration::from_millis(10), Duration::from_secs(60))
+    }
+}
+
+impl std::fmt::Debug for Watchdog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Watchdog").field("threshold", &self.threshold).finish_non_exhaustive()
+    }
+}
+
+struct Entry {
+    // `id` stays empty while the borrow is in flight
+    license: HeldLicense,
+    granted: bool,
+    since: Instant,
+    // Each handle is only reported once
+    reported: bool,
+}
+
+/// The borrows of a client and its clones that are in flight or still held
+#[derive(Default)]
+pub(crate) struct ManagedHandles {
+    next_key: AtomicU64,
+    entries: Mutex<HashMap<u64, Entry>>,
+    watchdog: Option<Watchdog>,
+    watchdog_started: AtomicBool,
+}
+
+impl std::fmt::Debug for ManagedHandles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ManagedHandles")
+            .field("held", &self.entries.lock().unwrap().len())
+            .field("watchdog", &self.watchdog)
+            .finish()
+    }
+}
+
+/// Removes a borrow from its registry when dropped
+pub(crate) struct Registration {
+    registry: Arc<ManagedHandles>,
+    key: u64,
+}
+
+impl Registration {
+    /// Mark the borrow as granted to `handle`
+    pub(crate) fn granted(&self, handle: &LicenseHandle) {
+        if let Some(entry) = self.registry.entries.lock().unwrap().get_mut(&self.key) {
+            entry.license.id = handle.id.clone();
+            entry.granted = true;
+            entry.since = handle.acquired;
+        }
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.registry.entries.lock().unwrap().remove(&self.key);
+    }
+}
+
+impl ManagedHandles {
+    pub(crate) fn new(watchdog: Option<Watchdog>) -> Self {
+        Self {
+            watchdog,
+            ..Self::default()
+        }
+    }
+    
+    /// Track a borrow of `tool` from now until the registration is dropped
+    pub(crate) fn begin(self: &Arc<Self>, tool: &str, user: &str) -> Registration {
+        let key = self.next_key.fetch_add(1, Ordering::Relaxed);
+        let entry = Entry {
+            license: HeldLicense {
+                id: String::new(),
+                tool: tool.to_string(),
+                user: user.to_string(),
+                held: Duration::ZERO,
+            },
+            granted: false,
+            since: Instant::now(),
+            reported: false,
+        };
+        self.entries.lock().unwrap().insert(key, entry);
+        self.start_watchdog();
+        
+        Registration {
+            registry: self.clone(),
+            key,
+        }
+    }
+    
+    /// Number of borrows of `tool` that have been started but not granted
+    pub(crate) fn in_flight(&self, tool: &str) -> usize {
+        self.entries
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|entry| !entry.granted && entry.license.tool == tool)
+            .count()
+    }
+    
+    /// Spawn the watchdog task on first use, from within the runtime
+    fn start_watchdog(self: &Arc<Self>) {
+        let interval = match &self.watchdog {
+            Some(watchdog) => watchdog.interval(),
+            None => return,
+        };
+        if self.watchdog_started.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        
+        // Held weakly so the task ends once the client and its handles are gone
+        let registry = Arc::downgrade(self);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                match registry.upgrade() {
+                    Some(registry) => registry.check(),
+                    None => break,
+                }
+            }
+        });
+    }
+    
+    fn check(&self) {
+        let watchdog = match &self.watchdog {
+            Some(watchdog) => watchdog,
+            None => return,
+        };
+        
+        let overdue: Vec<HeldLicense> = self
+            .entries
+            .lock()
+            .unwrap()
+            .values_mut()
+            .filter(|entry| entry.granted && !entry.reported && entry.since.elapsed() >= watchdog.threshold)
+            .map(|entry| {
+                entry.reported = true;
+                HeldLicense {
+                    held: entry.since.elapsed(),
+                    ..entry.license.clone()
+                }
+            })
+            .collect();
+        
+        // Called without the lock so the callback may borrow or return
+        for license in overdue {
+            (watchdog.callback)(license);
+        }
+    }
+}
+
+impl LicenseClient {
+    /// Start tracking a borrow if the client runs in managed mode
+    pub(crate) fn begin_borrow(&self, tool: &str, user: &str) -> Option<Registration> {
+        self.managed.as_ref().map(|managed| managed.begin(tool, user))
+    }
+    
+    /// Track `handle` under `registration`, or on its own if there is none
+    pub(crate) fn register_handle(&self, handle: &mut LicenseHandle, registration: Option<Registration>) {
+        let registration = registration.or_else(|| self.begin_borrow(&handle.tool, &handle.user));
+        if let Some(registration) = registration {
+            registration.granted(handle);
+            handle.registration = Some(registration);
+        }
+    }
+    
+    /// Seats of `tool` still free once our own in-flight borrows are granted
+    ///
+    /// The server's `available` count minus the borrows this client (and
+    /// its clones) have started but not yet had granted. Seats already held
+    /// are counted by the server itself. Borrows granted while the status
+    /// request is under way may be subtracted twice, so the result errs on
+    /// the low side, and may be negative.
+    ///
+    /// Only managed-mode clients (see
+    /// [`managed`](crate::LicenseClientBuilder::managed)) track their
+    /// borrows; for others this is the server's `available` count.
+    ///
+    /// # Errors
+    /// Same as [`get_status`](Self::get_status).
+    pub async fn effective_available(&self, tool: impl Into<String>) -> Result<i32> {
+        let tool = tool.into();
+        let status = self.get_status(&tool).await?;
+        let in_flight = self.managed.as_ref().map_or(0, |managed| managed.in_flight(&tool));
+        Ok(status.available - in_flight as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_watchdog_reports_long_held_handles_once() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .watchdog(Duration::from_millis(100), move |license| sink.lock().unwrap().push(license))
+            .build();
+        
+        let stuck = client.borrow("cad_tool", "alice").await.unwrap();
+        let quick = client.borrow("sim_tool", "bob").await.unwrap();
+        quick.return_license().await.unwrap();
+        
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        {
+            let reports = reports.lock().unwrap();
+            assert_eq!(reports.len(), 1);
+            assert_eq!(reports[0].tool, "cad_tool");
+            assert!(reports[0].held >= Duration::from_millis(100));
+        }
+        
+        stuck.return_license().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_effective_available_subtracts_in_flight_borrows() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "id": "abc-123" }))
+                    .set_delay(Duration::from_millis(300)),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tool": "cad_tool", "total": 5, "borrowed": 0, "available": 5
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .managed(true)
+            .build();
+        let borrows: Vec<_> = (0..2)
+            .map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move { client.borrow("cad_tool", "alice").await })
+            })
+            .collect();
+        
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(client.effective_available("cad_tool").await.unwrap(), 3);
+        
+        for borrow in borrows {
+            borrow.await.unwrap().unwrap().return_license().await.unwrap();
+        }
+        assert_eq!(client.effective_available("cad_tool").await.unwrap(), 5);
+    }
+}
@@ -0,0 +1,137 @@
+//! Offline grants cached on disk, for borrows while the server is unreachable
+
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use urlencoding::encode;
+
+use crate::{LicenseClient, LicenseError, LicenseHandle, PendingBorrow, ServerTimestamp};
+
+/// A grant as sent in the `offline_grant` field of a borrow response
+///
+/// The client doesn't check the token's signature; it only passes it on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct OfflineGrant {
+    /// Opaque, server-signed token for the licensed tool to verify
+    pub(crate) token: String,
+    /// When the grant stops being valid (RFC 3339)
+    pub(crate) expires_at: String,
+}
+
+impl LicenseClient {
+    fn offline_grant_path(&self, tool: &str, user: &str) -> Option<PathBuf> {
+        self.offline_grants
+            .as_ref()
+            .map(|dir| dir.join(format!("{}@{}.grant", encode(user), encode(tool))))
+    }
+    
+    /// Cache the offline grant of a successful borrow, if caching is configured
+    pub(crate) fn store_offline_grant(&self, pending: &PendingBorrow, grant: OfflineGrant) {
+        let path = match self.offline_grant_path(&pending.tool, &pending.user) {
+            Some(path) => path,
+            None => return,
+        };
+        let write = || -> std::io::Result<()> {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let temp = path.with_extension("tmp");
+            fs::write(&temp, serde_json::to_vec(&grant)?)?;
+            fs::rename(&temp, &path)
+        };
+        if let Err(e) = write() {
+            eprintln!("Warning: failed to cache offline grant {}: {}", path.display(), e);
+        }
+    }
+    
+    /// A handle backed by the cached grant for `pending`, if one is still valid
+    ///
+    /// The grant is used up, so it backs at most one handle. The handle gets
+    /// a local ID: the license the grant came with may long be returned.
+    pub(crate) fn offline_handle(&self, pending: &PendingBorrow) -> Option<LicenseHandle> {
+        let path = self.offline_grant_path(&pending.tool, &pending.user)?;
+        // Claim the grant by moving it away, so concurrent borrows (in this
+        // or another process) can't both use it
+        let claimed = path.with_extension(format!("claimed-{}", pending.idempotency_key));
+        fs::rename(&path, &claimed).ok()?;
+        let grant = fs::read(&claimed).ok().and_then(|bytes| serde_json::from_slice::<OfflineGrant>(&bytes).ok());
+        let _ = fs::remove_file(&claimed);
+        
+        let grant = grant?;
+        let expires_at = ServerTimestamp::parse(&grant.expires_at).ok()?;
+        if expires_at.utc() <= chrono::Utc::now() {
+            return None;
+        }
+        
+        let id = format!("offline-{}", pending.idempotency_key);
+        let body = serde_json::json!({ "id": id, "expires_at": grant.expires_at });
+        let mut handle = self
+            .handle_from_borrow_body(reqwest::StatusCode::OK, body.to_string().as_bytes(), pending.clone())
+            .ok()?;
+        handle.offline_grant = Some(grant.token);
+        Some(handle)
+    }
+}
+
+/// Whether `error` means the server couldn't be reached at all
+pub(crate) fn is_unreachable(error: &LicenseError) -> bool {
+    matches!(error, LicenseError::RequestFailed(e) if e.is_connect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_borrow_falls_back_to_cached_offline_grant() {
+        let server = MockServer::start().await;
+        let expires_at = (chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "abc-123",
+                "offline_grant": { "token": "signed-token", "expires_at": expires_at }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let dir = std::env::temp_dir().join(format!("license-grants-{}", LicenseClient::generate_idempotency_key()));
+        let online = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .offline_grants(&dir)
+            .build();
+        let license = online.borrow("cad_tool", "alice").await.unwrap();
+        assert_eq!(license.offline_grant(), None);
+        license.return_license().await.unwrap();
+        
+        // Nothing listens on a port that was just released
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let offline = LicenseClient::builder(format!("http://127.0.0.1:{}", port))
+            .enable_security(false)
+            .offline_grants(&dir)
+            .build();
+        
+        let license = offline.borrow("cad_tool", "alice").await.unwrap();
+        assert_eq!(license.id(), format!("offline-{}", license.idempotency_key()));
+        assert_eq!(license.offline_grant(), Some("signed-token"));
+        
+        // The grant was used up by the first offline borrow
+        let result = offline.borrow("cad_tool", "alice").await;
+        assert!(matches!(result, Err(LicenseError::RequestFailed(_))));
+        license.return_license().await.unwrap();
+        
+        // Grants are per tool and user
+        let result = offline.borrow("cad_tool", "bob").await;
+        assert!(matches!(result, Err(LicenseError::RequestFailed(_))));
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
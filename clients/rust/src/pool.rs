@@ -0,0 +1,175 @@
+//! Bulk borrows that acquire a set of seats all-or-nothing
+
+use std::time::Duration;
+
+use crate::{LicenseClient, LicenseError, LicenseHandle, Result};
+
+impl LicenseClient {
+    /// Borrow `count` seats of `tool`, at no more than `rate` borrows/sec
+    ///
+    /// Borrows are sent one after another, each starting no earlier than
+    /// `1 / rate` seconds after the previous one, so warming up a large pool
+    /// doesn't trip the server's rate limits. Transient failures are retried
+    /// according to the client's [retry policy](crate::LicenseClientBuilder::retry_policy).
+    ///
+    /// # Arguments
+    ///
+    /// * `tool` - Tool name
+    /// * `user` - Username
+    /// * `count` - Number of seats to acquire
+    /// * `rate` - Maximum borrows per second
+    ///
+    /// # Errors
+    ///
+    /// Returns `LicenseError::InvalidInput` if `rate` isn't a positive number.
+    /// If any borrow fails, the seats acquired so far are returned and that
+    /// borrow's error is passed on.
+    pub async fn borrow_pool(
+        &self,
+        tool: impl Into<String>,
+        user: impl Into<String>,
+        count: usize,
+        rate: f64,
+    ) -> Result<Vec<LicenseHandle>> {
+        if !(rate > 0.0 && rate.is_finite()) {
+            return Err(LicenseError::InvalidInput(format!("borrow rate must be positive, got {}", rate)));
+        }
+        let (tool, user) = (tool.into(), user.into());
+        let period = Duration::from_secs_f64(1.0 / rate);
+        let start = tokio::time::Instant::now();
+        
+        let mut handles = Vec::with_capacity(count);
+        for i in 0..count {
+            tokio::time::sleep_until(start + period.mul_f64(i as f64)).await;
+            match self.borrow(tool.clone(), user.clone()).await {
+                Ok(handle) => handles.push(handle),
+                Err(e) => {
+                    roll_back(handles).await;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(handles)
+    }
+    
+    /// Borrow several licenses at once, all or nothing
+    ///
+    /// Borrows each `(tool, user)` pair in order. If any borrow fails, the
+    /// licenses acquired so far are returned before the error is passed on,
+    /// so a partial set never stays borrowed on the server.
+    ///
+    /// # Errors
+    ///
+    /// The error of the first borrow that failed.
+    pub async fn borrow_many(&self, requests: &[(&str, &str)]) -> Result<Vec<LicenseHandle>> {
+        let mut handles = Vec::with_capacity(requests.len());
+        for (tool, user) in requests {
+            match self.borrow(*tool, *user).await {
+                Ok(handle) => handles.push(handle),
+                Err(e) => {
+                    roll_back(handles).await;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(handles)
+    }
+}
+
+/// Return the licenses of a bulk borrow that failed part way
+async fn roll_back(handles: Vec<LicenseHandle>) {
+    for handle in handles {
+        let id = handle.id().to_string();
+        if let Err(e) = handle.return_license().await {
+            eprintln!("Warning: failed to roll back license {}: {}", id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_borrow_pool_is_throttled_and_rolls_back() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .up_to_n_times(5)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(409).set_body_json(serde_json::json!({ "detail": "No licenses available" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(5)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        let started = Instant::now();
+        let handles = client.borrow_pool("cad_tool", "batch", 3, 20.0).await.unwrap();
+        assert_eq!(handles.len(), 3);
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        for handle in handles {
+            handle.return_license().await.unwrap();
+        }
+        
+        // Two seats are left, so the third borrow fails and the two are returned
+        let result = client.borrow_pool("cad_tool", "batch", 3, 1000.0).await;
+        assert!(matches!(result, Err(LicenseError::NoLicensesAvailable(_))));
+        
+        let result = client.borrow_pool("cad_tool", "batch", 3, 0.0).await;
+        assert!(matches!(result, Err(LicenseError::InvalidInput(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_many_returns_acquired_handles_on_failure() {
+        use wiremock::matchers::{body_json, body_partial_json};
+        
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .and(body_partial_json(serde_json::json!({ "tool": "cad_tool" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "cad-1" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .and(body_partial_json(serde_json::json!({ "tool": "sim_tool" })))
+            .respond_with(ResponseTemplate::new(409))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .and(body_json(serde_json::json!({ "id": "cad-1" })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        let result = client
+            .borrow_many(&[("cad_tool", "ci"), ("sim_tool", "ci"), ("ide_tool", "ci")])
+            .await;
+        assert!(matches!(result, Err(LicenseError::NoLicensesAvailable(tool)) if tool == "sim_tool"));
+        
+        // The third tool was never requested
+        let borrows = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|request| request.url.path() == "/licenses/borrow")
+            .count();
+        assert_eq!(borrows, 2);
+    }
+}
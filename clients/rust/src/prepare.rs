@@ -0,0 +1,185 @@
+//! Borrow requests signed now and sent later, e.g. through an offline relay
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{LicenseClient, LicenseError, LicenseHandle, PendingBorrow, Result};
+
+/// A fully signed borrow request, ready to be sent by [`LicenseClient::send_prepared`]
+///
+/// Serializable, so it can be stored or forwarded by a relay. The server
+/// only accepts a signature while its timestamp is within 5 minutes of its
+/// own clock (in either direction).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedBorrow {
+    /// URL the request is sent to
+    pub url: String,
+    /// Request headers, including the signature and idempotency key
+    pub headers: Vec<(String, String)>,
+    /// JSON request body
+    pub body: String,
+    /// Tool name, as passed to the prepare call
+    pub tool: String,
+    /// Username
+    pub user: String,
+    /// Idempotency key of the request
+    pub idempotency_key: String,
+}
+
+impl LicenseClient {
+    /// Sign a borrow request now, to be sent later with [`send_prepared`](Self::send_prepared)
+    ///
+    /// The signature is timestamped with the current time, so the request
+    /// must reach the server within 5 minutes; use
+    /// [`prepare_borrow_at`](Self::prepare_borrow_at) if the relay delay is
+    /// longer but known.
+    ///
+    /// # Errors
+    /// Returns `InvalidInput` for a blank tool or user (see
+    /// [`borrow`](Self::borrow)).
+    pub fn prepare_borrow(&self, tool: impl Into<String>, user: impl Into<String>) -> Result<SignedBorrow> {
+        self.prepare_borrow_at(tool, user, Utc::now())
+    }
+    
+    /// Sign a borrow request timestamped for `send_at`
+    ///
+    /// The server accepts the request from 5 minutes before `send_at` until
+    /// 5 minutes after it.
+    ///
+    /// # Errors
+    /// Same as [`prepare_borrow`](Self::prepare_borrow).
+    pub fn prepare_borrow_at(
+        &self,
+        tool: impl Into<String>,
+        user: impl Into<String>,
+        send_at: DateTime<Utc>,
+    ) -> Result<SignedBorrow> {
+        let mut pending = PendingBorrow::new(tool, user, Self::generate_idempotency_key());
+        pending.validate()?;
+        pending.timestamp = Some(send_at.timestamp());
+        
+        let request = self.borrow_request(&pending)?.build()?;
+        let headers = request
+            .headers()
+            .iter()
+            .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+            .collect();
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+            .unwrap_or_default();
+        
+        Ok(SignedBorrow {
+            url: request.url().to_string(),
+            headers,
+            body,
+            tool: pending.tool,
+            user: pending.user,
+            idempotency_key: pending.idempotency_key,
+        })
+    }
+    
+    /// Send a borrow request signed by [`prepare_borrow`](Self::prepare_borrow)
+    ///
+    /// The request is sent exactly as prepared, so it doesn't need to be the
+    /// same client (or host) that signed it. It must be configured for the
+    /// same server, though: the returned handle returns its license through
+    /// this client.
+    ///
+    /// # Errors
+    /// Returns `InvalidInput`, without sending anything, if `prepared.url`
+    /// isn't under this client's base URL, and `TimestampSkew` if the request
+    /// arrives outside its validity window; otherwise same as
+    /// [`borrow`](Self::borrow).
+    pub async fn send_prepared(&self, prepared: SignedBorrow) -> Result<LicenseHandle> {
+        if !self.is_own_url(&prepared.url) {
+            return Err(LicenseError::InvalidInput(format!(
+                "prepared borrow targets {}, not the server at {}",
+                prepared.url, self.base_url
+            )));
+        }
+        
+        // The timestamp is part of the prepared signature, so skew can't be
+        // corrected here
+        let response = self
+            .retrying(|| async {
+                let mut request = self.client.post(&prepared.url).body(prepared.body.clone());
+                for (name, value) in &prepared.headers {
+                    request = request.header(name, value);
+                }
+                self.buffer(self.send(request).await?).await
+            })
+            .await?;
+        
+        let pending = PendingBorrow::new(prepared.tool, prepared.user, prepared.idempotency_key);
+        let mut handle = self.handle_from_borrow_body(response.status, &response.body, pending)?;
+        self.register_handle(&mut handle, None);
+        Ok(handle)
+    }
+    
+    /// Whether `url` is on this client's server, under its base URL
+    fn is_own_url(&self, url: &str) -> bool {
+        let (Ok(url), Ok(base)) = (reqwest::Url::parse(url), reqwest::Url::parse(&self.base_url)) else {
+            return false;
+        };
+        let base_path = base.path().trim_end_matches('/');
+        url.origin() == base.origin()
+            && url
+                .path()
+                .strip_prefix(base_path)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_json, header, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_prepared_borrow_round_trip() {
+        let server = MockServer::start().await;
+        let send_at = Utc::now() + chrono::Duration::minutes(10);
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .and(header("x-timestamp", send_at.timestamp().to_string().as_str()))
+            .and(header_exists("x-signature"))
+            .and(header_exists("idempotency-key"))
+            .and(body_json(serde_json::json!({ "tool": "cad_tool", "user": "alice" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let signer = LicenseClient::builder(server.uri()).api_key(None).build();
+        let prepared = signer.prepare_borrow_at("cad_tool", "alice", send_at).unwrap();
+        
+        // Forwarded as JSON and sent by a client without credentials
+        let forwarded: SignedBorrow = serde_json::from_str(&serde_json::to_string(&prepared).unwrap()).unwrap();
+        let relay = LicenseClient::builder(server.uri()).enable_security(false).build();
+        let license = relay.send_prepared(forwarded).await.unwrap();
+        
+        assert_eq!(license.id(), "abc-123");
+        assert_eq!(license.idempotency_key(), prepared.idempotency_key);
+        license.return_license().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_send_prepared_rejects_other_server() {
+        let signer = LicenseClient::builder("http://license-a:8000").api_key(None).build();
+        let prepared = signer.prepare_borrow("cad_tool", "alice").unwrap();
+        
+        for base_url in ["http://license-b:8000", "http://license-a:8000/api"] {
+            let relay = LicenseClient::builder(base_url).enable_security(false).build();
+            let result = relay.send_prepared(prepared.clone()).await;
+            assert!(matches!(result, Err(LicenseError::InvalidInput(_))));
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//! Server-side queue tickets for resuming a wait across requests
+
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::{http_error, parse_json, LicenseClient, LicenseError, LicenseHandle, PendingBorrow, Result};
+
+/// A borrow request waiting in the server's queue
+///
+/// Tickets are plain data so they can be stored (e.g. in a session) and
+/// polled later with [`LicenseClient::poll_ticket`], possibly from another
+/// process.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueTicket {
+    /// Ticket ID assigned by the server
+    pub id: String,
+    /// Tool the seat was requested for
+    pub tool: String,
+    /// User the seat was requested for
+    pub user: String,
+}
+
+/// State of a [`QueueTicket`] as reported by the server
+#[derive(Debug)]
+pub enum TicketState {
+    /// Still waiting; `position` is 1 for the next request to be granted
+    Queued { position: u32 },
+    /// A seat was granted to the ticket and must now be returned as usual
+    Granted(Box<LicenseHandle>),
+    /// The ticket timed out or was cancelled server-side
+    Expired,
+}
+
+/// A change of position reported by [`LicenseClient::borrow_queued_notify`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueUpdate {
+    /// Position in the queue, 1 for the next request to be granted
+    pub position: u32,
+    /// Time until the grant at the rate the queue has moved so far, `None`
+    /// until it has moved at all
+    pub estimated_wait: Option<Duration>,
+}
+
+/// The borrow half of [`LicenseClient::borrow_queued_notify`]
+pub type BorrowFuture = Pin<Box<dyn Future<Output = Result<LicenseHandle>> + Send>>;
+
+impl LicenseClient {
+    /// Borrow through the server's queue, reporting position changes on a channel
+    ///
+    /// The returned future enqueues the request, polls the ticket every
+    /// `poll_interval` and resolves to the license once it is granted.
+    /// While it is being awaited, a [`QueueUpdate`] is sent each time the
+    /// position changes; the channel closes when the future completes.
+    /// Updates are dropped rather than delaying the borrow if the receiver
+    /// falls more than 16 behind.
+    ///
+    /// # Errors
+    /// The future fails with `Timeout` if the ticket expires server-side,
+    /// and otherwise as [`request_queue_ticket`](Self::request_queue_ticket)
+    /// and [`poll_ticket`](Self::poll_ticket) do.
+    pub fn borrow_queued_notify(
+        &self,
+        tool: impl Into<String>,
+        user: impl Into<String>,
+    ) -> (BorrowFuture, mpsc::Receiver<QueueUpdate>) {
+        let (updates, receiver) = mpsc::channel(16);
+        let client = self.clone();
+        let (tool, user) = (tool.into(), user.into());
+        
+        let borrow = async move {
+            let started = Instant::now();
+            let ticket = client.request_queue_ticket(tool, user).await?;
+            let mut first: Option<u32> = None;
+            let mut last: Option<u32> = None;
+            
+            loop {
+                match client.poll_ticket(&ticket).await? {
+                    TicketState::Granted(license) => return Ok(*license),
+                    TicketState::Expired => return Err(LicenseError::Timeout(started.elapsed())),
+                    TicketState::Queued { position } => {
+                        let first = *first.get_or_insert(position);
+                        if last != Some(position) {
+                            last = Some(position);
+                            let moved = first.saturating_sub(position);
+                            let estimated_wait = (moved > 0).then(|| started.elapsed() / moved * position);
+                            let _ = updates.try_send(QueueUpdate { position, estimated_wait });
+                        }
+                    }
+                }
+                tokio::time::sleep(client.poll_interval).await;
+            }
+        };
+        (Box::pin(borrow), receiver)
+    }
+    
+    /// Enqueue a borrow request server-side without waiting for a seat
+    ///
+    /// Unlike [`borrow_wait`](Self::borrow_wait) nothing is held on the client
+    /// while the request is queued; poll the returned ticket with
+    /// [`poll_ticket`](Self::poll_ticket).
+    ///
+    /// # Arguments
+    /// * `tool` - Name of the tool to borrow
+    /// * `user` - User requesting the license
+    ///
+    /// # Errors
+    /// Returns `HttpError` if the server rejects the request.
+    pub async fn request_queue_ticket(
+        &self,
+        tool: impl Into<String>,
+        user: impl Into<String>,
+    ) -> Result<QueueTicket> {
+        #[derive(Serialize)]
+        struct QueueRequest<'a> {
+            tool: &'a str,
+            user: &'a str,
+        }
+        
+        #[derive(Deserialize)]
+        struct QueueResponse {
+            ticket_id: String,
+        }
+        
+        let tool = tool.into();
+        let user = user.into();
+        let qualified = self.qualified_tool(&tool);
+        let url = self.endpoints().queue;
+        
+        let response = self
+            .with_retries(|| async {
+                let request = self.json_body(self.client.post(&url), &QueueRequest {
+                    tool: &qualified,
+                    user: &user,
+                });
+                self.send(self.signed(request, &qualified, &user)?).await
+            })
+            .await?;
+        
+        let status = response.status();
+        let body = self.read_body_capped(response).await?;
+        if !status.is_success() {
+            return Err(http_error(status, &body));
+        }
+        
+        let data: QueueResponse = parse_json(&body)?;
+        Ok(QueueTicket {
+            id: data.ticket_id,
+            tool,
+            user,
+        })
+    }
+    
+    /// Check on a ticket from [`request_queue_ticket`](Self::request_queue_ticket)
+    ///
+    /// # Errors
+    /// Returns `HttpError` if the server doesn't know the ticket, or
+    /// `InvalidResponse` if it reports an unknown state.
+    pub async fn poll_ticket(&self, ticket: &QueueTicket) -> Result<TicketState> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum State {
+            Queued,
+            Granted,
+            Expired,
+        }
+        
+        #[derive(Deserialize)]
+        struct PollResponse {
+            state: State,
+            #[serde(default)]
+            position: u32,
+        }
+        
+        let url = self.endpoints().queue_ticket(&ticket.id);
+        let response = self.fetch(|| self.authorized(self.client.get(&url))).await?.error_for_status()?;
+        
+        let data: PollResponse = parse_json(&response.body)?;
+        match data.state {
+            State::Queued => Ok(TicketState::Queued { position: data.position }),
+            State::Expired => Ok(TicketState::Expired),
+            // A granted ticket carries the same license fields as a borrow
+            State::Granted => {
+                let pending = PendingBorrow::new(&ticket.tool, &ticket.user, &ticket.id);
+                self.handle_from_borrow_body(response.status, &response.body, pending)
+                    .map(|license| TicketState::Granted(Box::new(license)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    fn ticket() -> QueueTicket {
+        QueueTicket {
+            id: "t-1".to_string(),
+            tool: "cad_tool".to_string(),
+            user: "alice".to_string(),
+        }
+    }
+    
+    async fn mock_poll(server: &MockServer, body: serde_json::Value) {
+        Mock::given(method("GET"))
+            .and(path("/licenses/queue/t-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(server)
+            .await;
+    }
+    
+    #[tokio::test]
+    async fn test_request_queue_ticket() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/queue"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "ticket_id": "t-1" })))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        assert_eq!(client.request_queue_ticket("cad_tool", "alice").await.unwrap(), ticket());
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_queued_notify() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/queue"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "ticket_id": "t-1" })))
+            .mount(&server)
+            .await;
+        for position in [2, 2, 1] {
+            Mock::given(method("GET"))
+                .and(path("/licenses/queue/t-1"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(
+                    serde_json::json!({ "state": "queued", "position": position }),
+                ))
+                .up_to_n_times(1)
+                .mount(&server)
+                .await;
+        }
+        mock_poll(&server, serde_json::json!({ "state": "granted", "id": "abc-123" })).await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .poll_interval(Duration::from_millis(10))
+            .build();
+        let (borrow, mut updates) = client.borrow_queued_notify("cad_tool", "alice");
+        
+        let license = borrow.await.unwrap();
+        assert_eq!(license.id(), "abc-123");
+        license.return_license().await.unwrap();
+        
+        let first = updates.recv().await.unwrap();
+        assert_eq!(first, QueueUpdate { position: 2, estimated_wait: None });
+        let second = updates.recv().await.unwrap();
+        assert_eq!(second.position, 1);
+        assert!(second.estimated_wait.is_some());
+        assert!(updates.recv().await.is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_poll_ticket_states() {
+        let server = MockServer::start().await;
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        
+        mock_poll(&server, serde_json::json!({ "state": "queued", "position": 3 })).await;
+        assert!(matches!(client.poll_ticket(&ticket()).await, Ok(TicketState::Queued { position: 3 })));
+        
+        server.reset().await;
+        mock_poll(&server, serde_json::json!({ "state": "expired" })).await;
+        assert!(matches!(client.poll_ticket(&ticket()).await, Ok(TicketState::Expired)));
+        
+        server.reset().await;
+        mock_poll(&server, serde_json::json!({ "state": "granted", "id": "abc-123" })).await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        match client.poll_ticket(&ticket()).await {
+            Ok(TicketState::Granted(license)) => {
+                assert_eq!(license.id(), "abc-123");
+                assert_eq!(license.tool(), "cad_tool");
+                license.return_license().await.unwrap();
+            }
+            other => panic!("expected a granted ticket, got {:?}", other),
+        }
+    }
+}
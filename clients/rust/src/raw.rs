@@ -0,0 +1,107 @@
+//! Untyped access to server endpoints the client doesn't wrap yet
+
+use reqwest::Method;
+
+use crate::{http_error, parse_json, LicenseClient, Result};
+
+impl LicenseClient {
+    /// Send a request to any server endpoint and return its JSON response
+    ///
+    /// An escape hatch for endpoints without a typed method. The standard
+    /// client behavior still applies: security headers per the auth mode
+    /// (signed over an empty tool and user, since the client doesn't know
+    /// the payload), the configured content type, retries and the response
+    /// size cap.
+    ///
+    /// # Arguments
+    /// * `method` - HTTP method
+    /// * `path` - Path relative to the base URL and API prefix, e.g. `/licenses/status`
+    /// * `body` - Optional JSON request body
+    ///
+    /// # Errors
+    /// Returns `HttpError` for non-success statuses and `InvalidResponse` if
+    /// the body isn't JSON. An empty body is returned as `Value::Null`.
+    pub async fn raw_request(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value> {
+        let url = format!("{}/{}", self.paths.root(&self.base_url), path.trim_start_matches('/'));
+        
+        let response = self
+            .with_retries(|| {
+                let mut request = self.client.request(method.clone(), &url);
+                if let Some(body) = &body {
+                    request = self.json_body(request, body);
+                }
+                // No tool or user to sign over, as for `verify_credentials`
+                let request = self.signed(request, "", "");
+                async move { self.send(request?).await }
+            })
+            .await?;
+        
+        let status = response.status();
+        let body = self.read_body_capped(response).await?;
+        if !status.is_success() {
+            return Err(http_error(status, &body));
+        }
+        
+        if body.is_empty() {
+            return Ok(serde_json::Value::Null);
+        }
+        parse_json(&body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LicenseError;
+    use wiremock::matchers::{body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_raw_request_sends_body_and_auth() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/reserve"))
+            .and(header("authorization", "Bearer key-1"))
+            .and(body_json(serde_json::json!({ "tool": "cad_tool" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "ok": true })))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .api_key(Some("key-1".to_string()))
+            .build();
+        let value = client
+            .raw_request(Method::POST, "/licenses/reserve", Some(serde_json::json!({ "tool": "cad_tool" })))
+            .await
+            .unwrap();
+        
+        assert_eq!(value, serde_json::json!({ "ok": true }));
+    }
+    
+    #[tokio::test]
+    async fn test_raw_request_maps_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/missing"))
+            .respond_with(ResponseTemplate::new(404).set_body_string(r#"{"detail":"Not Found"}"#))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/admin"))
+            .respond_with(ResponseTemplate::new(403).set_body_string(r#"{"detail":"Admin only"}"#))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        let result = client.raw_request(Method::GET, "missing", None).await;
+        assert!(matches!(result, Err(LicenseError::HttpError(404, _))));
+        
+        let result = client.raw_request(Method::GET, "admin", None).await;
+        assert!(matches!(result, Err(LicenseError::AuthenticationFailed { status: 403, message }) if message == "Admin only"));
+    }
+}
@@ -0,0 +1,126 @@
+//! Releasing held seats, in part or by license ID
+
+use serde::Deserialize;
+
+use crate::{http_error, LicenseClient, LicenseError, LicenseHandle, Result, ReturnOptions};
+
+impl LicenseClient {
+    /// Return license `id` without a handle for it
+    ///
+    /// For cleanup tooling, e.g. freeing the seat of a crashed worker whose
+    /// license ID is known from its logs. Sends the same request as
+    /// [`LicenseHandle::return_license`], minus the journal and handle
+    /// bookkeeping.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotHeld` if the server doesn't know the license (`404`) or
+    /// it was already returned (`409`), so scripts can tell that apart from
+    /// other failures.
+    pub async fn return_by_id(&self, id: &str) -> Result<()> {
+        #[derive(Deserialize)]
+        struct ReturnResponse {
+            tool: Option<String>,
+        }
+        
+        let response = self.send_return(id, &ReturnOptions::default()).await?;
+        let status = response.status();
+        let body = self.read_body_capped(response).await?;
+        match status.as_u16() {
+            404 | 409 => return Err(LicenseError::NotHeld(id.to_string())),
+            _ if !status.is_success() => return Err(http_error(status, &body)),
+            _ => {}
+        }
+        
+        // The server names the tool the seat belonged to
+        if let Ok(ReturnResponse { tool: Some(tool) }) = serde_json::from_slice(&body) {
+            self.status_cache.invalidate(&tool);
+        }
+        Ok(())
+    }
+    
+    /// Return `fraction` of `handles`, oldest first, and keep the rest
+    ///
+    /// The number released is `fraction` (clamped to `0.0..=1.0`) of the
+    /// handle count, rounded up so any positive fraction sheds at least one
+    /// seat. Returns are sent one after another.
+    ///
+    /// Returns the retained handles (oldest first) and the result of each
+    /// return, in the order the seats were released. A handle whose return
+    /// fails is not handed back.
+    pub async fn release_fraction(
+        &self,
+        mut handles: Vec<LicenseHandle>,
+        fraction: f64,
+    ) -> (Vec<LicenseHandle>, Vec<Result<()>>) {
+        handles.sort_by_key(|handle| handle.acquired);
+        
+        let fraction = if fraction.is_nan() { 0.0 } else { fraction.clamp(0.0, 1.0) };
+        let count = ((handles.len() as f64 * fraction).ceil() as usize).min(handles.len());
+        let retained = handles.split_off(count);
+        
+        let mut results = Vec::with_capacity(count);
+        for handle in handles {
+            results.push(handle.return_license().await);
+        }
+        (retained, results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_release_fraction_returns_oldest() {
+        let server = MockServer::start().await;
+        for id in ["first", "second", "third"] {
+            Mock::given(method("POST"))
+                .and(path("/licenses/borrow"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": id })))
+                .up_to_n_times(1)
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .and(body_json(serde_json::json!({ "id": "first" })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        let mut handles = Vec::new();
+        for _ in 0..3 {
+            handles.push(client.borrow("cad_tool", "alice").await.unwrap());
+        }
+        handles.reverse();
+        
+        let (retained, results) = client.release_fraction(handles, 0.3).await;
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_ok());
+        assert_eq!(retained.iter().map(|h| h.id()).collect::<Vec<_>>(), ["second", "third"]);
+    }
+    
+    #[tokio::test]
+    async fn test_return_by_id_tells_not_held_from_failures() {
+        let server = MockServer::start().await;
+        for (id, status) in [("held", 200), ("gone", 404), ("twice", 409), ("broken", 500)] {
+            Mock::given(method("POST"))
+                .and(path("/licenses/return"))
+                .and(body_json(serde_json::json!({ "id": id })))
+                .respond_with(ResponseTemplate::new(status).set_body_json(serde_json::json!({ "tool": "cad_tool" })))
+                .mount(&server)
+                .await;
+        }
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        client.return_by_id("held").await.unwrap();
+        assert!(matches!(client.return_by_id("gone").await, Err(LicenseError::NotHeld(id)) if id == "gone"));
+        assert!(matches!(client.return_by_id("twice").await, Err(LicenseError::NotHeld(_))));
+        assert!(matches!(client.return_by_id("broken").await, Err(LicenseError::HttpError(500, _))));
+    }
+}
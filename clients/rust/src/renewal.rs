@@ -0,0 +1,440 @@
+//! Background lease renewal for long-held licenses
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use urlencoding::encode;
+
+use crate::{http_error, parse_json, LicenseClient, LicenseError, LicenseHandle, Result, ServerTimestamp};
+
+/// State shared between a handle and its renewal task
+#[derive(Debug, Default)]
+struct RenewalState {
+    paused: AtomicBool,
+    expired: AtomicBool,
+    // Latest expiry reported by the server, if any
+    expires_at: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl RenewalState {
+    fn expires_at(&self) -> Option<DateTime<Utc>> {
+        *self.expires_at.lock().unwrap()
+    }
+    
+    fn lapsed(&self, now: DateTime<Utc>) -> bool {
+        lapsed(self.expires_at(), now)
+    }
+}
+
+fn lapsed(expires_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+    expires_at.is_some_and(|expires_at| now >= expires_at)
+}
+
+/// A running auto-renewal task, aborted when dropped
+#[derive(Debug)]
+pub(crate) struct Renewer {
+    state: Arc<RenewalState>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl Drop for Renewer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl LicenseClient {
+    /// POST a renewal for license `id`, held by `user` for `tool`
+    pub(crate) async fn send_renew(&self, id: &str, tool: &str, user: &str) -> Result<reqwest::Response> {
+        #[derive(Serialize)]
+        struct RenewRequest<'a> {
+            id: &'a str,
+        }
+        
+        let url = self.endpoints().renew;
+        let tool = self.qualified_tool(tool);
+        self.with_retries(|| async {
+            let request = self.json_body(self.client.post(&url), &RenewRequest { id });
+            self.send(self.signed(request, &tool, user)?).await
+        })
+        .await
+    }
+}
+
+/// Renew `id` every `interval` until the lease is lost
+async fn renew_loop(
+    client: LicenseClient,
+    (id, tool, user): (String, String, String),
+    interval: Duration,
+    state: Arc<RenewalState>,
+) {
+    #[derive(Deserialize)]
+    struct RenewResponse {
+        #[serde(default)]
+        expires_at: Option<String>,
+    }
+    
+    loop {
+        tokio::time::sleep(interval).await;
+        
+        if state.paused.load(Ordering::Relaxed) {
+            if state.lapsed(client.now()) {
+                state.expired.store(true, Ordering::Relaxed);
+                return;
+            }
+            continue;
+        }
+        
+        let response = match client.send_renew(&id, &tool, &user).await {
+            Ok(response) => response,
+            Err(e) => {
+                eprintln!("Warning: failed to renew license {}: {}", id, e);
+                continue;
+            }
+        };
+        
+        let status = response.status();
+        // The server no longer knows the lease, so there is nothing left to renew
+        if status.as_u16() == 404 || status.as_u16() == 409 {
+            state.expired.store(true, Ordering::Relaxed);
+            return;
+        }
+        if !status.is_success() {
+            eprintln!("Warning: failed to renew license {}: HTTP {}", id, status.as_u16());
+            continue;
+        }
+        
+        let expires_at = match client.read_body_capped(response).await {
+            Ok(body) => parse_json::<RenewResponse>(&body).ok().and_then(|data| data.expires_at),
+            Err(_) => None,
+        };
+        if let Some(expires_at) = expires_at.and_then(|value| ServerTimestamp::parse(&value).ok()) {
+            *state.expires_at.lock().unwrap() = Some(expires_at.utc());
+        }
+    }
+}
+
+impl LicenseHandle {
+    /// Renew the lease in the background every `interval`
+    ///
+    /// The task runs until the license is returned or the handle dropped, or
+    /// until the server reports the lease as gone (see
+    /// [`is_held`](Self::is_held)). Transient failures are retried on the
+    /// next tick. Calling this again restarts renewal with the new interval.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a Tokio runtime.
+    pub fn auto_renew(&mut self, interval: Duration) {
+        let state = Arc::new(RenewalState::default());
+        *state.expires_at.lock().unwrap() = self.expires_at.as_ref().map(ServerTimestamp::utc);
+        
+        let license = (self.id.clone(), self.tool.clone(), self.user.clone());
+        let task = tokio::spawn(renew_loop(self.client.clone(), license, interval, state.clone()));
+        self.renewer = Some(Renewer { state, task });
+    }
+    
+    /// Refresh the lease on the server once, keeping the license
+    ///
+    /// Takes `&self`, so it can be called repeatedly, e.g. from a timer as
+    /// a heartbeat. Security headers are sent when enabled.
+    ///
+    /// # Errors
+    /// Returns `LeaseExpired` if the server answers 409 because the lease is
+    /// already gone, `AuthenticationFailed` for 401/403 and `HttpError` for
+    /// other non-success responses.
+    pub async fn renew(&self) -> Result<()> {
+        self.client
+            .instrumented("renew", self.subject(), async {
+                let response = self.client.send_renew(&self.id, &self.tool, &self.user).await?;
+                let status = response.status();
+                if status.as_u16() == 409 {
+                    return Err(LicenseError::LeaseExpired(self.id.clone()));
+                }
+                if !status.is_success() {
+                    let body = self.client.read_body_capped(response).await?;
+                    return Err(http_error(status, &body));
+                }
+                Ok(())
+            })
+            .await
+    }
+    
+    /// Suspend auto-renewal without returning the seat
+    ///
+    /// If the lease expires while paused the handle becomes invalid and
+    /// stays so, even if renewal is resumed. Does nothing unless
+    /// [`auto_renew`](Self::auto_renew) was called.
+    pub fn pause_renewal(&self) {
+        if let Some(renewer) = &self.renewer {
+            renewer.state.paused.store(true, Ordering::Relaxed);
+        }
+    }
+    
+    /// Resume auto-renewal suspended by [`pause_renewal`](Self::pause_renewal)
+    pub fn resume_renewal(&self) {
+        if let Some(renewer) = &self.renewer {
+            renewer.state.paused.store(false, Ordering::Relaxed);
+        }
+    }
+    
+    /// Whether auto-renewal is currently paused
+    pub fn is_renewal_paused(&self) -> bool {
+        self.renewer
+            .as_ref()
+            .is_some_and(|renewer| renewer.state.paused.load(Ordering::Relaxed))
+    }
+    
+    /// Whether the seat is still held, as far as the client knows
+    ///
+    /// `false` once the license is returned, its last known expiry has
+    /// passed, or the server refused to renew it. This is a local check; it
+    /// makes no request; see [`is_valid`](Self::is_valid) to ask the server.
+    pub fn is_held(&self) -> bool {
+        if self.returned {
+            return false;
+        }
+        let now = self.client.now();
+        match &self.renewer {
+            Some(renewer) => !renewer.state.expired.load(Ordering::Relaxed) && !renewer.state.lapsed(now),
+            None => !lapsed(self.expires_at.as_ref().map(ServerTimestamp::utc), now),
+        }
+    }
+    
+    /// Whether the server still considers this license held by us
+    ///
+    /// Asks `GET /licenses/{id}/valid`, which answers `{"valid": bool}`;
+    /// servers without that endpoint (a bare 404 route miss or 501) are
+    /// asked for the user's outstanding borrows instead. Use it after
+    /// network trouble to detect seats the server has reclaimed. No request
+    /// is made once [`is_held`](Self::is_held) is `false`.
+    ///
+    /// # Errors
+    /// Request failures and non-success responses other than 404, which
+    /// means the server doesn't know the license and yields `Ok(false)`.
+    pub async fn is_valid(&self) -> Result<bool> {
+        if !self.is_held() {
+            return Ok(false);
+        }
+        
+        let endpoints = self.client.endpoints();
+        let url = endpoints.valid(&self.id);
+        let response = self.client.fetch(|| self.client.authorized(self.client.client.get(&url))).await?;
+        match response.error_for_endpoint(&url) {
+            Ok(response) => Ok(parse_json::<ValidResponse>(&response.body)?.valid),
+            Err(LicenseError::HttpError(404, _)) => Ok(false),
+            Err(LicenseError::UnsupportedEndpoint(_)) => {
+                let url = format!("{}?user={}", endpoints.borrows, encode(&self.user));
+                let borrows = self.client.fetch_borrows(&url).await?;
+                Ok(borrows.iter().any(|borrow| borrow.id == self.id))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ValidResponse {
+    valid: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+    use wiremock::matchers::{body_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    async fn borrow_expiring_in(server: &MockServer, lease: chrono::Duration) -> LicenseHandle {
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "abc-123",
+                "expires_at": (Utc::now() + lease).to_rfc3339(),
+            })))
+            .mount(server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(server)
+            .await;
+        
+        // Follows tokio's clock, so leases lapse when paused time is advanced
+        let origin = (SystemTime::now(), tokio::time::Instant::now());
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .clock(Arc::new(move || origin.0 + origin.1.elapsed()))
+            .build();
+        client.borrow("cad_tool", "alice").await.unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_renew_keeps_license() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/renew"))
+            .and(body_json(serde_json::json!({ "id": "abc-123" })))
+            .respond_with(ResponseTemplate::new(200))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/renew"))
+            .respond_with(ResponseTemplate::new(409))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/renew"))
+            .respond_with(ResponseTemplate::new(403).set_body_json(serde_json::json!({ "detail": "Token revoked" })))
+            .mount(&server)
+            .await;
+        
+        let license = borrow_expiring_in(&server, chrono::Duration::hours(1)).await;
+        license.renew().await.unwrap();
+        license.renew().await.unwrap();
+        assert!(!license.returned);
+        assert!(license.is_held());
+        
+        assert!(matches!(license.renew().await, Err(LicenseError::LeaseExpired(id)) if id == "abc-123"));
+        assert!(matches!(
+            license.renew().await,
+            Err(LicenseError::AuthenticationFailed { status: 403, message }) if message == "Token revoked"
+        ));
+        license.return_license().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_is_valid_asks_server() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/abc-123/valid"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "valid": true })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/abc-123/valid"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({ "detail": "License not found" })))
+            .mount(&server)
+            .await;
+        
+        let license = borrow_expiring_in(&server, chrono::Duration::hours(1)).await;
+        assert!(license.is_valid().await.unwrap());
+        assert!(!license.is_valid().await.unwrap());
+        license.return_license().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_is_valid_falls_back_to_borrows() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/abc-123/valid"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({ "detail": "Not Found" })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/abc-123/valid"))
+            .respond_with(ResponseTemplate::new(501))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/borrows"))
+            .and(query_param("user", "alice"))
+            .and(header("authorization", "Bearer token-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "id": "abc-123", "tool": "cad_tool", "user": "alice", "borrowed_at": "2024-01-01T00:00:00" }
+            ])))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/borrows"))
+            .and(header("authorization", "Bearer token-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        // The fallback is authenticated like any other request
+        let client = LicenseClient::builder(server.uri())
+            .auth(crate::AuthMode::Bearer("token-1".to_string()))
+            .build();
+        let license = client.borrow("cad_tool", "alice").await.unwrap();
+        assert!(license.is_valid().await.unwrap());
+        assert!(!license.is_valid().await.unwrap());
+        license.return_license().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_lease_expires_while_renewal_paused() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/renew"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+        
+        let mut license = borrow_expiring_in(&server, chrono::Duration::seconds(30)).await;
+        tokio::time::pause();
+        license.auto_renew(Duration::from_secs(5));
+        license.pause_renewal();
+        assert!(license.is_held());
+        
+        // Lets the renewal task see the lease lapse while paused
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        license.resume_renewal();
+        assert!(!license.is_held());
+        
+        tokio::time::resume();
+        license.return_license().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_resumed_renewal_extends_lease() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/renew"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "expires_at": (Utc::now() + chrono::Duration::hours(1)).to_rfc3339(),
+            })))
+            .mount(&server)
+            .await;
+        
+        let mut license = borrow_expiring_in(&server, chrono::Duration::seconds(30)).await;
+        tokio::time::pause();
+        license.auto_renew(Duration::from_secs(5));
+        license.pause_renewal();
+        assert!(license.is_renewal_paused());
+        license.resume_renewal();
+        // Let the renewal task start its first wait before time jumps
+        tokio::task::yield_now().await;
+        
+        // Past the original lease: held again once the renewal lands. Time
+        // stays put while this spins, so only the request can move it on.
+        tokio::time::advance(Duration::from_secs(60)).await;
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while !license.is_held() {
+            assert!(std::time::Instant::now() < deadline, "lease was not renewed");
+            tokio::task::yield_now().await;
+        }
+        
+        tokio::time::resume();
+        license.return_license().await.unwrap();
+    }
+}
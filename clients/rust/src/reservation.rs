@@ -0,0 +1,256 @@
+//! Short-lived seat reservations that are confirmed or released later
+
+use std::mem;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    http_error, parse_json, LicenseClient, LicenseError, LicenseHandle, PendingBorrow, Result, ServerTimestamp,
+};
+
+/// A seat held for a short time, to be committed or cancelled
+///
+/// Created by [`LicenseClient::reserve`]. The server frees the seat by
+/// itself once the reservation's TTL lapses. Dropping a reservation that
+/// was neither committed nor cancelled cancels it from a detached task,
+/// like an unreturned [`LicenseHandle`].
+#[derive(Debug)]
+pub struct Reservation {
+    id: String,
+    tool: String,
+    user: String,
+    expires_at: Option<ServerTimestamp>,
+    client: LicenseClient,
+    settled: bool,
+    runtime: Option<tokio::runtime::Handle>,
+}
+
+impl LicenseClient {
+    /// Reserve a seat of `tool` for `ttl` before committing to the borrow
+    ///
+    /// For schedulers that set up a job before starting it: the seat can't
+    /// be taken by anyone else while reserved, but becomes a license only
+    /// once [`Reservation::commit`] is called.
+    ///
+    /// # Arguments
+    ///
+    /// * `tool` - Tool name
+    /// * `user` - Username
+    /// * `ttl` - How long the server holds the seat (whole seconds)
+    ///
+    /// # Errors
+    ///
+    /// Returns `NoLicensesAvailable` if no seat is free, or `HttpError` if
+    /// the server rejects the request.
+    pub async fn reserve(
+        &self,
+        tool: impl Into<String>,
+        user: impl Into<String>,
+        ttl: Duration,
+    ) -> Result<Reservation> {
+        #[derive(Serialize)]
+        struct ReserveRequest<'a> {
+            tool: &'a str,
+            user: &'a str,
+            ttl_seconds: u64,
+        }
+        
+        #[derive(Deserialize)]
+        struct ReserveResponse {
+            id: String,
+            #[serde(default)]
+            expires_at: Option<String>,
+        }
+        
+        let (tool, user) = (tool.into(), user.into());
+        let qualified = self.qualified_tool(&tool);
+        let url = self.endpoints().reserve;
+        let body = ReserveRequest {
+            tool: &qualified,
+            user: &user,
+            ttl_seconds: ttl.as_secs(),
+        };
+        
+        let response = self
+            .with_retries(|| async {
+                let request = self.signed(self.json_body(self.client.post(&url), &body), &qualified, &user)?;
+                self.buffer(self.send(request).await?).await
+            })
+            .await?;
+        if response.status.as_u16() == 409 {
+            return Err(LicenseError::NoLicensesAvailable(tool));
+        }
+        let response = response.error_for_status()?;
+        
+        let data: ReserveResponse = parse_json(&response.body)?;
+        Ok(Reservation {
+            expires_at: data.expires_at.and_then(|value| ServerTimestamp::parse(&value).ok()),
+            id: data.id,
+            tool,
+            user,
+            client: self.clone(),
+            settled: false,
+            runtime: self.drop_runtime(),
+        })
+    }
+}
+
+impl Reservation {
+    /// Get reservation ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    
+    /// Get tool name
+    pub fn tool(&self) -> &str {
+        &self.tool
+    }
+    
+    /// Get username
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+    
+    /// Get when the server frees the seat unless committed, if it said so
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.expires_at.as_ref().map(|timestamp| timestamp.utc().into())
+    }
+    
+    /// Turn the reservation into a borrowed license
+    ///
+    /// # Errors
+    ///
+    /// Returns `ReservationExpired` if the server already freed the seat,
+    /// or `HttpError` for other failures.
+    pub async fn commit(mut self) -> Result<LicenseHandle> {
+        self.settled = true;
+        let client = &self.client;
+        let qualified = client.qualified_tool(&self.tool);
+        let url = client.endpoints().reservation_commit(&self.id);
+        
+        let response = client
+            .with_retries(|| async {
+                let request = client.signed(client.client.post(&url), &qualified, &self.user)?;
+                client.buffer(client.send(request).await?).await
+            })
+            .await?;
+        if matches!(response.status.as_u16(), 404 | 410) {
+            return Err(LicenseError::ReservationExpired(self.id.clone()));
+        }
+        if !response.status.is_success() {
+            return Err(http_error(response.status, &response.body));
+        }
+        
+        // A committed reservation answers like a borrow
+        let pending = PendingBorrow::new(&self.tool, &self.user, &self.id);
+        let mut handle = client.handle_from_borrow_body(response.status, &response.body, pending)?;
+        client.register_handle(&mut handle, None);
+        Ok(handle)
+    }
+    
+    /// Release the seat now instead of waiting for the TTL
+    ///
+    /// A reservation the server has already freed counts as cancelled.
+    pub async fn cancel(mut self) -> Result<()> {
+        self.settled = true;
+        cancel(&self.client, &self.id, &self.tool, &self.user).await
+    }
+}
+
+async fn cancel(client: &LicenseClient, id: &str, tool: &str, user: &str) -> Result<()> {
+    let qualified = client.qualified_tool(tool);
+    let url = client.endpoints().reservation_cancel(id);
+    let response = client
+        .with_retries(|| async {
+            let request = client.signed(client.client.post(&url), &qualified, user)?;
+            client.buffer(client.send(request).await?).await
+        })
+        .await?;
+    if response.status.is_success() || matches!(response.status.as_u16(), 404 | 410) {
+        return Ok(());
+    }
+    Err(http_error(response.status, &response.body))
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if self.settled {
+            return;
+        }
+        let runtime = match self.runtime.take() {
+            Some(runtime) => runtime,
+            None => {
+                eprintln!(
+                    "Warning: reservation {} dropped without commit or cancel; the server frees it when it expires",
+                    self.id
+                );
+                return;
+            }
+        };
+        
+        let client = self.client.clone();
+        let (id, tool, user) = (mem::take(&mut self.id), mem::take(&mut self.tool), mem::take(&mut self.user));
+        let task = runtime.spawn({
+            let client = client.clone();
+            async move {
+                if let Err(e) = cancel(&client, &id, &tool, &user).await {
+                    eprintln!("Warning: failed to cancel dropped reservation {}: {}", id, e);
+                }
+            }
+        });
+        client.pending_returns.push(task);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_reservation_commit_cancel_and_drop() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/reserve"))
+            .and(body_partial_json(serde_json::json!({ "tool": "cad_tool", "ttl_seconds": 30 })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "r-1", "expires_at": "2025-03-01T12:00:30Z"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/reserve/r-1/commit"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/reserve/r-1/cancel"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        let reservation = client.reserve("cad_tool", "alice", Duration::from_secs(30)).await.unwrap();
+        assert_eq!(reservation.id(), "r-1");
+        assert!(reservation.expires_at().is_some());
+        let license = reservation.commit().await.unwrap();
+        assert_eq!(license.id(), "abc-123");
+        assert_eq!(license.tool(), "cad_tool");
+        license.return_license().await.unwrap();
+        
+        let reservation = client.reserve("cad_tool", "alice", Duration::from_secs(30)).await.unwrap();
+        reservation.cancel().await.unwrap();
+        
+        drop(client.reserve("cad_tool", "alice", Duration::from_secs(30)).await.unwrap());
+        client.drain().await;
+    }
+}
@@ -0,0 +1,366 @@
+//! Retries with exponential backoff, limited by a shared retry budget
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rand::Rng;
+
+use chrono::{DateTime, Utc};
+
+use crate::clock::response_date;
+use crate::{Buffered, ErrorBody, LicenseClient, Result};
+
+/// How failed requests are retried
+///
+/// Transport failures (connect errors, timeouts, truncated responses) and
+/// `429`/`502`/`503`/`504` responses are retried with exponential backoff and jitter, as long as
+/// the [`RetryBudget`] allows it. So are responses whose error body carries
+/// one of the [`retryable_codes`](Self::retryable_codes), whatever their
+/// status. Borrow retries reuse the original idempotency key.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Maximum retries per request, on top of the first attempt
+    pub max_retries: u32,
+    /// Backoff before the first retry; doubled for every further retry
+    pub base_delay: Duration,
+    /// Upper bound for the backoff between two attempts
+    pub max_delay: Duration,
+    /// Client-wide limit on how many retries may be made
+    pub budget: RetryBudget,
+    /// Application error codes (the `code` of the error body, e.g.
+    /// `"POOL_REBALANCING"`) that mean "try again shortly"
+    pub retryable_codes: HashSet<String>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            budget: RetryBudget::default(),
+            retryable_codes: HashSet::new(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Also retry responses carrying the error code `code`
+    pub fn retry_on_code(mut self, code: impl Into<String>) -> Self {
+        self.retryable_codes.insert(code.into());
+        self
+    }
+    
+    fn is_retryable<T: Attempt>(&self, response: &T) -> bool {
+        is_retryable_status(response.status())
+            || (!self.retryable_codes.is_empty()
+                && response.error_code().is_some_and(|code| self.retryable_codes.contains(&code)))
+    }
+    
+    /// Backoff before retry number `retry` (starting at 1), with jitter
+    fn backoff(&self, retry: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_delay);
+        exponential.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+/// Token bucket limiting retries to a fraction of all requests
+///
+/// Every request deposits `ratio` tokens and every retry spends one, so
+/// under sustained failure the client makes at most roughly
+/// `ratio * requests` retries instead of multiplying its load on a server
+/// that is trying to recover. The bucket starts full and holds at most
+/// `max_tokens`, which allows short bursts of retries.
+#[derive(Debug, Clone)]
+pub struct RetryBudget {
+    /// Retry tokens earned per request (e.g. `0.1` = one retry per ten requests)
+    pub ratio: f64,
+    /// Capacity of the bucket
+    pub max_tokens: f64,
+}
+
+impl Default for RetryBudget {
+    fn default() -> Self {
+        Self {
+            ratio: 0.1,
+            max_tokens: 10.0,
+        }
+    }
+}
+
+/// Retry policy plus the budget state shared by clones of a client
+#[derive(Debug)]
+pub(crate) struct Retrier {
+    policy: RetryPolicy,
+    tokens: Mutex<f64>,
+}
+
+impl Retrier {
+    pub(crate) fn new(policy: RetryPolicy) -> Self {
+        Self {
+            tokens: Mutex::new(policy.budget.max_tokens),
+            policy,
+        }
+    }
+    
+    fn deposit(&self) {
+        let mut tokens = self.tokens.lock().unwrap();
+        *tokens = (*tokens + self.policy.budget.ratio).min(self.policy.budget.max_tokens);
+    }
+    
+    fn try_withdraw(&self) -> bool {
+        let mut tokens = self.tokens.lock().unwrap();
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 429 | 502 | 503 | 504)
+}
+
+/// Outcome of one attempt, whose status decides whether to retry
+pub(crate) trait Attempt {
+    fn status(&self) -> reqwest::StatusCode;
+    
+    /// The error code in the body, for attempts that have read it
+    fn error_code(&self) -> Option<String> {
+        None
+    }
+    
+    /// The server's time, if the attempt was rejected as unauthenticated
+    fn rejected_at(&self) -> Option<DateTime<Utc>>;
+}
+
+impl Attempt for reqwest::Response {
+    fn status(&self) -> reqwest::StatusCode {
+        self.status()
+    }
+    
+    fn rejected_at(&self) -> Option<DateTime<Utc>> {
+        is_auth_failure(self.status()).then(|| response_date(self.headers())).flatten()
+    }
+}
+
+impl Attempt for Buffered {
+    fn status(&self) -> reqwest::StatusCode {
+        self.status
+    }
+    
+    fn error_code(&self) -> Option<String> {
+        ErrorBody::parse(&self.body).code
+    }
+    
+    fn rejected_at(&self) -> Option<DateTime<Utc>> {
+        if !is_auth_failure(self.status) {
+            return None;
+        }
+        ErrorBody::parse(&self.body).server_time.or(self.date)
+    }
+}
+
+fn is_auth_failure(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 401 | 403)
+}
+
+impl LicenseClient {
+    /// Run `attempt` until it succeeds, fails permanently, or retries run out
+    ///
+    /// `attempt` must build a fresh request each time so signatures and
+    /// timestamps are regenerated. Attempts that also read the body (see
+    /// [`fetch`](Self::fetch)) are retried when it arrives truncated. When
+    /// retries are exhausted the last response or error is returned as-is.
+    ///
+    /// With [clock skew correction](crate::LicenseClientBuilder::correct_clock_skew),
+    /// a response rejected for a skewed timestamp corrects the clock and the
+    /// attempts run once more.
+    pub(crate) async fn with_retries<T, F, Fut>(&self, mut attempt: F) -> Result<T>
+    where
+        T: Attempt,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let result = self.retrying(&mut attempt).await;
+        let server_time = match &result {
+            Ok(response) => self.skewed_server_time(response),
+            Err(_) => None,
+        };
+        match server_time {
+            Some(server_time) => {
+                self.correct_clock(server_time)?;
+                self.retrying(attempt).await
+            }
+            None => result,
+        }
+    }
+    
+    /// Like [`with_retries`](Self::with_retries), without clock skew
+    /// correction, for requests whose timestamp can't change
+    pub(crate) async fn retrying<T, F, Fut>(&self, mut attempt: F) -> Result<T>
+    where
+        T: Attempt,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let retrier = match &self.retrier {
+            Some(retrier) => retrier,
+            None => return attempt().await,
+        };
+        retrier.deposit();
+        
+        let mut retries = 0;
+        loop {
+            let result = attempt().await;
+            let retryable = match &result {
+                Ok(response) => retrier.policy.is_retryable(response),
+                Err(e) => e.is_retryable(),
+            };
+            
+            if !retryable || retries >= retrier.policy.max_retries || !retrier.try_withdraw() {
+                return result;
+            }
+            
+            retries += 1;
+            tokio::time::sleep(retrier.policy.backoff(retries)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_budget_limits_retries() {
+        let retrier = Retrier::new(RetryPolicy {
+            budget: RetryBudget { ratio: 0.5, max_tokens: 2.0 },
+            ..RetryPolicy::default()
+        });
+        
+        assert!(retrier.try_withdraw());
+        assert!(retrier.try_withdraw());
+        assert!(!retrier.try_withdraw());
+        
+        // Two more requests earn one more retry
+        retrier.deposit();
+        retrier.deposit();
+        assert!(retrier.try_withdraw());
+        assert!(!retrier.try_withdraw());
+    }
+    
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(300),
+            ..RetryPolicy::default()
+        };
+        assert!(policy.backoff(1) <= Duration::from_millis(100));
+        assert!(policy.backoff(10) <= Duration::from_millis(300));
+        assert!(policy.backoff(10) >= Duration::from_millis(150));
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_retries_retryable_error_code() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(409).set_body_json(serde_json::json!({
+                "code": "POOL_REBALANCING", "message": "pool is being rebalanced"
+            })))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(10),
+            ..RetryPolicy::default()
+        };
+        
+        // Without the code configured the 409 is final
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .retry_policy(policy.clone())
+            .build();
+        assert!(client.borrow("cad_tool", "alice").await.is_err());
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .retry_policy(policy.retry_on_code("POOL_REBALANCING"))
+            .build();
+        let license = client.borrow("cad_tool", "alice").await.unwrap();
+        assert_eq!(license.id(), "abc-123");
+        license.return_license().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_retried_borrow_reuses_idempotency_key() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        // The first attempt times out, though the server may well have granted it
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({ "id": "abc-123" }))
+                .set_delay(Duration::from_millis(500)))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .timeout(Duration::from_millis(100))
+            .retry_policy(RetryPolicy {
+                base_delay: Duration::from_millis(10),
+                ..RetryPolicy::default()
+            })
+            .build();
+        let license = client.borrow_with_key("cad_tool", "alice", "logical-borrow-1").await.unwrap();
+        assert_eq!(license.idempotency_key(), "logical-borrow-1");
+        
+        let keys: Vec<_> = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|request| request.headers.get("Idempotency-Key").unwrap().to_str().unwrap().to_string())
+            .collect();
+        assert_eq!(keys, ["logical-borrow-1", "logical-borrow-1"]);
+        
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        license.return_license().await.unwrap();
+    }
+}
@@ -0,0 +1,127 @@
+//! Reasons and metadata reported alongside a return
+
+use serde_json::{Map, Value};
+
+use crate::{LicenseHandle, Result};
+
+/// Extra details sent with a return, see [`LicenseHandle::return_with`]
+///
+/// The server records them for reliability analytics, e.g. to correlate
+/// seat usage with tool crashes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReturnOptions {
+    reason: Option<String>,
+    metadata: Map<String, Value>,
+}
+
+impl ReturnOptions {
+    /// Options that send nothing beyond the license ID
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Report why the license is being returned (e.g. `"error"`)
+    pub fn reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+    
+    /// Attach an arbitrary field, e.g. `("exit_code", 137)`
+    ///
+    /// Fields are sent at the top level of the return body, next to `id`
+    /// and `reason`; those two names can't be overridden.
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+    
+    /// The JSON body of a return of license `id`
+    pub(crate) fn body(&self, id: &str) -> Value {
+        let mut body = self.metadata.clone();
+        if let Some(reason) = &self.reason {
+            body.insert("reason".to_string(), reason.clone().into());
+        }
+        body.insert("id".to_string(), id.into());
+        Value::Object(body)
+    }
+}
+
+impl LicenseHandle {
+    /// Return the license, reporting a reason and metadata to the server
+    ///
+    /// Behaves like [`return_license`](Self::return_license), which is the
+    /// same as passing `ReturnOptions::default()`. Journaled returns that
+    /// are replayed after a crash are sent without these details.
+    ///
+    /// # Errors
+    /// Same as [`return_license`](Self::return_license).
+    pub async fn return_with(mut self, options: ReturnOptions) -> Result<()> {
+        let result = self.return_impl(&options).await;
+        // Failed returns stay in the journal, if any; don't try again on drop
+        self.returned = true;
+        result?;
+        self.cancel_expiry_warning();
+        self.renewer = None;
+        self.host_lock = None;
+        self.host_permit = None;
+        self.registration = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LicenseClient, LicenseError};
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_return_with_reason_and_metadata() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .and(body_json(serde_json::json!({ "id": "abc-123", "reason": "error", "exit_code": 137 })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        let license = client.borrow("cad_tool", "alice").await.unwrap();
+        
+        let options = ReturnOptions::new()
+            .reason("error")
+            .metadata("exit_code", 137)
+            .metadata("id", "spoofed");
+        license.return_with(options).await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_failed_return_is_not_resent_on_drop() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        let license = client.borrow("cad_tool", "alice").await.unwrap();
+        
+        let result = license.return_with(ReturnOptions::new()).await;
+        assert!(matches!(result, Err(LicenseError::HttpError(500, _))));
+        client.drain().await;
+    }
+}
@@ -0,0 +1,77 @@
+//! Running a licensed subprocess while holding a seat
+
+use std::process::ExitStatus;
+
+use crate::{LicenseClient, LicenseError, Result};
+
+impl LicenseClient {
+    /// Borrow a seat, run `command` to completion, then return the seat
+    ///
+    /// The seat is returned however the process ends (non-zero exit,
+    /// signal), and also if it fails to start. If the returned future is
+    /// dropped early the process is killed. A std
+    /// [`Command`](std::process::Command) can be passed directly.
+    ///
+    /// # Arguments
+    /// * `tool` - Tool name
+    /// * `user` - Username
+    /// * `command` - The process to run
+    ///
+    /// # Errors
+    /// Borrow errors as for [`borrow`](Self::borrow); `ProcessFailed` if the
+    /// process can't be started or waited on; or the return's error if the
+    /// seat couldn't be returned afterwards.
+    pub async fn run_tool(
+        &self,
+        tool: impl Into<String>,
+        user: impl Into<String>,
+        command: impl Into<tokio::process::Command>,
+    ) -> Result<ExitStatus> {
+        let mut command = command.into();
+        command.kill_on_drop(true);
+        
+        let license = self.borrow(tool, user).await?;
+        let status = match command.spawn() {
+            Ok(mut child) => child.wait().await,
+            Err(e) => Err(e),
+        };
+        license.return_license().await?;
+        
+        status.map_err(|e| LicenseError::ProcessFailed(e.to_string()))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_run_tool_returns_seat_on_failure() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        
+        let mut command = Command::new("sh");
+        command.args(["-c", "exit 3"]);
+        let status = client.run_tool("cad_tool", "alice", command).await.unwrap();
+        assert_eq!(status.code(), Some(3));
+        
+        let missing = Command::new("/nonexistent/cad-tool");
+        let result = client.run_tool("cad_tool", "alice", missing).await;
+        assert!(matches!(result, Err(LicenseError::ProcessFailed(_))));
+    }
+}
@@ -0,0 +1,178 @@
+//! Routing borrows across several license servers by tool name
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+
+use crate::{LicenseClient, LicenseError, LicenseHandle, LicenseStatus, Result};
+
+/// Points per server on the hash ring, to spread tools evenly
+const VIRTUAL_NODES: usize = 64;
+
+type Router = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// A client for tools sharded across several license servers
+///
+/// Each tool is served by one server, picked by (in order) a per-tool
+/// [`route`](Self::route), the custom [`router`](Self::router), or a
+/// consistent hash of the tool name. Hashing is stable across processes,
+/// so every service using the same set of servers agrees on the routing,
+/// and adding or removing a server only moves the tools on its part of the
+/// ring. Handles return to the server they were borrowed from.
+#[derive(Clone)]
+pub struct ShardedLicenseClient {
+    // Keyed by base URL
+    servers: BTreeMap<String, LicenseClient>,
+    // Sorted (hash, base URL) points
+    ring: Vec<(u64, String)>,
+    routes: HashMap<String, String>,
+    router: Option<Router>,
+}
+
+impl std::fmt::Debug for ShardedLicenseClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShardedLicenseClient")
+            .field("servers", &self.servers)
+            .field("routes", &self.routes)
+            .field("router", &self.router.as_ref().map(|_| "<fn>"))
+            .finish_non_exhaustive()
+    }
+}
+
+impl ShardedLicenseClient {
+    /// Shard across `clients`, each configured for one server
+    pub fn new(clients: impl IntoIterator<Item = LicenseClient>) -> Self {
+        let servers: BTreeMap<String, LicenseClient> = clients
+            .into_iter()
+            .map(|client| (client.base_url.clone(), client))
+            .collect();
+        
+        let mut ring: Vec<(u64, String)> = servers
+            .keys()
+            .flat_map(|url| (0..VIRTUAL_NODES).map(move |i| (hash(&format!("{}#{}", url, i)), url.clone())))
+            .collect();
+        ring.sort();
+        
+        Self {
+            servers,
+            ring,
+            routes: HashMap::new(),
+            router: None,
+        }
+    }
+    
+    /// Always send `tool` to the server at `base_url`
+    pub fn route(mut self, tool: impl Into<String>, base_url: impl Into<String>) -> Self {
+        self.routes.insert(tool.into(), base_url.into());
+        self
+    }
+    
+    /// Pick servers with `router`, which returns the base URL for a tool
+    ///
+    /// Tools for which it returns `None` fall back to consistent hashing.
+    /// Per-tool [`route`](Self::route)s take precedence.
+    pub fn router<F>(mut self, router: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.router = Some(Arc::new(router));
+        self
+    }
+    
+    /// The client for the server that serves `tool`
+    ///
+    /// # Errors
+    /// Returns `InvalidInput` if no servers are configured, or if a route
+    /// names a server that isn't one of them.
+    pub fn server_for(&self, tool: &str) -> Result<&LicenseClient> {
+        let url = match self.routes.get(tool) {
+            Some(url) => Some(url.clone()),
+            None => self.router.as_ref().and_then(|router| router(tool)),
+        };
+        
+        match url {
+            Some(url) => self
+                .servers
+                .get(&url)
+                .ok_or_else(|| LicenseError::InvalidInput(format!("no server {} configured for tool {}", url, tool))),
+            None => {
+                let point = hash(tool);
+                let index = self.ring.partition_point(|(h, _)| *h < point);
+                let (_, url) = self
+                    .ring
+                    .get(index)
+                    .or_else(|| self.ring.first())
+                    .ok_or_else(|| LicenseError::InvalidInput("no license servers configured".to_string()))?;
+                Ok(&self.servers[url])
+            }
+        }
+    }
+    
+    /// Borrow a license for `tool` from the server that serves it
+    ///
+    /// # Errors
+    /// Same as [`server_for`](Self::server_for) and
+    /// [`LicenseClient::borrow`].
+    pub async fn borrow(&self, tool: impl Into<String>, user: impl Into<String>) -> Result<LicenseHandle> {
+        let tool = tool.into();
+        self.server_for(&tool)?.borrow(tool, user).await
+    }
+    
+    /// Get the status of `tool` from the server that serves it
+    pub async fn get_status(&self, tool: impl Into<String>) -> Result<LicenseStatus> {
+        let tool = tool.into();
+        self.server_for(&tool)?.get_status(tool).await
+    }
+}
+
+fn hash(key: &str) -> u64 {
+    let digest = Sha256::digest(key.as_bytes());
+    u64::from_be_bytes(digest[..8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    async fn shard(id: &str) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": id })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        server
+    }
+    
+    #[tokio::test]
+    async fn test_sharded_borrow_routes_by_tool() {
+        let (first, second) = (shard("from-first").await, shard("from-second").await);
+        let client = |server: &MockServer| LicenseClient::builder(server.uri()).enable_security(false).build();
+        let sharded = ShardedLicenseClient::new([client(&first), client(&second)]);
+        
+        // Hashing is deterministic and spreads tools over both servers
+        let tools: Vec<String> = (0..20).map(|i| format!("tool_{}", i)).collect();
+        let urls: Vec<&str> = tools
+            .iter()
+            .map(|tool| sharded.server_for(tool).unwrap().base_url.as_str())
+            .collect();
+        assert!(urls.contains(&first.uri().as_str()) && urls.contains(&second.uri().as_str()));
+        assert_eq!(sharded.server_for("tool_3").unwrap().base_url, urls[3]);
+        
+        let sharded = sharded.route("cad_tool", second.uri());
+        let license = sharded.borrow("cad_tool", "alice").await.unwrap();
+        assert_eq!(license.id(), "from-second");
+        license.return_license().await.unwrap();
+        
+        assert_eq!(first.received_requests().await.unwrap().len(), 0);
+        assert_eq!(second.received_requests().await.unwrap().len(), 2);
+    }
+}
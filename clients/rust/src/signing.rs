@@ -0,0 +1,203 @@
+//! Pluggable request signing
+
+use std::sync::Arc;
+
+/// How requests authenticate with the server
+///
+/// Set with [`LicenseClientBuilder::auth`](crate::LicenseClientBuilder::auth).
+/// The older `enable_security` flag selects between `Hmac` (with the
+/// configured credentials) and `None`.
+#[derive(Clone, PartialEq, Eq)]
+pub enum AuthMode {
+    /// No authentication headers
+    None,
+    /// HMAC-SHA256 signatures over tool, user and timestamp, keyed by the
+    /// vendor secret (`X-Signature`, `X-Timestamp`, `X-Vendor-ID`)
+    Hmac { vendor_id: String, secret: String },
+    /// A static `Authorization: Bearer` token
+    Bearer(String),
+}
+
+impl AuthMode {
+    /// Whether requests carry any authentication
+    pub(crate) fn is_enabled(&self) -> bool {
+        !matches!(self, AuthMode::None)
+    }
+    
+    /// The vendor ID sent with HMAC signatures
+    pub(crate) fn vendor_id(&self) -> Option<&str> {
+        match self {
+            AuthMode::Hmac { vendor_id, .. } => Some(vendor_id),
+            _ => None,
+        }
+    }
+}
+
+// Secrets are redacted so clients can be logged safely
+impl std::fmt::Debug for AuthMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthMode::None => f.write_str("None"),
+            AuthMode::Hmac { vendor_id, .. } => f
+                .debug_struct("Hmac")
+                .field("vendor_id", vendor_id)
+                .field("secret", &"<redacted>")
+                .finish(),
+            AuthMode::Bearer(_) => f.debug_tuple("Bearer").field(&"<redacted>").finish(),
+        }
+    }
+}
+
+/// What a [`Signer`] gets to sign a request with
+#[derive(Debug, Clone, Copy)]
+pub struct SignContext<'a> {
+    /// Tool name as sent to the server (with any tool prefix)
+    pub tool: &'a str,
+    /// Username
+    pub user: &'a str,
+    /// Unix timestamp (seconds) the request is signed for
+    pub timestamp: &'a str,
+    /// The client's API key, if any
+    pub api_key: Option<&'a str>,
+}
+
+/// Authentication headers produced by a [`Signer`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SignedHeaders {
+    /// Header names and values, added to the request in order
+    pub headers: Vec<(String, String)>,
+}
+
+impl SignedHeaders {
+    /// No headers yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Add a header
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+/// A function producing all authentication headers of a signed request
+///
+/// See [`LicenseClientBuilder::signer`](crate::LicenseClientBuilder::signer).
+pub type Signer = Arc<dyn Fn(&SignContext) -> SignedHeaders + Send + Sync>;
+
+/// Shared signer handle, so the client and builder can stay `Clone` + `Debug`
+#[derive(Clone)]
+pub(crate) struct SharedSigner(pub(crate) Signer);
+
+impl std::fmt::Debug for SharedSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Signer")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LicenseClient;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_custom_signer_replaces_builtin_headers() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .and(header("x-auth", "cad_tool:alice"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .signer(Arc::new(|ctx: &SignContext| {
+                SignedHeaders::new().header("X-Auth", format!("{}:{}", ctx.tool, ctx.user))
+            }))
+            .build();
+        let license = client.borrow("cad_tool", "alice").await.unwrap();
+        
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests[0].headers.get("x-signature").is_none());
+        license.return_license().await.unwrap();
+    }
+    
+    #[test]
+    fn test_auth_mode_headers() {
+        let names = |mode: AuthMode| {
+            let client = LicenseClient::builder("http://localhost:8000").api_key(None).auth(mode).build();
+            client.security_headers("cad_tool", "alice", "1700000000".to_string()).unwrap().headers
+        };
+        
+        assert!(names(AuthMode::None).is_empty());
+        assert_eq!(
+            names(AuthMode::Bearer("token-1".to_string())),
+            vec![("Authorization".to_string(), "Bearer token-1".to_string())]
+        );
+        let hmac = names(AuthMode::Hmac {
+            vendor_id: "acme".to_string(),
+            secret: "secret".to_string(),
+        });
+        let hmac: Vec<&str> = hmac.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(hmac, ["X-Signature", "X-Timestamp", "X-Vendor-ID"]);
+        
+        // The old flag still switches between HMAC and nothing
+        let client = LicenseClient::builder("http://localhost:8000").enable_security(false).build();
+        assert_eq!(client.auth, AuthMode::None);
+    }
+    
+    #[tokio::test]
+    async fn test_bearer_mode_authenticates_borrow_return_and_status() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .and(header("authorization", "Bearer token-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .and(header("authorization", "Bearer token-1"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .and(header("authorization", "Bearer token-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tool": "cad_tool", "total": 5, "borrowed": 0, "available": 5
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/borrowed"))
+            .and(header("authorization", "Bearer token-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .auth(AuthMode::Bearer("token-1".to_string()))
+            .build();
+        let license = client.borrow("cad_tool", "alice").await.unwrap();
+        license.return_license().await.unwrap();
+        client.get_status("cad_tool").await.unwrap();
+        assert!(client.list_borrowed().await.unwrap().is_empty());
+        
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests.iter().all(|request| request.headers.get("x-signature").is_none()));
+    }
+}
@@ -0,0 +1,107 @@
+//! Minimal Server-Sent Events reading for the streaming endpoints
+
+use std::collections::VecDeque;
+
+use crate::{http_error, LicenseClient, Result};
+
+/// One dispatched event; only the fields the client uses are kept
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SseEvent {
+    pub(crate) event: Option<String>,
+    pub(crate) data: String,
+}
+
+/// Incremental parser fed with response chunks as they arrive
+///
+/// Chunks may split lines (and UTF-8 sequences) anywhere, so bytes are
+/// buffered until a full line is available.
+#[derive(Debug, Default)]
+pub(crate) struct SseParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+    ready: VecDeque<SseEvent>,
+}
+
+impl SseParser {
+    pub(crate) fn push(&mut self, chunk: &[u8]) {
+        self.buffer.extend_from_slice(chunk);
+        while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            self.line(line.trim_end_matches(['\n', '\r']));
+        }
+    }
+    
+    /// The next complete event, if one has been dispatched
+    pub(crate) fn next_event(&mut self) -> Option<SseEvent> {
+        self.ready.pop_front()
+    }
+    
+    fn line(&mut self, line: &str) {
+        // A blank line dispatches the event; comments start with a colon
+        if line.is_empty() {
+            let event = self.event.take();
+            if !self.data.is_empty() {
+                let data = std::mem::take(&mut self.data).join("\n");
+                self.ready.push_back(SseEvent { event, data });
+            }
+            return;
+        }
+        if line.starts_with(':') {
+            return;
+        }
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            _ => {}
+        }
+    }
+}
+
+impl LicenseClient {
+    /// Open an event stream at `url`
+    ///
+    /// Returns `None` when the server has no such stream (`404`, `405` or
+    /// `501`), so callers can fall back to polling.
+    pub(crate) async fn open_event_stream(&self, url: &str) -> Result<Option<reqwest::Response>> {
+        let request = self
+            .authorized(self.client.get(url))
+            .header(reqwest::header::ACCEPT, "text/event-stream");
+        let response = self.send(request).await?;
+        let status = response.status();
+        if matches!(status.as_u16(), 404 | 405 | 501) {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            return Err(http_error(status, &response.bytes().await.unwrap_or_default()));
+        }
+        Ok(Some(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parser_handles_split_chunks_and_comments() {
+        let mut parser = SseParser::default();
+        parser.push(b": keep-alive\n\nevent: sta");
+        parser.push(b"tus\r\ndata: {\"a\":\ndata:1}\r\n");
+        assert_eq!(parser.next_event(), None);
+        
+        parser.push(b"\ndata: second\n\n");
+        assert_eq!(
+            parser.next_event(),
+            Some(SseEvent {
+                event: Some("status".to_string()),
+                data: "{\"a\":\n1}".to_string(),
+            })
+        );
+        assert_eq!(parser.next_event().map(|event| event.data), Some("second".to_string()));
+        assert_eq!(parser.next_event(), None);
+    }
+}
@@ -0,0 +1,137 @@
+//! Request counters for observing client load
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use reqwest::header::ACCEPT;
+
+use crate::{LicenseClient, Result};
+
+/// Snapshot of the client's request counters
+///
+/// reqwest doesn't expose its connection pool, so these are counted by the
+/// client itself and shared by all of its clones. They are approximate: the
+/// fields are read one at a time and may not be mutually consistent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Requests currently waiting for a response
+    pub in_flight: u64,
+    /// Requests sent since the client was built
+    pub total_requests: u64,
+    /// Requests that failed in transport or got a 5xx response
+    pub total_errors: u64,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct RequestCounters {
+    in_flight: AtomicU64,
+    total_requests: AtomicU64,
+    total_errors: AtomicU64,
+}
+
+/// Decrements `in_flight` when dropped, so cancelled requests are counted too
+struct InFlight<'a>(&'a RequestCounters);
+
+impl<'a> InFlight<'a> {
+    fn start(counters: &'a RequestCounters) -> Self {
+        counters.total_requests.fetch_add(1, Ordering::Relaxed);
+        counters.in_flight.fetch_add(1, Ordering::Relaxed);
+        Self(counters)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl LicenseClient {
+    /// Snapshot of request counts, for correlating latency with load
+    pub fn pool_stats(&self) -> PoolStats {
+        PoolStats {
+            in_flight: self.counters.in_flight.load(Ordering::Relaxed),
+            total_requests: self.counters.total_requests.load(Ordering::Relaxed),
+            total_errors: self.counters.total_errors.load(Ordering::Relaxed),
+        }
+    }
+    
+    /// Send a request, keeping the request counters up to date
+    pub(crate) async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let request = match self.accept_header() {
+            Some(accept) => request.header(ACCEPT, accept),
+            None => request,
+        };
+        let _in_flight = InFlight::start(&self.counters);
+        let result = request.send().await;
+        self.count_outcome(&result);
+        let response = result?;
+        self.check_api_version(response.status())?;
+        Ok(response)
+    }
+    
+    /// Execute a prebuilt request, keeping the request counters up to date
+    pub(crate) async fn execute(&self, mut request: reqwest::Request) -> Result<reqwest::Response> {
+        if let Some(accept) = self.accept_header() {
+            request.headers_mut().insert(ACCEPT, accept);
+        }
+        let _in_flight = InFlight::start(&self.counters);
+        let result = self.client.execute(request).await;
+        self.count_outcome(&result);
+        let response = result?;
+        self.check_api_version(response.status())?;
+        Ok(response)
+    }
+    
+    fn count_outcome(&self, result: &reqwest::Result<reqwest::Response>) {
+        let failed = match result {
+            Ok(response) => response.status().is_server_error(),
+            Err(_) => true,
+        };
+        if failed {
+            self.counters.total_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LicenseError;
+    use std::time::Duration;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_pool_stats_count_requests_and_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tool": "cad_tool", "total": 5, "borrowed": 0, "available": 5
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/ide_tool/status"))
+            .respond_with(ResponseTemplate::new(500).set_delay(Duration::from_millis(200)))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        assert_eq!(client.pool_stats(), PoolStats::default());
+        client.get_status("cad_tool").await.unwrap();
+        
+        let slow = tokio::spawn({
+            let client = client.clone();
+            async move { client.get_status("ide_tool").await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(client.pool_stats().in_flight, 1);
+        
+        assert!(matches!(slow.await.unwrap(), Err(LicenseError::HttpError(500, _))));
+        assert_eq!(
+            client.pool_stats(),
+            PoolStats { in_flight: 0, total_requests: 2, total_errors: 1 }
+        );
+    }
+}
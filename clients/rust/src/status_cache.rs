@@ -0,0 +1,213 @@
+//! Recently observed pool status, used to fail fast on empty pools and to
+//! answer repeated status requests
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{LicenseClient, LicenseStatus};
+
+/// The latest status seen for each tool, keyed by the server's tool name
+///
+/// Fed by [`LicenseClient::get_status`] and
+/// [`LicenseClient::get_all_statuses`], shared by all clones of a client.
+#[derive(Debug, Default)]
+pub(crate) struct StatusCache {
+    snapshots: Mutex<HashMap<String, (Instant, LicenseStatus)>>,
+    // The last full list from `get_all_statuses`
+    all: Mutex<Option<(Instant, Vec<LicenseStatus>)>>,
+}
+
+impl StatusCache {
+    pub(crate) fn record(&self, statuses: &[LicenseStatus]) {
+        let now = Instant::now();
+        let mut snapshots = self.snapshots.lock().unwrap();
+        for status in statuses {
+            snapshots.insert(status.tool.clone(), (now, status.clone()));
+        }
+    }
+    
+    /// Record the full list of statuses, as well as each tool's
+    pub(crate) fn record_all(&self, statuses: &[LicenseStatus]) {
+        self.record(statuses);
+        *self.all.lock().unwrap() = Some((Instant::now(), statuses.to_vec()));
+    }
+    
+    /// Forget `tool`'s snapshot, e.g. after this client freed one of its seats
+    pub(crate) fn invalidate(&self, tool: &str) {
+        self.snapshots.lock().unwrap().remove(tool);
+        *self.all.lock().unwrap() = None;
+    }
+    
+    /// `tool`'s snapshot, if it is younger than `ttl`
+    pub(crate) fn get(&self, tool: &str, ttl: Duration) -> Option<LicenseStatus> {
+        match self.snapshots.lock().unwrap().get(tool) {
+            Some((taken, status)) if taken.elapsed() < ttl => Some(status.clone()),
+            _ => None,
+        }
+    }
+    
+    /// The full list of statuses, if it is younger than `ttl`
+    pub(crate) fn get_all(&self, ttl: Duration) -> Option<Vec<LicenseStatus>> {
+        match &*self.all.lock().unwrap() {
+            Some((taken, statuses)) if taken.elapsed() < ttl => Some(statuses.clone()),
+            _ => None,
+        }
+    }
+    
+    /// Whether a snapshot younger than `max_age` shows `tool` with no seat left
+    pub(crate) fn is_exhausted(&self, tool: &str, max_age: Duration) -> bool {
+        match self.snapshots.lock().unwrap().get(tool) {
+            Some((taken, status)) => {
+                taken.elapsed() < max_age && status.available <= 0 && status.overage >= status.max_overage
+            }
+            None => false,
+        }
+    }
+}
+
+impl LicenseClient {
+    /// Fail the borrow of `tool` without a request if the pool is known empty
+    pub(crate) fn fast_fail(&self, tool: &str) -> bool {
+        match self.fast_fail {
+            Some(max_age) => self.status_cache.is_exhausted(&self.qualified_tool(tool), max_age),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LicenseError;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_statuses_are_cached_for_ttl() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "tool": "cad_tool", "total": 2, "borrowed": 1, "available": 1 }
+            ])))
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/sim_tool/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tool": "sim_tool", "total": 2, "borrowed": 0, "available": 2
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .status_cache_ttl(Duration::from_millis(200))
+            .build();
+        
+        assert_eq!(client.get_all_statuses().await.unwrap().len(), 1);
+        assert_eq!(client.get_all_statuses().await.unwrap().len(), 1);
+        // Tools seen in the full list are served from it
+        assert_eq!(client.get_status("cad_tool").await.unwrap().available, 1);
+        assert_eq!(client.get_status("sim_tool").await.unwrap().available, 2);
+        assert_eq!(client.get_status("sim_tool").await.unwrap().available, 2);
+        
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        client.get_all_statuses().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_invalidates_cached_status() {
+        let server = MockServer::start().await;
+        let cad_status = |borrowed: i32| {
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tool": "cad_tool", "total": 4, "borrowed": borrowed, "available": 4 - borrowed,
+                "commit": 2, "max_overage": 2, "overage": 0
+            }))
+        };
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .respond_with(cad_status(1))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        // Someone else takes the last committed seat
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .respond_with(cad_status(2))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .status_cache_ttl(Duration::from_secs(60))
+            .build();
+        assert_eq!(client.get_status("cad_tool").await.unwrap().available, 3);
+        
+        // The commit check asks the server rather than trusting the cache
+        let result = client.borrow_commit_only("cad_tool", "alice").await;
+        assert!(matches!(result, Err(LicenseError::WouldExceedCommit(_))));
+        assert_eq!(client.get_status("cad_tool").await.unwrap().available, 2);
+        
+        // A successful borrow drops the cached status
+        let license = client.borrow("cad_tool", "alice").await.unwrap();
+        client.get_status("cad_tool").await.unwrap();
+        let status_requests = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|request| request.method.as_str() == "GET")
+            .count();
+        assert_eq!(status_requests, 3);
+        license.return_license().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_fails_fast_on_fresh_empty_status() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "tool": "cad_tool", "total": 2, "borrowed": 2, "available": 0, "commit": 2, "max_overage": 0, "overage": 0 },
+                { "tool": "sim_tool", "total": 2, "borrowed": 1, "available": 1, "commit": 2, "max_overage": 0, "overage": 0 }
+            ])))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(409).set_body_json(serde_json::json!({ "detail": "No licenses available" })))
+            .expect(2)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .fast_fail(Duration::from_millis(200))
+            .build();
+        
+        // Nothing cached yet, so the server is asked
+        assert!(matches!(client.borrow("cad_tool", "alice").await, Err(LicenseError::NoLicensesAvailable(_))));
+        
+        client.get_all_statuses().await.unwrap();
+        assert!(matches!(client.borrow("cad_tool", "alice").await, Err(LicenseError::NoLicensesAvailable(_))));
+        
+        // Stale snapshots are ignored
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert!(matches!(client.borrow("cad_tool", "alice").await, Err(LicenseError::NoLicensesAvailable(_))));
+    }
+}
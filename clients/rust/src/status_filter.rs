@@ -0,0 +1,125 @@
+//! Filtered, paginated status listings for servers with many tools
+
+use crate::{parse_json, LicenseClient, LicenseStatus, Result};
+
+/// Which statuses [`LicenseClient::get_statuses_filtered`] asks for
+///
+/// Every criterion is optional; the default filter matches all tools.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StatusFilter {
+    name_contains: Option<String>,
+    only_available: bool,
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
+impl StatusFilter {
+    /// A filter matching every tool
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Only tools whose name contains `text` (sent as `name`)
+    pub fn name_contains(mut self, text: impl Into<String>) -> Self {
+        self.name_contains = Some(text.into());
+        self
+    }
+    
+    /// Only tools with a seat available (sent as `only_available`)
+    pub fn only_available(mut self, only_available: bool) -> Self {
+        self.only_available = only_available;
+        self
+    }
+    
+    /// Return at most `limit` statuses
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+    
+    /// Skip the first `offset` matching statuses
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+    
+    /// The query parameters for the criteria that are set
+    fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = Vec::new();
+        if let Some(text) = &self.name_contains {
+            query.push(("name", text.clone()));
+        }
+        if self.only_available {
+            query.push(("only_available", "true".to_string()));
+        }
+        if let Some(limit) = self.limit {
+            query.push(("limit", limit.to_string()));
+        }
+        if let Some(offset) = self.offset {
+            query.push(("offset", offset.to_string()));
+        }
+        query
+    }
+    
+    fn matches(&self, status: &LicenseStatus) -> bool {
+        self.name_contains.as_ref().is_none_or(|text| status.tool.contains(text.as_str()))
+            && (!self.only_available || status.available > 0)
+    }
+}
+
+impl LicenseClient {
+    /// Get the statuses of the tools matching `filter`
+    ///
+    /// The criteria are sent as query parameters of the status listing, so
+    /// large deployments only send the page that was asked for. Name and
+    /// availability are checked again here for servers that ignore them.
+    pub async fn get_statuses_filtered(&self, filter: StatusFilter) -> Result<Vec<LicenseStatus>> {
+        let url = self.endpoints().all_statuses;
+        let query = filter.query();
+        
+        let response = self
+            .fetch(|| self.authorized(self.client.get(&url).query(&query)))
+            .await?
+            .error_for_status()?;
+        let mut statuses: Vec<LicenseStatus> = parse_json(&response.body)?;
+        statuses.retain(|status| filter.matches(status));
+        Ok(statuses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_filter_is_sent_as_query_and_applied() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/status"))
+            .and(query_param("name", "cad"))
+            .and(query_param("only_available", "true"))
+            .and(query_param("limit", "10"))
+            .and(query_param("offset", "20"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "tool": "cad_tool", "total": 2, "borrowed": 1, "available": 1 },
+                { "tool": "cad_viewer", "total": 2, "borrowed": 2, "available": 0 },
+                { "tool": "sim_tool", "total": 2, "borrowed": 0, "available": 2 }
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        let filter = StatusFilter::new()
+            .name_contains("cad")
+            .only_available(true)
+            .limit(10)
+            .offset(20);
+        let statuses = client.get_statuses_filtered(filter).await.unwrap();
+        
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].tool, "cad_tool");
+    }
+}
@@ -0,0 +1,105 @@
+//! Parsing of timestamps sent by the license server
+
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Utc};
+
+use crate::{LicenseError, Result};
+
+/// A timestamp received from the server, normalized to UTC
+///
+/// Accepts RFC 3339 timestamps with `Z` or a numeric offset (`+02:00`).
+/// Timestamps without any offset are assumed to be UTC, and a warning is
+/// printed since the server should always send one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerTimestamp {
+    utc: DateTime<Utc>,
+    offset: Option<FixedOffset>,
+}
+
+impl ServerTimestamp {
+    /// Parse a timestamp string as sent by the server
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        
+        if let Ok(parsed) = DateTime::parse_from_rfc3339(value) {
+            return Ok(Self {
+                utc: parsed.with_timezone(&Utc),
+                offset: Some(*parsed.offset()),
+            });
+        }
+        
+        for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"] {
+            if let Ok(naive) = NaiveDateTime::parse_from_str(value, format) {
+                eprintln!("Warning: server timestamp '{}' has no UTC offset, assuming UTC", value);
+                return Ok(Self {
+                    utc: naive.and_utc(),
+                    offset: None,
+                });
+            }
+        }
+        
+        Err(LicenseError::InvalidResponse(format!("Invalid timestamp: {}", value)))
+    }
+    
+    /// The timestamp in UTC
+    pub fn utc(&self) -> DateTime<Utc> {
+        self.utc
+    }
+    
+    /// The UTC offset the server sent, or `None` if it sent none
+    pub fn offset(&self) -> Option<FixedOffset> {
+        self.offset
+    }
+    
+    /// The timestamp in the server's original offset (UTC if none was sent)
+    pub fn original(&self) -> DateTime<FixedOffset> {
+        let offset = self.offset.unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+        self.utc.with_timezone(&offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    
+    fn noon_utc() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap()
+    }
+    
+    #[test]
+    fn test_parse_zulu() {
+        let ts = ServerTimestamp::parse("2025-03-01T12:00:00Z").unwrap();
+        assert_eq!(ts.utc(), noon_utc());
+        assert_eq!(ts.offset(), FixedOffset::east_opt(0));
+    }
+    
+    #[test]
+    fn test_parse_zero_offset() {
+        let ts = ServerTimestamp::parse("2025-03-01T12:00:00.000000+00:00").unwrap();
+        assert_eq!(ts.utc(), noon_utc());
+        assert_eq!(ts.offset(), FixedOffset::east_opt(0));
+    }
+    
+    #[test]
+    fn test_parse_positive_offset() {
+        let ts = ServerTimestamp::parse("2025-03-01T14:00:00+02:00").unwrap();
+        assert_eq!(ts.utc(), noon_utc());
+        assert_eq!(ts.offset(), FixedOffset::east_opt(2 * 3600));
+        assert_eq!(ts.original().to_rfc3339(), "2025-03-01T14:00:00+02:00");
+    }
+    
+    #[test]
+    fn test_parse_missing_offset_assumes_utc() {
+        let ts = ServerTimestamp::parse("2025-03-01T12:00:00").unwrap();
+        assert_eq!(ts.utc(), noon_utc());
+        assert_eq!(ts.offset(), None);
+    }
+    
+    #[test]
+    fn test_parse_invalid() {
+        assert!(matches!(
+            ServerTimestamp::parse("yesterday"),
+            Err(LicenseError::InvalidResponse(_))
+        ));
+    }
+}
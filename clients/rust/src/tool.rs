@@ -0,0 +1,98 @@
+//! Names of the tools the server ships with
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::LicenseError;
+
+/// A tool known to the server
+///
+/// Converts into a tool name wherever one is expected, so
+/// `client.borrow(Tool::CanBusAnalyzerPro, "alice")` and
+/// `client.borrow("CAN Bus Analyzer Pro", "alice")` are the same borrow.
+/// Custom tools keep using plain strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tool {
+    EcuDevelopmentSuite,
+    GreenHillsMultiIde,
+    AutosarConfigurationTool,
+    CanBusAnalyzerPro,
+    ModelBasedDesignStudio,
+}
+
+impl Tool {
+    /// Every known tool
+    pub const ALL: [Tool; 5] = [
+        Tool::EcuDevelopmentSuite,
+        Tool::GreenHillsMultiIde,
+        Tool::AutosarConfigurationTool,
+        Tool::CanBusAnalyzerPro,
+        Tool::ModelBasedDesignStudio,
+    ];
+    
+    /// The tool's name on the server
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Tool::EcuDevelopmentSuite => "ECU Development Suite",
+            Tool::GreenHillsMultiIde => "GreenHills Multi IDE",
+            Tool::AutosarConfigurationTool => "AUTOSAR Configuration Tool",
+            Tool::CanBusAnalyzerPro => "CAN Bus Analyzer Pro",
+            Tool::ModelBasedDesignStudio => "Model-Based Design Studio",
+        }
+    }
+}
+
+impl fmt::Display for Tool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Tool {
+    type Err = LicenseError;
+    
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Tool::ALL
+            .into_iter()
+            .find(|tool| tool.as_str() == name)
+            .ok_or_else(|| LicenseError::InvalidInput(format!("unknown tool: {}", name)))
+    }
+}
+
+impl From<Tool> for String {
+    fn from(tool: Tool) -> Self {
+        tool.as_str().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LicenseClient;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[test]
+    fn test_tool_names_round_trip() {
+        for tool in Tool::ALL {
+            assert_eq!(tool.as_str().parse::<Tool>().unwrap(), tool);
+        }
+        assert!(matches!("Custom Tool".parse::<Tool>(), Err(LicenseError::InvalidInput(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_accepts_tool() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .and(body_partial_json(serde_json::json!({ "tool": "CAN Bus Analyzer Pro" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        let license = client.borrow(Tool::CanBusAnalyzerPro, "alice").await.unwrap();
+        assert_eq!(license.tool(), "CAN Bus Analyzer Pro");
+    }
+}
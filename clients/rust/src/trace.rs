@@ -0,0 +1,159 @@
+//! Captured HTTP exchanges for debugging
+
+use reqwest::header::HeaderMap;
+
+use crate::{LicenseClient, LicenseHandle, PendingBorrow, Result};
+
+/// A single HTTP request/response exchange, as sent and received
+///
+/// Produced by [`LicenseClient::borrow_traced`]. Header values that are not
+/// valid UTF-8 are shown as `<binary>`. `status` is 0 and the response
+/// fields are empty if no response arrived, and the whole trace is empty if
+/// the borrow failed before a request was sent.
+#[derive(Debug, Clone, Default)]
+pub struct HttpTrace {
+    pub method: String,
+    pub url: String,
+    pub request_headers: Vec<(String, String)>,
+    pub request_body: Option<String>,
+    pub status: u16,
+    pub response_headers: Vec<(String, String)>,
+    pub response_body: String,
+}
+
+impl HttpTrace {
+    pub(crate) fn from_request(request: &reqwest::Request) -> Self {
+        Self {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            request_headers: header_pairs(request.headers()),
+            request_body: request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map(|bytes| String::from_utf8_lossy(bytes).into_owned()),
+            ..Self::default()
+        }
+    }
+    
+    pub(crate) fn record_response_head(&mut self, response: &reqwest::Response) {
+        self.status = response.status().as_u16();
+        self.response_headers = header_pairs(response.headers());
+    }
+}
+
+impl LicenseClient {
+    /// Borrow a license and capture the full HTTP exchange
+    ///
+    /// Behaves like [`borrow`](Self::borrow) but also returns an
+    /// [`HttpTrace`] with the exact request and response, for attaching to
+    /// bug reports. The trace is returned whether or not the borrow
+    /// succeeded, since failed exchanges (authentication, proxies) are the
+    /// ones most worth reporting. Hedging and retries are not applied so the
+    /// trace describes a single request. The trace includes authentication
+    /// headers, so treat it as sensitive.
+    pub async fn borrow_traced(
+        &self,
+        tool: impl Into<String>,
+        user: impl Into<String>,
+    ) -> (Result<LicenseHandle>, HttpTrace) {
+        let pending = PendingBorrow::new(tool, user, Self::generate_idempotency_key());
+        let mut trace = HttpTrace::default();
+        let result = self.send_traced_borrow(pending, &mut trace).await;
+        (result, trace)
+    }
+    
+    async fn send_traced_borrow(&self, pending: PendingBorrow, trace: &mut HttpTrace) -> Result<LicenseHandle> {
+        let guards = self.guard_borrow(&pending).await?;
+        
+        let request = self.borrow_request(&pending)?.build()?;
+        *trace = HttpTrace::from_request(&request);
+        
+        let response = self.execute(request).await?;
+        let status = response.status();
+        trace.record_response_head(&response);
+        let body = self.read_body_capped(response).await?;
+        trace.response_body = String::from_utf8_lossy(&body).into_owned();
+        
+        let mut handle = self.handle_from_borrow_body(status, &body, pending)?;
+        self.finish_borrow(&mut handle, guards);
+        Ok(handle)
+    }
+}
+
+fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                value.to_str().unwrap_or("<binary>").to_string(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LicenseError;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_borrow_traced_captures_success_and_failure() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(403)
+                .insert_header("X-Proxy", "corporate")
+                .set_body_string("signature rejected"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        // The watchdog only sees handles registered like any other borrow's
+        let reports = Arc::new(AtomicUsize::new(0));
+        let sink = reports.clone();
+        let client = LicenseClient::builder(server.uri())
+            .watchdog(Duration::from_millis(50), move |_| {
+                sink.fetch_add(1, Ordering::Relaxed);
+            })
+            .build();
+        let (license, trace) = client.borrow_traced("cad_tool", "alice").await;
+        let license = license.unwrap();
+        assert_eq!(license.id(), "abc-123");
+        assert_eq!((trace.method.as_str(), trace.status), ("POST", 200));
+        assert_eq!(trace.url, format!("{}/licenses/borrow", server.uri()));
+        assert!(trace.request_headers.iter().any(|(name, _)| name == "x-signature"));
+        assert!(trace.request_body.unwrap().contains("\"alice\""));
+        assert!(trace.response_body.contains("abc-123"));
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(reports.load(Ordering::Relaxed), 1);
+        license.return_license().await.unwrap();
+        
+        let (license, trace) = client.borrow_traced("cad_tool", "alice").await;
+        assert!(matches!(license, Err(LicenseError::AuthenticationFailed { status: 403, .. })));
+        assert_eq!(trace.status, 403);
+        assert!(trace.response_headers.contains(&("x-proxy".to_string(), "corporate".to_string())));
+        assert_eq!(trace.response_body, "signature rejected");
+        
+        // Rejected before anything is sent
+        let (license, trace) = client.borrow_traced("cad_tool", " ").await;
+        assert!(matches!(license, Err(LicenseError::InvalidInput(_))));
+        assert_eq!(trace.status, 0);
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+    }
+}
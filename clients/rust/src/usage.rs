@@ -0,0 +1,200 @@
+//! Per-user views of who currently holds a tool's seats
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use urlencoding::encode;
+
+use crate::{parse_json, LicenseClient, Result};
+
+/// An outstanding borrow as listed by the server
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct BorrowedLicense {
+    /// License ID
+    pub id: String,
+    /// Tool name, as reported by the server
+    pub tool: String,
+    /// User holding the seat
+    pub user: String,
+    /// When the seat was borrowed, as reported by the server (empty if not)
+    #[serde(default)]
+    pub borrowed_at: String,
+}
+
+impl LicenseClient {
+    /// List the outstanding borrows of a tool
+    ///
+    /// # Arguments
+    /// * `tool` - Name of the tool
+    ///
+    /// # Errors
+    /// Returns `HttpError` if the server rejects the request.
+    pub async fn holders(&self, tool: impl Into<String>) -> Result<Vec<BorrowedLicense>> {
+        let tool = self.qualified_tool(&tool.into()).into_owned();
+        let url = format!("{}?tool={}", self.endpoints().borrows, encode(&tool));
+        
+        // Servers that don't filter by tool return every borrow, so filter here too
+        let mut borrows = self.fetch_borrows(&url).await?;
+        borrows.retain(|borrow| borrow.tool == tool);
+        Ok(borrows)
+    }
+    
+    /// List every outstanding borrow, of all tools and users
+    ///
+    /// Complements [`get_all_statuses`](Self::get_all_statuses), which only
+    /// has the counts, e.g. for a dashboard of who holds what.
+    ///
+    /// # Errors
+    /// Returns `HttpError` if the server rejects the request.
+    pub async fn list_borrowed(&self) -> Result<Vec<BorrowedLicense>> {
+        self.fetch_borrows(&self.endpoints().borrowed).await
+    }
+    
+    /// GET a list of outstanding borrows from `url`
+    pub(crate) async fn fetch_borrows(&self, url: &str) -> Result<Vec<BorrowedLicense>> {
+        let response = self.fetch(|| self.authorized(self.client.get(url))).await?.error_for_status()?;
+        parse_json(&response.body)
+    }
+    
+    /// Count the seats of a tool held by each user
+    ///
+    /// Users without a seat are not included.
+    ///
+    /// # Arguments
+    /// * `tool` - Name of the tool
+    ///
+    /// # Errors
+    /// Same as [`holders`](Self::holders).
+    pub async fn usage_by_user(&self, tool: impl Into<String>) -> Result<HashMap<String, i32>> {
+        let mut usage = HashMap::new();
+        for borrow in self.holders(tool).await? {
+            *usage.entry(borrow.user).or_insert(0) += 1;
+        }
+        Ok(usage)
+    }
+    
+    /// Count the overage seats of a tool held by each user, for chargeback
+    ///
+    /// The server reports how many seats are currently past the commit
+    /// (`LicenseStatus::overage`); those are attributed to the most recent
+    /// borrows, since every older seat would still fit within the commit.
+    /// Seats are therefore counted as overage by their current position,
+    /// not by whether they were beyond the commit when borrowed.
+    ///
+    /// Returns users with at least one overage seat, most seats first (ties
+    /// by user name).
+    ///
+    /// # Arguments
+    /// * `tool` - Name of the tool
+    ///
+    /// # Errors
+    /// Same as [`get_status`](Self::get_status) and [`holders`](Self::holders).
+    pub async fn overage_attribution(&self, tool: impl Into<String>) -> Result<Vec<(String, i32)>> {
+        let tool = tool.into();
+        let status = self.get_status(&tool).await?;
+        let mut borrows = self.holders(&tool).await?;
+        
+        // One server formats all `borrowed_at`s alike, so ISO 8601 strings
+        // sort chronologically; the ID breaks ties deterministically
+        borrows.sort_by(|a, b| (&b.borrowed_at, &b.id).cmp(&(&a.borrowed_at, &a.id)));
+        
+        let mut overage: HashMap<String, i32> = HashMap::new();
+        for borrow in borrows.into_iter().take(status.overage.max(0) as usize) {
+            *overage.entry(borrow.user).or_insert(0) += 1;
+        }
+        
+        let mut attribution: Vec<(String, i32)> = overage.into_iter().collect();
+        attribution.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(attribution)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_usage_by_user_counts_seats_of_the_tool() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/borrows"))
+            .and(query_param("tool", "cad tool"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "id": "1", "tool": "cad tool", "user": "alice", "borrowed_at": "2024-01-01T00:00:00" },
+                { "id": "2", "tool": "cad tool", "user": "alice", "borrowed_at": "2024-01-01T00:00:00" },
+                { "id": "3", "tool": "cad tool", "user": "bob", "borrowed_at": "2024-01-01T00:00:00" },
+                { "id": "4", "tool": "other", "user": "bob", "borrowed_at": "2024-01-01T00:00:00" }
+            ])))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        let usage = client.usage_by_user("cad tool").await.unwrap();
+        
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage["alice"], 2);
+        assert_eq!(usage["bob"], 1);
+    }
+    
+    #[tokio::test]
+    async fn test_list_borrowed() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/borrowed"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "id": "1", "tool": "cad_tool", "user": "alice", "borrowed_at": "2024-01-01T00:00:00" },
+                { "id": "2", "tool": "sim_tool", "user": "bob" }
+            ])))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        let borrowed = client.list_borrowed().await.unwrap();
+        
+        assert_eq!(borrowed.len(), 2);
+        assert_eq!(borrowed[0].borrowed_at, "2024-01-01T00:00:00");
+        assert_eq!(
+            borrowed[1],
+            BorrowedLicense {
+                id: "2".to_string(),
+                tool: "sim_tool".to_string(),
+                user: "bob".to_string(),
+                borrowed_at: String::new(),
+            }
+        );
+    }
+    
+    #[tokio::test]
+    async fn test_overage_attribution_charges_newest_seats() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tool": "cad_tool", "total": 5, "borrowed": 5, "available": 0,
+                "commit": 2, "max_overage": 3, "overage": 3
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/borrows"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "id": "5", "tool": "cad_tool", "user": "carol", "borrowed_at": "2024-01-01T00:05:00" },
+                { "id": "1", "tool": "cad_tool", "user": "alice", "borrowed_at": "2024-01-01T00:01:00" },
+                { "id": "2", "tool": "cad_tool", "user": "bob", "borrowed_at": "2024-01-01T00:02:00" },
+                { "id": "4", "tool": "cad_tool", "user": "bob", "borrowed_at": "2024-01-01T00:04:00" },
+                { "id": "3", "tool": "cad_tool", "user": "alice", "borrowed_at": "2024-01-01T00:03:00" }
+            ])))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        let attribution = client.overage_attribution("cad_tool").await.unwrap();
+        
+        assert_eq!(
+            attribution,
+            vec![("alice".to_string(), 1), ("bob".to_string(), 1), ("carol".to_string(), 1)]
+        );
+    }
+}
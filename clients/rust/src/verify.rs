@@ -0,0 +1,72 @@
+//! Checking credentials without borrowing
+
+use crate::{LicenseClient, Result};
+
+impl LicenseClient {
+    /// Check that the server accepts this client's credentials
+    ///
+    /// Sends a signed `POST` to `/auth/verify`, which consumes no license.
+    /// The signature covers an empty tool and user. Handy in CI to catch
+    /// clock skew or a rotated vendor secret before the first real borrow.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AuthenticationFailed` if the server rejects the signature or
+    /// API key, `UnsupportedEndpoint` if it has no verify endpoint, and
+    /// `HttpError` for other failures.
+    pub async fn verify_auth(&self) -> Result<()> {
+        let url = self.endpoints().verify_auth;
+        let body = serde_json::json!({ "tool": "", "user": "" });
+        
+        let response = self
+            .with_retries(|| async {
+                let request = self.signed(self.json_body(self.client.post(&url), &body), "", "")?;
+                self.buffer(self.send(request).await?).await
+            })
+            .await?;
+        response.error_for_endpoint(&url)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LicenseError;
+    use wiremock::matchers::{header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_verify_auth() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/auth/verify"))
+            .and(header_exists("X-Signature"))
+            .respond_with(ResponseTemplate::new(200))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/auth/verify"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(serde_json::json!({ "detail": "Invalid signature" })))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::with_credentials(server.uri(), "acme", "secret");
+        client.verify_auth().await.unwrap();
+        
+        let result = client.verify_auth().await;
+        assert!(matches!(
+            result,
+            Err(LicenseError::AuthenticationFailed { status: 401, message }) if message == "Invalid signature"
+        ));
+        
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({ "detail": "Not Found" })))
+            .mount(&server)
+            .await;
+        let client = LicenseClient::with_credentials(server.uri(), "acme", "secret");
+        assert!(matches!(client.verify_auth().await, Err(LicenseError::UnsupportedEndpoint(_))));
+    }
+}
@@ -0,0 +1,571 @@
+//! Borrow variants that wait for a seat to free up
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use tokio::time::{sleep, Instant};
+
+use crate::instrument::Subject;
+use crate::{LicenseClient, LicenseError, LicenseHandle, LicenseStatus, Result};
+
+/// Progress report passed to the callback of
+/// [`LicenseClient::borrow_with_callback`]
+///
+/// There is no queue position: polling borrows don't join the server's
+/// queue. Use [`LicenseClient::borrow_queued_notify`] to wait in line.
+#[derive(Debug, Clone)]
+pub struct WaitProgress {
+    /// Latest status of the tool being waited for
+    pub status: LicenseStatus,
+    /// Borrow attempts made so far
+    pub attempts: u32,
+    /// Time spent waiting so far
+    pub elapsed: Duration,
+}
+
+/// How long one of the waiting borrow methods took to get its seat
+///
+/// Available from [`LicenseHandle::grant_info`] on handles returned by
+/// `borrow_wait` and friends, e.g. for reporting seat acquisition times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GrantInfo {
+    /// Time from the start of the call until the seat was granted
+    pub waited: Duration,
+    /// Borrow attempts made, including the successful one
+    pub attempts: u32,
+}
+
+impl LicenseHandle {
+    /// Get wait statistics, for handles from the waiting borrow methods
+    ///
+    /// `None` for handles from non-waiting methods such as
+    /// [`borrow`](LicenseClient::borrow).
+    pub fn grant_info(&self) -> Option<&GrantInfo> {
+        self.grant.as_ref()
+    }
+}
+
+/// Attach wait statistics to a successful borrow
+fn granted(result: Result<LicenseHandle>, start: Instant, attempts: u32) -> Result<LicenseHandle> {
+    result.map(|mut handle| {
+        handle.grant = Some(GrantInfo {
+            waited: start.elapsed(),
+            attempts,
+        });
+        handle
+    })
+}
+
+impl LicenseClient {
+    /// Borrow a license, polling until one is available or `timeout` elapses
+    ///
+    /// Polls every `poll_interval` (see the builder). Each attempt uses a
+    /// fresh idempotency key, since a refused borrow is a completed request.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LicenseError::NoLicensesAvailable` if no seat was granted
+    /// before the timeout. Other errors are returned immediately.
+    pub async fn borrow_wait(
+        &self,
+        tool: impl Into<String>,
+        user: impl Into<String>,
+        timeout: Duration,
+    ) -> Result<LicenseHandle> {
+        let (tool, user) = (tool.into(), user.into());
+        let waiting = self.wait_for_seat(tool.clone(), user.clone(), timeout, None::<fn(&WaitProgress)>);
+        self.instrumented("wait", Subject::waiting(Some(&tool), &user), waiting).await
+    }
+    
+    /// Borrow a license, waiting until one frees up or `timeout` elapses
+    ///
+    /// Unlike [`borrow_wait`](Self::borrow_wait), which retries the borrow
+    /// on every poll, this borrows once and then polls the tool's status
+    /// every `poll_interval` (see the builder), only borrowing again once
+    /// the server reports a free seat. Status requests are cheap for the
+    /// server and don't show up as refused borrows.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LicenseError::NoLicensesAvailable` if no seat was granted
+    /// before the timeout. Other errors are returned immediately.
+    pub async fn borrow_blocking_until(
+        &self,
+        tool: impl Into<String>,
+        user: impl Into<String>,
+        timeout: Duration,
+    ) -> Result<LicenseHandle> {
+        let (tool, user) = (tool.into(), user.into());
+        let waiting = self.wait_for_status(tool.clone(), user.clone(), timeout);
+        self.instrumented("wait", Subject::waiting(Some(&tool), &user), waiting).await
+    }
+    
+    async fn wait_for_status(&self, tool: String, user: String, timeout: Duration) -> Result<LicenseHandle> {
+        let start = Instant::now();
+        let deadline = start + timeout;
+        let mut attempts = 0;
+        
+        loop {
+            attempts += 1;
+            match self.borrow(tool.clone(), user.clone()).await {
+                Err(LicenseError::NoLicensesAvailable(_)) => {}
+                result => return granted(result, start, attempts),
+            }
+            
+            loop {
+                if !self.sleep_until_next_poll(deadline).await {
+                    return Err(LicenseError::NoLicensesAvailable(tool));
+                }
+                if self.get_status(tool.clone()).await?.available > 0 {
+                    break;
+                }
+            }
+        }
+    }
+    
+    /// Like [`borrow_wait`](Self::borrow_wait), reporting progress on every poll
+    ///
+    /// After each refused attempt the tool's current status is fetched and
+    /// passed to `callback`, e.g. to drive a spinner or ETA in a UI. The
+    /// status request is only made for this variant, so `borrow_wait` pays
+    /// nothing for it.
+    pub async fn borrow_with_callback<F>(
+        &self,
+        tool: impl Into<String>,
+        user: impl Into<String>,
+        timeout: Duration,
+        callback: F,
+    ) -> Result<LicenseHandle>
+    where
+        F: FnMut(&WaitProgress),
+    {
+        let (tool, user) = (tool.into(), user.into());
+        let waiting = self.wait_for_seat(tool.clone(), user.clone(), timeout, Some(callback));
+        self.instrumented("wait", Subject::waiting(Some(&tool), &user), waiting).await
+    }
+    
+    async fn wait_for_seat<F>(
+        &self,
+        tool: String,
+        user: String,
+        timeout: Duration,
+        mut callback: Option<F>,
+    ) -> Result<LicenseHandle>
+    where
+        F: FnMut(&WaitProgress),
+    {
+        let start = Instant::now();
+        let deadline = start + timeout;
+        let mut attempts = 0;
+        
+        loop {
+            attempts += 1;
+            match self.borrow(tool.clone(), user.clone()).await {
+                Err(LicenseError::NoLicensesAvailable(_)) => {}
+                result => return granted(result, start, attempts),
+            }
+            
+            if let Some(callback) = callback.as_mut() {
+                let status = self.get_status(tool.clone()).await?;
+                callback(&WaitProgress {
+                    status,
+                    attempts,
+                    elapsed: start.elapsed(),
+                });
+            }
+            
+            if !self.sleep_until_next_poll(deadline).await {
+                return Err(LicenseError::NoLicensesAvailable(tool));
+            }
+        }
+    }
+    
+    /// Borrow any one of several interchangeable tools, waiting up to `timeout`
+    ///
+    /// On every poll the candidates are re-ranked by current availability,
+    /// breaking ties by how availability changed since the previous poll, and
+    /// the best-ranked tool with free seats is tried first. This adapts to
+    /// shifting contention over a long wait instead of always preferring the
+    /// first tool in the list. `handle.tool()` reports which tool was granted.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LicenseError::NoLicensesAvailable` (naming all candidates) if
+    /// none could be borrowed before the timeout.
+    pub async fn borrow_any_wait(
+        &self,
+        tools: &[&str],
+        user: impl Into<String>,
+        timeout: Duration,
+    ) -> Result<LicenseHandle> {
+        let user = user.into();
+        let waiting = self.wait_for_any(tools, user.clone(), timeout);
+        self.instrumented("wait", Subject::waiting(None, &user), waiting).await
+    }
+    
+    async fn wait_for_any(&self, tools: &[&str], user: String, timeout: Duration) -> Result<LicenseHandle> {
+        let start = Instant::now();
+        let deadline = start + timeout;
+        let mut previous: HashMap<String, i32> = HashMap::new();
+        let mut attempts = 0;
+        
+        loop {
+            let statuses = self.get_all_statuses().await?;
+            
+            // Statuses carry the server-side (prefixed) names, so rank those
+            // and map the winner back to the caller's bare name
+            let qualified: Vec<String> = tools.iter().map(|tool| self.qualified_tool(tool).into_owned()).collect();
+            let candidates: Vec<&str> = qualified.iter().map(String::as_str).collect();
+            
+            for status in rank_candidates(&candidates, &statuses, &previous) {
+                if status.available <= 0 {
+                    break;
+                }
+                let index = candidates.iter().position(|c| *c == status.tool).unwrap_or_default();
+                attempts += 1;
+                match self.borrow(tools[index], user.clone()).await {
+                    Err(LicenseError::NoLicensesAvailable(_)) => continue,
+                    result => return granted(result, start, attempts),
+                }
+            }
+            
+            previous = statuses
+                .into_iter()
+                .map(|status| (status.tool, status.available))
+                .collect();
+            
+            if !self.sleep_until_next_poll(deadline).await {
+                return Err(LicenseError::NoLicensesAvailable(tools.join(", ")));
+            }
+        }
+    }
+    
+    /// Borrow whichever candidate tool is likely to free up soonest
+    ///
+    /// Candidates are ordered by the average time this client has held
+    /// their seats (see [`average_hold_time`](Self::average_hold_time)),
+    /// shortest first; tools with no history keep their given order after
+    /// those with one. Each is tried once, and if none is immediately
+    /// available the client waits up to `timeout` on the top-ranked tool,
+    /// since its seats are expected to turn over fastest.
+    pub async fn borrow_any_shortest_hold(
+        &self,
+        tools: &[&str],
+        user: impl Into<String>,
+        timeout: Duration,
+    ) -> Result<LicenseHandle> {
+        let user = user.into();
+        let waiting = self.wait_for_shortest_hold(tools, user.clone(), timeout);
+        self.instrumented("wait", Subject::waiting(None, &user), waiting).await
+    }
+    
+    async fn wait_for_shortest_hold(&self, tools: &[&str], user: String, timeout: Duration) -> Result<LicenseHandle> {
+        let start = Instant::now();
+        let mut ranked: Vec<&str> = tools.to_vec();
+        // Stable sort: `None` (no history) sorts after every known average
+        ranked.sort_by_key(|tool| self.average_hold_time(tool).map_or((1, Duration::ZERO), |avg| (0, avg)));
+        
+        let mut attempts = 0;
+        for tool in &ranked {
+            attempts += 1;
+            match self.borrow(*tool, user.clone()).await {
+                Err(LicenseError::NoLicensesAvailable(_)) => continue,
+                result => return granted(result, start, attempts),
+            }
+        }
+        
+        let tool = match ranked.first() {
+            Some(tool) => *tool,
+            None => return Err(LicenseError::NoLicensesAvailable(String::new())),
+        };
+        let result = self
+            .wait_for_seat(tool.to_string(), user, timeout, None::<fn(&WaitProgress)>)
+            .await;
+        let waited_attempts = result
+            .as_ref()
+            .ok()
+            .and_then(LicenseHandle::grant_info)
+            .map_or(0, |grant| grant.attempts);
+        granted(result, start, attempts + waited_attempts)
+    }
+    
+    /// Wait until the license `id` (possibly held by someone else) is returned
+    ///
+    /// Polls the server's list of outstanding borrows every `poll_interval`
+    /// and returns once `id` is no longer in it. An unknown `id` counts as
+    /// released.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LicenseError::Timeout` if the license is still borrowed when
+    /// `timeout` elapses. Request errors are returned immediately.
+    pub async fn wait_for_release(&self, id: &str, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        let url = self.endpoints().borrows;
+        
+        loop {
+            let borrows = self.fetch_borrows(&url).await?;
+            if !borrows.iter().any(|borrow| borrow.id == id) {
+                return Ok(());
+            }
+            
+            if !self.sleep_until_next_poll(deadline).await {
+                return Err(LicenseError::Timeout(timeout));
+            }
+        }
+    }
+    
+    /// Sleep for one poll interval, capped at the deadline
+    ///
+    /// Returns `false` without sleeping if the deadline has already passed.
+    pub(crate) async fn sleep_until_next_poll(&self, deadline: Instant) -> bool {
+        let now = Instant::now();
+        if now >= deadline {
+            return false;
+        }
+        sleep(self.poll_interval.min(deadline - now)).await;
+        true
+    }
+}
+
+/// Order candidate tools best-first for the next borrow attempt
+///
+/// Tools are ranked by seats available, then by the change in availability
+/// since the previous poll (a tool that is freeing up beats one that is
+/// filling). Candidates missing from `statuses` are left out.
+fn rank_candidates<'a>(
+    candidates: &[&str],
+    statuses: &'a [LicenseStatus],
+    previous: &HashMap<String, i32>,
+) -> Vec<&'a LicenseStatus> {
+    let mut ranked: Vec<(&LicenseStatus, i32)> = statuses
+        .iter()
+        .filter(|status| candidates.contains(&status.tool.as_str()))
+        .map(|status| {
+            let trend = previous
+                .get(&status.tool)
+                .map(|before| status.available - before)
+                .unwrap_or(0);
+            (status, trend)
+        })
+        .collect();
+    
+    // Stable sort keeps the caller's order for otherwise equal tools
+    ranked.sort_by(|(a, a_trend), (b, b_trend)| {
+        b.available.cmp(&a.available).then(b_trend.cmp(a_trend))
+    });
+    ranked.into_iter().map(|(status, _)| status).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn status(tool: &str, available: i32) -> LicenseStatus {
+        LicenseStatus {
+            tool: tool.to_string(),
+            total: 10,
+            borrowed: 10 - available,
+            available,
+            commit: 10,
+            max_overage: 0,
+            overage: 0,
+            in_commit: true,
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_wait_reports_grant_info() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(409))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .poll_interval(Duration::from_millis(20))
+            .build();
+        let license = client.borrow_wait("cad_tool", "alice", Duration::from_secs(5)).await.unwrap();
+        
+        let grant = license.grant_info().unwrap();
+        assert_eq!(grant.attempts, 3);
+        assert!(grant.waited >= Duration::from_millis(40));
+        license.return_license().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_with_callback_reports_each_poll() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(409))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(409))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tool": "cad_tool", "total": 1, "borrowed": 1, "available": 0
+            })))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .poll_interval(Duration::from_millis(20))
+            .build();
+        let mut reports = Vec::new();
+        let license = client
+            .borrow_with_callback("cad_tool", "alice", Duration::from_secs(5), |progress| {
+                reports.push((progress.attempts, progress.status.available));
+            })
+            .await
+            .unwrap();
+        assert_eq!(reports, [(1, 0), (2, 0)]);
+        license.return_license().await.unwrap();
+        
+        // A failed status request ends the wait
+        let result = client
+            .borrow_with_callback("cad_tool", "alice", Duration::from_secs(5), |_| {})
+            .await;
+        assert!(matches!(result, Err(LicenseError::HttpError(500, _))));
+    }
+    
+    #[tokio::test]
+    async fn test_borrow_blocking_until_polls_status_between_attempts() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let cad_status = |available: i32| {
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tool": "cad_tool", "total": 1, "borrowed": 1 - available, "available": available
+            }))
+        };
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(409))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/borrow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "abc-123" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .respond_with(cad_status(0))
+            .up_to_n_times(3)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .respond_with(cad_status(1))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/licenses/return"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .poll_interval(Duration::from_millis(20))
+            .build();
+        
+        // The seat never frees up within the timeout
+        let result = client.borrow_blocking_until("cad_tool", "alice", Duration::from_millis(50)).await;
+        assert!(matches!(result, Err(LicenseError::NoLicensesAvailable(_))));
+        
+        let license = client.borrow_blocking_until("cad_tool", "alice", Duration::from_secs(5)).await.unwrap();
+        assert_eq!(license.grant_info().unwrap().attempts, 2);
+        license.return_license().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_wait_for_release() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        
+        let held = serde_json::json!([
+            { "id": "abc-123", "tool": "cad_tool", "user": "bob", "borrowed_at": "2024-01-01T00:00:00" }
+        ]);
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/borrows"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&held))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/borrows"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .poll_interval(Duration::from_millis(20))
+            .build();
+        
+        let result = client.wait_for_release("abc-123", Duration::from_millis(10)).await;
+        assert!(matches!(result, Err(LicenseError::Timeout(_))));
+        
+        client.wait_for_release("abc-123", Duration::from_secs(5)).await.unwrap();
+    }
+    
+    #[test]
+    fn test_rank_by_availability_then_trend() {
+        let statuses = vec![status("a", 1), status("b", 3), status("c", 1), status("other", 9)];
+        let previous = HashMap::from([("a".to_string(), 4), ("c".to_string(), 0)]);
+        
+        let ranked: Vec<&str> = rank_candidates(&["a", "b", "c"], &statuses, &previous)
+            .into_iter()
+            .map(|s| s.tool.as_str())
+            .collect();
+        assert_eq!(ranked, vec!["b", "c", "a"]);
+    }
+}
@@ -0,0 +1,143 @@
+//! Live status updates as an async stream
+
+use futures_util::{stream, Stream};
+use tokio::time::{Interval, MissedTickBehavior};
+
+use crate::sse::SseParser;
+use crate::{parse_json, LicenseClient, LicenseStatus, Result};
+
+/// Where a status watch currently gets its updates from
+enum Source {
+    /// Try the server's event stream first
+    Connect,
+    Events(Box<reqwest::Response>, SseParser),
+    Poll(Interval),
+}
+
+struct Watch {
+    client: LicenseClient,
+    tool: String,
+    source: Source,
+}
+
+impl Watch {
+    async fn next(&mut self) -> Result<LicenseStatus> {
+        loop {
+            match &mut self.source {
+                Source::Connect => {
+                    let url = self.client.endpoints().status_stream(&self.tool);
+                    match self.client.open_event_stream(&url).await {
+                        Ok(Some(response)) => self.source = Source::Events(Box::new(response), SseParser::default()),
+                        Ok(None) => self.source = Source::Poll(self.client.poll_ticker()),
+                        Err(e) => {
+                            self.source = Source::Poll(self.client.poll_ticker());
+                            return Err(e);
+                        }
+                    }
+                }
+                Source::Events(response, parser) => {
+                    if let Some(event) = parser.next_event() {
+                        return parse_json(event.data.as_bytes());
+                    }
+                    match response.chunk().await {
+                        Ok(Some(chunk)) => parser.push(&chunk),
+                        // The server closed the stream; reconnect after a pause
+                        Ok(None) => {
+                            tokio::time::sleep(self.client.poll_interval).await;
+                            self.source = Source::Connect;
+                        }
+                        Err(e) => {
+                            self.source = Source::Connect;
+                            return Err(e.into());
+                        }
+                    }
+                }
+                Source::Poll(interval) => {
+                    interval.tick().await;
+                    return self.client.get_status(&self.tool).await;
+                }
+            }
+        }
+    }
+}
+
+impl LicenseClient {
+    /// Stream fresh statuses of `tool` for as long as the stream is kept
+    ///
+    /// Uses the server's status event stream when it has one, and otherwise
+    /// polls [`get_status`](Self::get_status) every
+    /// [`poll_interval`](crate::LicenseClientBuilder::poll_interval), starting
+    /// right away. Failed polls yield an error and the stream carries on.
+    /// Nothing runs in the background: dropping the stream stops the
+    /// polling and closes the event stream.
+    pub fn watch_status(&self, tool: impl Into<String>) -> impl Stream<Item = Result<LicenseStatus>> {
+        let watch = Watch {
+            client: self.clone(),
+            tool: tool.into(),
+            source: Source::Connect,
+        };
+        stream::unfold(watch, |mut watch| async move {
+            let status = watch.next().await;
+            Some((status, watch))
+        })
+    }
+    
+    fn poll_ticker(&self) -> Interval {
+        let mut interval = tokio::time::interval(self.poll_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        interval
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+    use std::time::Duration;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    #[tokio::test]
+    async fn test_watch_status_reads_events() {
+        let server = MockServer::start().await;
+        let body = concat!(
+            "data: {\"tool\": \"cad_tool\", \"total\": 5, \"borrowed\": 1, \"available\": 4}\n\n",
+            ": keep-alive\n\n",
+            "data: {\"tool\": \"cad_tool\", \"total\": 5, \"borrowed\": 2, \"available\": 3}\n\n",
+        );
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status/stream"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri()).enable_security(false).build();
+        let statuses: Vec<_> = client.watch_status("cad_tool").take(2).collect().await;
+        let available: Vec<_> = statuses.into_iter().map(|status| status.unwrap().available).collect();
+        assert_eq!(available, [4, 3]);
+    }
+    
+    #[tokio::test]
+    async fn test_watch_status_polls_without_event_stream() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/licenses/cad_tool/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tool": "cad_tool", "total": 5, "borrowed": 2, "available": 3
+            })))
+            .expect(3)
+            .mount(&server)
+            .await;
+        
+        let client = LicenseClient::builder(server.uri())
+            .enable_security(false)
+            .poll_interval(Duration::from_millis(10))
+            .build();
+        let mut statuses = Box::pin(client.watch_status("cad_tool"));
+        for _ in 0..3 {
+            assert_eq!(statuses.next().await.unwrap().unwrap().available, 3);
+        }
+        drop(statuses);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
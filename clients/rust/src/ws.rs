@@ -0,0 +1,287 @@
+//! Borrow and return over one WebSocket connection (`ws` feature)
+//!
+//! Every request is a JSON text frame mirroring the HTTP request it stands
+//! for, tagged with an ID chosen by the client:
+//!
+//! ```text
+//! {"id": 7, "op": "borrow", "headers": {"X-Signature": "..."}, "body": {"tool": "cad_tool", "user": "alice"}}
+//! {"id": 8, "op": "return", "headers": {...}, "body": {"id": "abc-123"}}
+//! ```
+//!
+//! `headers` are the ones the HTTP request would carry (signature,
+//! idempotency key) and `body` is its JSON body. The server answers each
+//! with a text frame carrying the same ID, the HTTP status the request
+//! would have got and the response body:
+//!
+//! ```text
+//! {"id": 7, "status": 200, "body": {"id": "abc-123", "expires_at": "..."}}
+//! ```
+//!
+//! Replies may arrive in any order, so any number of requests can be in
+//! flight on the one connection.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use futures_util::stream::SplitSink;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::{AuthMode, Buffered, LicenseClient, LicenseError, LicenseHandle, PendingBorrow, ReturnOptions, Result};
+
+type Sink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+// `None` once the connection is gone
+type Waiting = Mutex<Option<HashMap<u64, oneshot::Sender<Reply>>>>;
+
+#[derive(Serialize)]
+struct Frame<'a> {
+    id: u64,
+    op: &'a str,
+    headers: HashMap<String, String>,
+    body: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct Reply {
+    id: u64,
+    status: u16,
+    #[serde(default)]
+    body: serde_json::Value,
+}
+
+/// A WebSocket connection borrowing and returning on behalf of a client
+///
+/// Created by [`LicenseClient::connect_ws`]. Handles borrowed through a
+/// session behave like any other [`LicenseHandle`], except that they are
+/// returned over the session as well. Clones share the connection, which
+/// closes once the last clone and the last of its handles are dropped.
+#[derive(Clone)]
+pub struct WsSession {
+    inner: Arc<Inner>,
+}
+
+impl std::fmt::Debug for WsSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WsSession")
+            .field("client", &self.inner.client)
+            .finish_non_exhaustive()
+    }
+}
+
+struct Inner {
+    client: LicenseClient,
+    sink: tokio::sync::Mutex<Sink>,
+    waiting: Arc<Waiting>,
+    next_id: AtomicU64,
+    reader: tokio::task::JoinHandle<()>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+impl LicenseClient {
+    /// Open a WebSocket session for low-latency borrows and returns
+    ///
+    /// Connects to `/ws` below the base URL (`http` becomes `ws`, `https`
+    /// becomes `wss`). Requests are signed exactly as over HTTP; in bearer
+    /// mode the token is also sent with the upgrade request. The HTTP path
+    /// stays available, so each call site can pick its transport.
+    ///
+    /// # Errors
+    ///
+    /// Returns `WebSocket` if the connection or upgrade fails.
+    pub async fn connect_ws(&self) -> Result<WsSession> {
+        let url = self.endpoints().ws;
+        let url = match url.split_once("://") {
+            Some(("https", rest)) => format!("wss://{}", rest),
+            Some(("http", rest)) => format!("ws://{}", rest),
+            _ => url,
+        };
+        let mut request = url.into_client_request().map_err(ws_error)?;
+        if let AuthMode::Bearer(token) = &self.auth {
+            let value = HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|e| LicenseError::Configuration(e.to_string()))?;
+            request.headers_mut().insert("Authorization", value);
+        }
+        
+        let (stream, _) = tokio_tungstenite::connect_async(request).await.map_err(ws_error)?;
+        let (sink, mut frames) = stream.split();
+        let waiting: Arc<Waiting> = Arc::new(Mutex::new(Some(HashMap::new())));
+        
+        let reader = tokio::spawn({
+            let waiting = waiting.clone();
+            async move {
+                while let Some(Ok(frame)) = frames.next().await {
+                    let Message::Text(text) = frame else { continue };
+                    let reply: Reply = match serde_json::from_str(&text) {
+                        Ok(reply) => reply,
+                        Err(e) => {
+                            eprintln!("Warning: ignoring malformed WebSocket reply: {}", e);
+                            continue;
+                        }
+                    };
+                    let sender = waiting.lock().unwrap().as_mut().and_then(|map| map.remove(&reply.id));
+                    if let Some(sender) = sender {
+                        let _ = sender.send(reply);
+                    }
+                }
+                // Dropping the senders fails every request still waiting
+                waiting.lock().unwrap().take();
+            }
+        });
+        
+        Ok(WsSession {
+            inner: Arc::new(Inner {
+                client: self.clone(),
+                sink: tokio::sync::Mutex::new(sink),
+                waiting,
+                next_id: AtomicU64::new(1),
+                reader,
+            }),
+        })
+    }
+}
+
+impl WsSession {
+    /// Borrow a license over the session
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`LicenseClient::borrow`], or `WebSocket`
+    /// if the connection fails.
+    pub async fn borrow(&self, tool: impl Into<String>, user: impl Into<String>) -> Result<LicenseHandle> {
+        let client = &self.inner.client;
+        let pending = PendingBorrow::new(tool, user, LicenseClient::generate_idempotency_key());
+        pending.validate()?;
+        let qualified = client.qualified_tool(&pending.tool);
+        
+        let mut headers = client.security_headers(&qualified, &pending.user, client.timestamp()?)?.headers;
+        headers.push(("Idempotency-Key".to_string(), pending.idempotency_key.clone()));
+        let body = serde_json::json!({ "tool": qualified, "user": pending.user });
+        let reply = self.request("borrow", headers, body).await?;
+        
+        let mut handle = client.handle_from_borrow_body(reply.status, &reply.body, pending)?;
+        client.register_handle(&mut handle, None);
+        handle.ws = Some(self.clone());
+        Ok(handle)
+    }
+    
+    /// Send a return for license `id`, as the session's handles do
+    pub(crate) async fn send_return(&self, id: &str, options: &ReturnOptions) -> Result<Buffered> {
+        let headers = self.inner.client.return_headers(id)?.headers;
+        self.request("return", headers, options.body(id)).await
+    }
+    
+    async fn request(&self, op: &str, headers: Vec<(String, String)>, body: serde_json::Value) -> Result<Buffered> {
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, reply) = oneshot::channel();
+        match self.inner.waiting.lock().unwrap().as_mut() {
+            Some(waiting) => waiting.insert(id, sender),
+            None => return Err(LicenseError::WebSocket("connection closed".to_string())),
+        };
+        
+        let frame = Frame {
+            id,
+            op,
+            headers: headers.into_iter().collect(),
+            body,
+        };
+        let text = serde_json::to_string(&frame).map_err(|e| LicenseError::InvalidInput(e.to_string()))?;
+        let sent = self.inner.sink.lock().await.send(Message::Text(text)).await;
+        if let Err(e) = sent {
+            if let Some(waiting) = self.inner.waiting.lock().unwrap().as_mut() {
+                waiting.remove(&id);
+            }
+            return Err(ws_error(e));
+        }
+        
+        let reply = reply
+            .await
+            .map_err(|_| LicenseError::WebSocket("connection closed before the reply".to_string()))?;
+        let status = reqwest::StatusCode::from_u16(reply.status)
+            .map_err(|_| LicenseError::InvalidResponse(format!("invalid status {}", reply.status)))?;
+        let body = match reply.body {
+            serde_json::Value::Null => Vec::new(),
+            body => body.to_string().into_bytes(),
+        };
+        Ok(Buffered { status, body, date: None })
+    }
+}
+
+fn ws_error(error: tokio_tungstenite::tungstenite::Error) -> LicenseError {
+    LicenseError::WebSocket(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    
+    /// Accept one connection and answer its frames, replying to the first
+    /// two only once both have arrived (in reverse order)
+    async fn mock_server() -> (String, tokio::task::JoinHandle<Vec<serde_json::Value>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let mut frames = Vec::new();
+            while let Some(Ok(Message::Text(text))) = ws.next().await {
+                let frame: serde_json::Value = serde_json::from_str(&text).unwrap();
+                frames.push(frame);
+                if frames.len() < 2 {
+                    continue;
+                }
+                let answer: Vec<_> = if frames.len() == 2 { vec![1, 0] } else { vec![frames.len() - 1] };
+                for index in answer {
+                    let frame = &frames[index];
+                    let body = match frame["op"].as_str() {
+                        Some("borrow") => serde_json::json!({ "id": format!("ws-{}", frame["body"]["tool"].as_str().unwrap()) }),
+                        _ => serde_json::Value::Null,
+                    };
+                    let reply = serde_json::json!({ "id": frame["id"], "status": 200, "body": body });
+                    ws.send(Message::Text(reply.to_string())).await.unwrap();
+                }
+            }
+            frames
+        });
+        (url, server)
+    }
+    
+    #[tokio::test]
+    async fn test_borrows_are_multiplexed_and_returned_over_the_session() {
+        let (url, server) = mock_server().await;
+        let client = LicenseClient::with_credentials(url, "acme", "secret");
+        let session = client.connect_ws().await.unwrap();
+        
+        let (cad, sim) = tokio::join!(session.borrow("cad_tool", "alice"), session.borrow("sim_tool", "bob"));
+        let (cad, sim) = (cad.unwrap(), sim.unwrap());
+        assert_eq!((cad.id(), cad.tool()), ("ws-cad_tool", "cad_tool"));
+        assert_eq!((sim.id(), sim.user()), ("ws-sim_tool", "bob"));
+        
+        cad.return_license().await.unwrap();
+        drop(sim);
+        client.drain().await;
+        drop(session);
+        
+        let frames = server.await.unwrap();
+        assert_eq!(frames.len(), 4);
+        assert!(frames[0]["headers"]["X-Signature"].is_string());
+        assert!(frames[0]["headers"]["Idempotency-Key"].is_string());
+        let returned: Vec<_> = frames[2..].iter().map(|frame| (frame["op"].clone(), frame["body"]["id"].clone())).collect();
+        assert_eq!(returned, [
+            (serde_json::json!("return"), serde_json::json!("ws-cad_tool")),
+            (serde_json::json!("return"), serde_json::json!("ws-sim_tool")),
+        ]);
+    }
+}
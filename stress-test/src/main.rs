@@ -1,15 +1,18 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use colored::*;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use rand::Rng;
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-use tokio::sync::Semaphore;
-use tokio::time::sleep;
-use hmac::{Hmac, Mac};
-use sha2::Sha256;
+use license_client::{LicenseClient, LicenseHandle, LicenseStatus, Tool};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, RwLock, Semaphore};
+use tokio::task::JoinSet;
+use tokio::time::{sleep, MissedTickBehavior};
+use hdrhistogram::Histogram;
+use sha2::{Digest, Sha256};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "License Server Stress Testing Tool", long_about = None)]
@@ -34,6 +37,11 @@ struct Args {
     #[arg(short = 'H', long, default_value = "1")]
     hold_time: u64,
 
+    /// Sample each hold time from a distribution instead of using
+    /// --hold-time: constant:S, uniform:MIN-MAX or exp:MEAN (seconds)
+    #[arg(long, value_parser = HoldDist::parse)]
+    hold_dist: Option<HoldDist>,
+
     /// Test mode: checkout-only, return-all, or full-cycle
     #[arg(short, long, default_value = "full-cycle")]
     mode: String,
@@ -41,37 +49,272 @@ struct Args {
     /// Ramp-up time in seconds (gradually increase load)
     #[arg(short, long, default_value = "0")]
     ramp_up: u64,
+
+    /// Borrow and return for this many seconds before measuring, so
+    /// connections are already open (warm-up results are discarded)
+    #[arg(long, default_value = "0")]
+    warmup: u64,
+
+    /// Dispatch borrows at this many per second instead of a fixed number
+    /// of operations per worker (requires --duration)
+    #[arg(long, requires = "duration", value_parser = parse_rate)]
+    rate: Option<f64>,
+
+    /// How long to sustain --rate, in seconds
+    #[arg(long, requires = "rate")]
+    duration: Option<u64>,
+
+    /// Derive each operation's user by hashing (worker, op, key), so reruns
+    /// with the same key reproduce the same user assignment
+    #[arg(long)]
+    user_hash_key: Option<String>,
+
+    /// Rotate each worker's operations through this many distinct users
+    /// (stress-worker-N-0, stress-worker-N-1, ...) to exercise per-user limits
+    #[arg(long, default_value = "1", conflicts_with = "user_hash_key")]
+    users_per_worker: usize,
+
+    /// Break the results down per user as well
+    #[arg(long)]
+    per_user_stats: bool,
+
+    /// Number of distinct users to hash into (defaults to the worker count)
+    #[arg(long)]
+    user_pool: Option<usize>,
+
+    /// How often to sample tool statuses for the overage timeline, in milliseconds
+    #[arg(long, default_value = "500")]
+    sample_interval_ms: u64,
+
+    /// Flag tools whose slowest borrow took longer than this, in milliseconds
+    #[arg(long, default_value = "5000")]
+    max_wait_ms: u64,
+
+    /// Result format: colored human summary, or json/csv for CI
+    #[arg(short, long, value_enum, default_value = "human")]
+    output: OutputFormat,
+
+    /// Write the json/csv results to this file instead of stdout
+    /// (the human summary is then still printed)
+    #[arg(long)]
+    output_file: Option<std::path::PathBuf>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct BorrowRequest {
-    tool: String,
-    user: String,
+impl Args {
+    /// The hold time distribution, with --hold-time as the constant case
+    fn hold(&self) -> HoldDist {
+        self.hold_dist.unwrap_or(HoldDist::Constant(self.hold_time as f64))
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct BorrowResponse {
-    id: String,
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Human,
+    Json,
+    Csv,
+}
+
+/// How long each operation holds its license before returning it
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum HoldDist {
+    Constant(f64),
+    Uniform { min: f64, max: f64 },
+    /// Exponential with the given mean, so most holds are short and a few long
+    Exp { mean: f64 },
+}
+
+impl HoldDist {
+    fn parse(value: &str) -> Result<Self, String> {
+        let seconds = |text: &str| match text.trim().parse::<f64>() {
+            Ok(secs) if secs.is_finite() && secs >= 0.0 => Ok(secs),
+            _ => Err(format!("invalid number of seconds: {}", text)),
+        };
+        let (kind, params) = value
+            .split_once(':')
+            .ok_or_else(|| format!("expected KIND:PARAMS (e.g. uniform:1-5), got {}", value))?;
+        match kind {
+            "constant" => Ok(HoldDist::Constant(seconds(params)?)),
+            "uniform" => {
+                let (min, max) = params
+                    .split_once('-')
+                    .ok_or_else(|| format!("expected uniform:MIN-MAX, got {}", value))?;
+                let (min, max) = (seconds(min)?, seconds(max)?);
+                if min > max {
+                    return Err(format!("uniform minimum {} is above the maximum {}", min, max));
+                }
+                Ok(HoldDist::Uniform { min, max })
+            }
+            "exp" => Ok(HoldDist::Exp { mean: seconds(params)? }),
+            _ => Err(format!("unknown distribution {} (expected constant, uniform or exp)", kind)),
+        }
+    }
+
+    fn sample(&self) -> Duration {
+        let mut rng = rand::thread_rng();
+        let secs = match *self {
+            HoldDist::Constant(secs) => secs,
+            HoldDist::Uniform { min, max } => rng.gen_range(min..=max),
+            // Inverse transform; 1 - u is in (0, 1], so the log is finite
+            HoldDist::Exp { mean } => -mean * (1.0 - rng.gen::<f64>()).ln(),
+        };
+        Duration::from_secs_f64(secs)
+    }
+}
+
+impl std::fmt::Display for HoldDist {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HoldDist::Constant(secs) => write!(f, "constant:{}", secs),
+            HoldDist::Uniform { min, max } => write!(f, "uniform:{}-{}", min, max),
+            HoldDist::Exp { mean } => write!(f, "exp:{}", mean),
+        }
+    }
+}
+
+/// Settings shared by all workers
+struct WorkerConfig {
+    client: LicenseClient,
+    workers: usize,
     tool: String,
-    user: String,
-    borrowed_at: String,
+    hold: HoldDist,
+    mode: String,
+    operations: usize,
+    users_per_worker: usize,
+    user_hash: Option<UserHash>,
+    /// Licenses being held, by ID, until their return (or, in checkout-only
+    /// mode, the end of the run)
+    outstanding: Mutex<HashMap<String, LicenseHandle>>,
+    /// Read-locked by every return in progress, so an interrupt can wait for them
+    returning: Arc<RwLock<()>>,
+}
+
+impl WorkerConfig {
+    fn pick_tool(&self) -> &str {
+        if self.tool == "random" {
+            get_random_tool()
+        } else {
+            self.tool.as_str()
+        }
+    }
+
+    /// The user operation `op_index` of worker `worker_id` borrows as
+    fn user(&self, worker_id: usize, op_index: usize) -> String {
+        match &self.user_hash {
+            Some(user_hash) => user_hash.user(worker_id, op_index),
+            None if self.users_per_worker == 1 => format!("stress-worker-{}", worker_id),
+            None => format!("stress-worker-{}-{}", worker_id, op_index % self.users_per_worker),
+        }
+    }
+}
+
+/// Deterministic mapping of operations onto a fixed pool of users
+struct UserHash {
+    key: String,
+    pool: usize,
+}
+
+impl UserHash {
+    fn user(&self, worker_id: usize, op_index: usize) -> String {
+        let digest = Sha256::digest(format!("{}|{}|{}", worker_id, op_index, self.key));
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        format!("stress-user-{}", u64::from_be_bytes(bytes) % self.pool as u64)
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct StatusResponse {
+/// A tool crossing into (overage > 0) or out of (overage == 0) overage
+#[derive(Debug, Clone, Serialize)]
+struct OverageTransition {
+    elapsed_secs: f64,
     tool: String,
-    total: i32,
-    borrowed: i32,
-    available: i32,
+    overage: i32,
 }
 
-#[derive(Debug, Clone)]
+/// Poll statuses every `interval` and record overage transitions until aborted
+async fn sample_overage(
+    client: LicenseClient,
+    interval: Duration,
+    start: Instant,
+    timeline: Arc<Mutex<Vec<OverageTransition>>>,
+) {
+    let mut in_overage: HashMap<String, bool> = HashMap::new();
+    loop {
+        // Sampling failures are ignored; the next sample catches up
+        if let Ok(statuses) = client.get_all_statuses().await {
+            let transitions = overage_transitions(&mut in_overage, statuses, start.elapsed());
+            timeline.lock().unwrap().extend(transitions);
+        }
+        sleep(interval).await;
+    }
+}
+
+/// Transitions in `statuses` relative to the previous sample, which
+/// `in_overage` tracks per tool
+fn overage_transitions(
+    in_overage: &mut HashMap<String, bool>,
+    statuses: Vec<LicenseStatus>,
+    elapsed: Duration,
+) -> Vec<OverageTransition> {
+    let mut transitions = Vec::new();
+    for status in statuses {
+        let now_in_overage = status.overage > 0;
+        let was_in_overage = in_overage.insert(status.tool.clone(), now_in_overage).unwrap_or(false);
+        if now_in_overage != was_in_overage {
+            transitions.push(OverageTransition {
+                elapsed_secs: elapsed.as_secs_f64(),
+                tool: status.tool,
+                overage: status.overage,
+            });
+        }
+    }
+    transitions
+}
+
+#[derive(Debug, Clone, Serialize)]
 struct TestStats {
     successful_borrows: usize,
     failed_borrows: usize,
     successful_returns: usize,
     failed_returns: usize,
+    #[serde(skip)]
     total_duration: Duration,
+    /// Request latencies in microseconds, failed requests included
+    #[serde(skip)]
+    borrow_latency: Histogram<u64>,
+    #[serde(skip)]
+    return_latency: Histogram<u64>,
+    #[serde(skip)]
+    per_tool: BTreeMap<String, BorrowStats>,
+    #[serde(skip)]
+    per_user: BTreeMap<String, BorrowStats>,
+}
+
+/// Borrow outcomes and waits for one tool or user
+#[derive(Debug, Clone, Default)]
+struct BorrowStats {
+    successful_borrows: usize,
+    failed_borrows: usize,
+    total_wait: Duration,
+    max_wait: Duration,
+}
+
+impl BorrowStats {
+    fn record(&mut self, borrowed: bool, wait: Duration) {
+        if borrowed {
+            self.successful_borrows += 1;
+        } else {
+            self.failed_borrows += 1;
+        }
+        self.total_wait += wait;
+        self.max_wait = self.max_wait.max(wait);
+    }
+
+    fn merge(&mut self, other: &BorrowStats) {
+        self.successful_borrows += other.successful_borrows;
+        self.failed_borrows += other.failed_borrows;
+        self.total_wait += other.total_wait;
+        self.max_wait = self.max_wait.max(other.max_wait);
+    }
 }
 
 impl TestStats {
@@ -82,186 +325,409 @@ impl TestStats {
             successful_returns: 0,
             failed_returns: 0,
             total_duration: Duration::from_secs(0),
+            borrow_latency: latency_histogram(),
+            return_latency: latency_histogram(),
+            per_tool: BTreeMap::new(),
+            per_user: BTreeMap::new(),
+        }
+    }
+
+    fn merge(&mut self, other: &TestStats) {
+        self.successful_borrows += other.successful_borrows;
+        self.failed_borrows += other.failed_borrows;
+        self.successful_returns += other.successful_returns;
+        self.failed_returns += other.failed_returns;
+        // All histograms share the same bounds, so adding can't run out of range
+        self.borrow_latency.add(&other.borrow_latency).expect("histograms merge");
+        self.return_latency.add(&other.return_latency).expect("histograms merge");
+        for (tool, stats) in &other.per_tool {
+            self.per_tool.entry(tool.clone()).or_default().merge(stats);
+        }
+        for (user, stats) in &other.per_user {
+            self.per_user.entry(user.clone()).or_default().merge(stats);
         }
     }
 }
 
-async fn borrow_license(
-    client: &Client,
-    base_url: &str,
-    tool: &str,
-    user: &str,
-) -> Result<BorrowResponse, String> {
-    let url = format!("{}/licenses/borrow", base_url);
-    let req = BorrowRequest {
-        tool: tool.to_string(),
-        user: user.to_string(),
-    };
+/// Histogram of microsecond latencies up to a minute, with 3 significant digits
+///
+/// Slower requests are recorded as one minute; the client times out well before.
+fn latency_histogram() -> Histogram<u64> {
+    Histogram::new_with_bounds(1, 60_000_000, 3).expect("valid histogram bounds")
+}
 
-    // Security headers: Authorization + HMAC signature
-    let api_key = std::env::var("LICENSE_API_KEY").ok();
-    let vendor_id = "techvendor";
-    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs().to_string();
+fn record_latency(histogram: &mut Histogram<u64>, latency: Duration) {
+    histogram.saturating_record(latency.as_micros() as u64);
+}
 
-    // Build signature payload: tool|user|timestamp|api_key (if provided)
-    let payload = match &api_key {
-        Some(k) => format!("{}|{}|{}|{}", tool, user, &timestamp, k),
-        None => format!("{}|{}|{}", tool, user, &timestamp),
-    };
-    // Vendor secret must match server demo secret
-    let vendor_secret = "techvendor_secret_ecu_2025_demo_xyz789abc123def456";
-    type HmacSha256 = Hmac<Sha256>;
-    let mut mac = HmacSha256::new_from_slice(vendor_secret.as_bytes()).map_err(|e| e.to_string())?;
-    mac.update(payload.as_bytes());
-    let signature = hex::encode(mac.finalize().into_bytes());
-
-    let mut req_builder = client.post(&url).json(&req)
-        .header("X-Signature", signature)
-        .header("X-Timestamp", timestamp)
-        .header("X-Vendor-ID", vendor_id);
-    if let Some(k) = api_key { req_builder = req_builder.header("Authorization", format!("Bearer {}", k)); }
-
-    let response = req_builder
-        .send()
-        .await
-        .map_err(|e| format!("Request failed: {}", e))?;
-
-    if response.status().is_success() {
-        response
-            .json::<BorrowResponse>()
-            .await
-            .map_err(|e| format!("Parse failed: {}", e))
-    } else {
-        Err(format!("HTTP {}: {}", response.status(), response.text().await.unwrap_or_default()))
+/// Latency percentiles of one operation, in milliseconds
+#[derive(Debug, Serialize)]
+struct LatencySummary {
+    count: u64,
+    p50_ms: f64,
+    p90_ms: f64,
+    p99_ms: f64,
+    max_ms: f64,
+}
+
+impl LatencySummary {
+    fn new(histogram: &Histogram<u64>) -> Self {
+        let ms = |micros: u64| micros as f64 / 1000.0;
+        Self {
+            count: histogram.len(),
+            p50_ms: ms(histogram.value_at_quantile(0.50)),
+            p90_ms: ms(histogram.value_at_quantile(0.90)),
+            p99_ms: ms(histogram.value_at_quantile(0.99)),
+            max_ms: ms(histogram.max()),
+        }
     }
+
+    fn human(&self) -> String {
+        format!(
+            "p50 {:.2}ms  p90 {:.2}ms  p99 {:.2}ms  max {:.2}ms",
+            self.p50_ms, self.p90_ms, self.p99_ms, self.max_ms
+        )
+    }
+}
+
+/// Tools whose success rate is below this fraction of the mean are flagged as starved
+const STARVED_SUCCESS_FRACTION: f64 = 0.5;
+
+/// Borrow counts, success rate and waits of one breakdown row
+#[derive(Debug, Serialize)]
+struct BorrowSummary {
+    successful_borrows: usize,
+    failed_borrows: usize,
+    success_rate: f64,
+    mean_wait_ms: f64,
+    max_wait_ms: f64,
 }
 
-async fn return_license(
-    client: &Client,
-    base_url: &str,
-    borrow_id: &str,
-) -> Result<(), String> {
-    let url = format!("{}/licenses/return", base_url);
-    
-    #[derive(Serialize)]
-    struct ReturnRequest {
-        id: String,
+impl BorrowSummary {
+    fn new(stats: &BorrowStats) -> Self {
+        let ms = |wait: Duration| wait.as_secs_f64() * 1000.0;
+        let borrows = (stats.successful_borrows + stats.failed_borrows).max(1) as f64;
+        Self {
+            successful_borrows: stats.successful_borrows,
+            failed_borrows: stats.failed_borrows,
+            success_rate: stats.successful_borrows as f64 / borrows * 100.0,
+            mean_wait_ms: ms(stats.total_wait) / borrows,
+            max_wait_ms: ms(stats.max_wait),
+        }
     }
-    
-    let req = ReturnRequest {
-        id: borrow_id.to_string(),
-    };
+}
 
-    // Add Authorization header if API key present (return does not require signature)
-    let api_key = std::env::var("LICENSE_API_KEY").ok();
-    let mut req_builder = client.post(&url).json(&req);
-    if let Some(k) = api_key { req_builder = req_builder.header("Authorization", format!("Bearer {}", k)); }
+/// Per-tool breakdown row, with the reasons the tool looks starved (if any)
+#[derive(Debug, Serialize)]
+struct ToolReport {
+    tool: String,
+    #[serde(flatten)]
+    borrows: BorrowSummary,
+    flags: Vec<&'static str>,
+}
 
-    let response = req_builder.send().await.map_err(|e| format!("Request failed: {}", e))?;
+impl ToolReport {
+    fn breakdown(per_tool: &BTreeMap<String, BorrowStats>, max_wait: Duration) -> Vec<Self> {
+        let mut tools: Vec<_> = per_tool
+            .iter()
+            .map(|(tool, stats)| Self {
+                tool: tool.clone(),
+                borrows: BorrowSummary::new(stats),
+                flags: Vec::new(),
+            })
+            .collect();
 
-    if response.status().is_success() {
-        Ok(())
-    } else {
-        Err(format!("HTTP {}: {}", response.status(), response.text().await.unwrap_or_default()))
+        // Each tool counts once, however often it was picked
+        let mean_success_rate =
+            tools.iter().map(|tool| tool.borrows.success_rate).sum::<f64>() / tools.len().max(1) as f64;
+        for tool in &mut tools {
+            if tool.borrows.success_rate < mean_success_rate * STARVED_SUCCESS_FRACTION {
+                tool.flags.push("low_success_rate");
+            }
+            if tool.borrows.max_wait_ms > max_wait.as_secs_f64() * 1000.0 {
+                tool.flags.push("long_wait");
+            }
+        }
+        tools
     }
 }
 
-async fn get_status(client: &Client, base_url: &str) -> Result<Vec<StatusResponse>, String> {
-    let url = format!("{}/licenses/status", base_url);
+/// Per-user breakdown row (`--per-user-stats`)
+#[derive(Debug, Serialize)]
+struct UserReport {
+    user: String,
+    #[serde(flatten)]
+    borrows: BorrowSummary,
+}
 
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| format!("Request failed: {}", e))?;
+/// Everything a run produced, in the shape written by `--output json`
+///
+/// Field order is fixed so reports of different runs diff cleanly.
+#[derive(Debug, Serialize)]
+struct Report {
+    url: String,
+    workers: usize,
+    operations: usize,
+    tool: String,
+    mode: String,
+    hold_time_secs: u64,
+    hold_dist: String,
+    warmup_secs: u64,
+    users_per_worker: usize,
+    #[serde(flatten)]
+    stats: TestStats,
+    total_time_secs: f64,
+    throughput_ops_per_sec: f64,
+    borrow_success_rate: f64,
+    return_success_rate: f64,
+    /// Dispatch rate asked for with `--rate`, if any
+    target_rate_ops_per_sec: Option<f64>,
+    /// Dispatch rate reached in `--rate` mode
+    achieved_rate_ops_per_sec: Option<f64>,
+    borrow_latency: LatencySummary,
+    return_latency: LatencySummary,
+    tools: Vec<ToolReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    users: Option<Vec<UserReport>>,
+    overage_timeline: Vec<OverageTransition>,
+}
 
-    if response.status().is_success() {
-        response
-            .json::<Vec<StatusResponse>>()
-            .await
-            .map_err(|e| format!("Parse failed: {}", e))
+impl Report {
+    fn new(
+        args: &Args,
+        stats: TestStats,
+        total_time: Duration,
+        achieved_rate: Option<f64>,
+        overage_timeline: Vec<OverageTransition>,
+    ) -> Self {
+        let rate = |successful: usize, failed: usize| {
+            if successful + failed > 0 {
+                (successful as f64 / (successful + failed) as f64) * 100.0
+            } else {
+                0.0
+            }
+        };
+        Self {
+            url: args.url.clone(),
+            workers: args.workers,
+            operations: args.operations,
+            tool: args.tool.clone(),
+            mode: args.mode.clone(),
+            hold_time_secs: args.hold_time,
+            hold_dist: args.hold().to_string(),
+            warmup_secs: args.warmup,
+            users_per_worker: args.users_per_worker.max(1),
+            throughput_ops_per_sec: (stats.successful_borrows + stats.successful_returns) as f64
+                / total_time.as_secs_f64(),
+            borrow_success_rate: rate(stats.successful_borrows, stats.failed_borrows),
+            return_success_rate: rate(stats.successful_returns, stats.failed_returns),
+            target_rate_ops_per_sec: args.rate,
+            achieved_rate_ops_per_sec: achieved_rate,
+            total_time_secs: total_time.as_secs_f64(),
+            borrow_latency: LatencySummary::new(&stats.borrow_latency),
+            return_latency: LatencySummary::new(&stats.return_latency),
+            tools: ToolReport::breakdown(&stats.per_tool, Duration::from_millis(args.max_wait_ms)),
+            users: args.per_user_stats.then(|| {
+                stats
+                    .per_user
+                    .iter()
+                    .map(|(user, stats)| UserReport {
+                        user: user.clone(),
+                        borrows: BorrowSummary::new(stats),
+                    })
+                    .collect()
+            }),
+            stats,
+            overage_timeline,
+        }
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("report serializes") + "\n"
+    }
+
+    /// One header row and one value row; the per-tool and per-user breakdowns
+    /// and the overage timeline are left out
+    fn to_csv(&self) -> String {
+        let latency = |summary: &LatencySummary| {
+            [summary.p50_ms, summary.p90_ms, summary.p99_ms, summary.max_ms].map(|value| format!("{:.3}", value))
+        };
+        let [borrow_p50, borrow_p90, borrow_p99, borrow_max] = latency(&self.borrow_latency);
+        let [return_p50, return_p90, return_p99, return_max] = latency(&self.return_latency);
+        let optional = |value: Option<f64>| value.map(|value| format!("{:.3}", value)).unwrap_or_default();
+        let starved_tools = self.tools.iter().filter(|tool| !tool.flags.is_empty()).count();
+        let columns: [(&str, String); 29] = [
+            ("url", csv_field(&self.url)),
+            ("workers", self.workers.to_string()),
+            ("operations", self.operations.to_string()),
+            ("tool", csv_field(&self.tool)),
+            ("mode", csv_field(&self.mode)),
+            ("hold_time_secs", self.hold_time_secs.to_string()),
+            ("hold_dist", csv_field(&self.hold_dist)),
+            ("warmup_secs", self.warmup_secs.to_string()),
+            ("users_per_worker", self.users_per_worker.to_string()),
+            ("successful_borrows", self.stats.successful_borrows.to_string()),
+            ("failed_borrows", self.stats.failed_borrows.to_string()),
+            ("successful_returns", self.stats.successful_returns.to_string()),
+            ("failed_returns", self.stats.failed_returns.to_string()),
+            ("total_time_secs", format!("{:.3}", self.total_time_secs)),
+            ("throughput_ops_per_sec", format!("{:.3}", self.throughput_ops_per_sec)),
+            ("borrow_success_rate", format!("{:.2}", self.borrow_success_rate)),
+            ("return_success_rate", format!("{:.2}", self.return_success_rate)),
+            ("target_rate_ops_per_sec", optional(self.target_rate_ops_per_sec)),
+            ("achieved_rate_ops_per_sec", optional(self.achieved_rate_ops_per_sec)),
+            ("borrow_p50_ms", borrow_p50),
+            ("borrow_p90_ms", borrow_p90),
+            ("borrow_p99_ms", borrow_p99),
+            ("borrow_max_ms", borrow_max),
+            ("return_p50_ms", return_p50),
+            ("return_p90_ms", return_p90),
+            ("return_p99_ms", return_p99),
+            ("return_max_ms", return_max),
+            ("starved_tools", starved_tools.to_string()),
+            ("overage_transitions", self.overage_timeline.len().to_string()),
+        ];
+        let header: Vec<_> = columns.iter().map(|(name, _)| *name).collect();
+        let values: Vec<_> = columns.iter().map(|(_, value)| value.as_str()).collect();
+        format!("{}\n{}\n", header.join(","), values.join(","))
+    }
+}
+
+/// Quote a CSV value if it contains a separator, quote or newline
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
     } else {
-        Err(format!("HTTP {}", response.status()))
+        value.to_string()
     }
 }
 
 fn get_random_tool() -> &'static str {
-    let tools = [
-        "ECU Development Suite",
-        "GreenHills Multi IDE",
-        "AUTOSAR Configuration Tool",
-        "CAN Bus Analyzer Pro",
-        "Model-Based Design Studio",
-    ];
     let mut rng = rand::thread_rng();
-    tools[rng.gen_range(0..tools.len())]
+    Tool::ALL[rng.gen_range(0..Tool::ALL.len())].as_str()
+}
+
+fn parse_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        _ => Err(format!("expected a positive number of operations per second, got {}", value)),
+    }
+}
+
+/// What one borrow (and, in full-cycle mode, its return) did
+struct Operation {
+    tool: String,
+    user: String,
+    borrow: Result<(), String>,
+    borrow_latency: Duration,
+    /// Outcome and latency of the return, if one was attempted
+    returned: Option<(Result<(), String>, Duration)>,
+}
+
+impl Operation {
+    /// Progress bar message for the last step of the operation
+    fn message(&self) -> String {
+        match (&self.borrow, &self.returned) {
+            (Err(e), _) => format!("Borrow ✗ {}", e),
+            (Ok(()), None) => format!("Borrow ✓ {}", self.tool),
+            (Ok(()), Some((Ok(()), _))) => format!("Return ✓ {}", self.tool),
+            (Ok(()), Some((Err(e), _))) => format!("Return ✗ {}", e),
+        }
+    }
+}
+
+impl TestStats {
+    fn record(&mut self, operation: &Operation) {
+        let borrowed = operation.borrow.is_ok();
+        if borrowed {
+            self.successful_borrows += 1;
+        } else {
+            self.failed_borrows += 1;
+        }
+        for (breakdown, key) in [(&mut self.per_tool, &operation.tool), (&mut self.per_user, &operation.user)] {
+            breakdown.entry(key.clone()).or_default().record(borrowed, operation.borrow_latency);
+        }
+        record_latency(&mut self.borrow_latency, operation.borrow_latency);
+        if let Some((result, latency)) = &operation.returned {
+            match result {
+                Ok(()) => self.successful_returns += 1,
+                Err(_) => self.failed_returns += 1,
+            }
+            record_latency(&mut self.return_latency, *latency);
+        }
+    }
+}
+
+/// Borrow once as `worker_id`, then hold and return in full-cycle mode
+async fn run_operation(config: &WorkerConfig, worker_id: usize, op_index: usize) -> Operation {
+    let client = &config.client;
+
+    let selected_tool = config.pick_tool();
+
+    let user = config.user(worker_id, op_index);
+
+    // Borrow phase
+    let borrow_started = Instant::now();
+    let borrowed = client.borrow(selected_tool, user.clone()).await;
+    let mut operation = Operation {
+        tool: selected_tool.to_string(),
+        user,
+        borrow: borrowed.as_ref().map(|_| ()).map_err(ToString::to_string),
+        borrow_latency: borrow_started.elapsed(),
+        returned: None,
+    };
+
+    if let Ok(handle) = borrowed {
+        // Hold the license where an interrupt can find and return it
+        let id = handle.id().to_string();
+        config.outstanding.lock().unwrap().insert(id.clone(), handle);
+        if config.mode == "full-cycle" {
+            sleep(config.hold.sample()).await;
+            let handle = config.outstanding.lock().unwrap().remove(&id).expect("held until returned");
+
+            // Return phase
+            let return_started = Instant::now();
+            let returned = return_detached(config, handle).await;
+            operation.returned = Some((returned, return_started.elapsed()));
+        }
+    }
+    operation
+}
+
+/// Return `handle` on a task of its own, so cancelling the caller can't cut it off
+///
+/// The client doesn't return dropped handles, so a return abandoned halfway
+/// would leave the license borrowed.
+async fn return_detached(config: &WorkerConfig, handle: LicenseHandle) -> Result<(), String> {
+    // Only fails once an interrupt is waiting for returns, which is too late for this one
+    let in_flight = Arc::clone(&config.returning).try_read_owned().ok();
+    tokio::spawn(async move {
+        let _in_flight = in_flight;
+        handle.return_license().await.map_err(|e| e.to_string())
+    })
+    .await
+    .expect("Return panicked")
 }
 
 async fn run_worker(
     worker_id: usize,
-    client: Arc<Client>,
-    base_url: Arc<String>,
-    tool: Arc<String>,
-    hold_time: u64,
-    mode: Arc<String>,
-    operations: usize,
+    config: Arc<WorkerConfig>,
     semaphore: Arc<Semaphore>,
     progress: ProgressBar,
 ) -> TestStats {
     let mut stats = TestStats::new();
     let start = Instant::now();
+    let operations = config.operations;
 
     for i in 0..operations {
         let _permit = semaphore.acquire().await.unwrap();
 
-        let selected_tool = if tool.as_str() == "random" {
-            get_random_tool()
-        } else {
-            tool.as_str()
-        };
-
-        let user = format!("stress-worker-{}", worker_id);
-
-        // Borrow phase
-        match borrow_license(&client, &base_url, selected_tool, &user).await {
-            Ok(borrow_response) => {
-                stats.successful_borrows += 1;
-                progress.set_message(format!(
-                    "Worker {} | Borrow ✓ {} | Op {}/{}",
-                    worker_id, selected_tool, i + 1, operations
-                ));
-
-                if mode.as_str() == "full-cycle" {
-                    // Hold the license
-                    sleep(Duration::from_secs(hold_time)).await;
-
-                    // Return phase
-                    match return_license(&client, &base_url, &borrow_response.id).await {
-                        Ok(_) => {
-                            stats.successful_returns += 1;
-                            progress.set_message(format!(
-                                "Worker {} | Return ✓ {} | Op {}/{}",
-                                worker_id, selected_tool, i + 1, operations
-                            ));
-                        }
-                        Err(e) => {
-                            stats.failed_returns += 1;
-                            progress.set_message(format!(
-                                "Worker {} | Return ✗ {} | Op {}/{}",
-                                worker_id, e, i + 1, operations
-                            ));
-                        }
-                    }
-                }
-            }
-            Err(e) => {
-                stats.failed_borrows += 1;
-                progress.set_message(format!(
-                    "Worker {} | Borrow ✗ {} | Op {}/{}",
-                    worker_id, e, i + 1, operations
-                ));
-            }
-        }
+        let operation = run_operation(&config, worker_id, i).await;
+        stats.record(&operation);
+        progress.set_message(format!(
+            "Worker {} | {} | Op {}/{}",
+            worker_id, operation.message(), i + 1, operations
+        ));
 
         progress.inc(1);
 
@@ -274,58 +740,237 @@ async fn run_worker(
     stats
 }
 
+/// Dispatch operations at `rate` per second for `duration`, however long each takes
+///
+/// Each tick of the timer starts one operation on its own task, so slow
+/// responses don't lower the offered load (an open-loop test). Returns the
+/// stats and the rate actually achieved while dispatching.
+async fn run_sustained(
+    config: Arc<WorkerConfig>,
+    rate: f64,
+    duration: Duration,
+    progress: ProgressBar,
+    mut stop: watch::Receiver<bool>,
+) -> Option<(TestStats, f64)> {
+    let mut stats = TestStats::new();
+    let mut tasks = JoinSet::new();
+    let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / rate));
+    // Ticks missed while the runtime was busy are caught up at once
+    interval.set_missed_tick_behavior(MissedTickBehavior::Burst);
+
+    let start = Instant::now();
+    let mut dispatched = 0usize;
+    let run = async {
+        loop {
+            interval.tick().await;
+            if start.elapsed() >= duration {
+                break;
+            }
+            let config = Arc::clone(&config);
+            // Spread operations over as many identities as there are workers
+            let worker_id = dispatched % config.workers.max(1);
+            let op_index = dispatched / config.workers.max(1);
+            tasks.spawn(async move { run_operation(&config, worker_id, op_index).await });
+            dispatched += 1;
+
+            while let Some(finished) = tasks.try_join_next() {
+                let operation = finished.expect("Operation panicked");
+                stats.record(&operation);
+                progress.inc(1);
+                progress.set_message(operation.message());
+            }
+        }
+        let achieved_rate = dispatched as f64 / start.elapsed().as_secs_f64();
+
+        while let Some(finished) = tasks.join_next().await {
+            let operation = finished.expect("Operation panicked");
+            stats.record(&operation);
+            progress.inc(1);
+            progress.set_message(operation.message());
+        }
+        achieved_rate
+    };
+    let achieved_rate = tokio::select! {
+        achieved_rate = run => achieved_rate,
+        _ = interrupted(&mut stop) => {
+            tasks.shutdown().await;
+            progress.abandon_with_message("Interrupted");
+            return None;
+        }
+    };
+    stats.total_duration = start.elapsed();
+    progress.finish_with_message(format!("{} operations dispatched", dispatched));
+    Some((stats, achieved_rate))
+}
+
+/// Start `args.workers` workers (spread over the ramp-up) and merge their stats
+///
+/// Returns `None` if stopped first, once every worker has been cancelled.
+async fn run_workers(
+    args: &Args,
+    config: Arc<WorkerConfig>,
+    multi_progress: &MultiProgress,
+    style: &ProgressStyle,
+    mut stop: watch::Receiver<bool>,
+) -> Option<TestStats> {
+    let semaphore = Arc::new(Semaphore::new(args.workers));
+    let mut workers = JoinSet::new();
+    let mut all_stats = TestStats::new();
+    let run = async {
+        for worker_id in 0..args.workers {
+            let config = Arc::clone(&config);
+            let semaphore = Arc::clone(&semaphore);
+
+            let progress = multi_progress.add(ProgressBar::new(args.operations as u64));
+            progress.set_style(style.clone());
+
+            // Ramp-up delay
+            if args.ramp_up > 0 {
+                let delay = (args.ramp_up * 1000) / args.workers as u64;
+                sleep(Duration::from_millis(delay * worker_id as u64)).await;
+            }
+
+            workers.spawn(run_worker(worker_id, config, semaphore, progress));
+        }
+
+        // Wait for all workers
+        while let Some(finished) = workers.join_next().await {
+            all_stats.merge(&finished.expect("Worker panicked"));
+        }
+    };
+    tokio::select! {
+        _ = run => Some(all_stats),
+        _ = interrupted(&mut stop) => {
+            workers.shutdown().await;
+            None
+        }
+    }
+}
+
+/// Borrow and immediately return on every worker until `duration` is up
+///
+/// Opens the connections the measured run will reuse. Nothing is recorded and
+/// licenses are always returned, whatever the mode. Returns the number of
+/// operations, or `None` if stopped first.
+async fn warm_up(config: Arc<WorkerConfig>, duration: Duration, mut stop: watch::Receiver<bool>) -> Option<usize> {
+    let deadline = Instant::now() + duration;
+    let mut tasks = JoinSet::new();
+    for worker_id in 0..config.workers {
+        let config = Arc::clone(&config);
+        tasks.spawn(async move {
+            let mut operations = 0;
+            while Instant::now() < deadline {
+                let user = format!("stress-warmup-{}", worker_id);
+                if let Ok(handle) = config.client.borrow(config.pick_tool(), user).await {
+                    let _ = return_detached(&config, handle).await;
+                }
+                operations += 1;
+                sleep(Duration::from_millis(10)).await;
+            }
+            operations
+        });
+    }
+
+    let run = async {
+        let mut operations = 0;
+        while let Some(finished) = tasks.join_next().await {
+            operations += finished.expect("Warm-up panicked");
+        }
+        operations
+    };
+    tokio::select! {
+        operations = run => Some(operations),
+        _ = interrupted(&mut stop) => {
+            tasks.shutdown().await;
+            None
+        }
+    }
+}
+
+/// Resolves once `stop` is set
+async fn interrupted(stop: &mut watch::Receiver<bool>) {
+    if stop.wait_for(|&stopped| stopped).await.is_err() {
+        // Nobody can stop the run any more
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Return the licenses an interrupted run was still holding
+async fn reclaim_outstanding(config: &WorkerConfig) {
+    let handles: Vec<_> = config.outstanding.lock().unwrap().drain().map(|(_, handle)| handle).collect();
+    let held = handles.len();
+    let mut returns = JoinSet::new();
+    for handle in handles {
+        returns.spawn(handle.return_license());
+    }
+    let mut reclaimed = 0;
+    while let Some(finished) = returns.join_next().await {
+        match finished.expect("Return panicked") {
+            Ok(()) => reclaimed += 1,
+            Err(e) => eprintln!("  {} {}", "Return failed:".red(), e),
+        }
+    }
+    // Returns that were in flight when their worker was cancelled
+    let _ = config.returning.write().await;
+
+    eprintln!();
+    eprintln!(
+        "{} returned {} of {} outstanding licenses",
+        "Interrupted:".yellow().bold(),
+        reclaimed.to_string().green(),
+        held
+    );
+}
+
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
+    // Machine-readable results on stdout replace the human summary
+    let human = args.output == OutputFormat::Human || args.output_file.is_some();
 
-    println!("{}", "╔══════════════════════════════════════════════════════════╗".cyan().bold());
-    println!("{}", "║   License Server Stress Test                             ║".cyan().bold());
-    println!("{}", "╚══════════════════════════════════════════════════════════╝".cyan().bold());
-    println!();
-
-    println!("{}", "Configuration:".yellow().bold());
-    println!("  Server:      {}", args.url.green());
-    println!("  Workers:     {}", args.workers.to_string().green());
-    println!("  Operations:  {} per worker", args.operations.to_string().green());
-    println!("  Total Ops:   {}", (args.workers * args.operations).to_string().green().bold());
-    println!("  Tool:        {}", args.tool.green());
-    println!("  Hold Time:   {}s", args.hold_time.to_string().green());
-    println!("  Mode:        {}", args.mode.green());
-    println!("  Ramp-up:     {}s", args.ramp_up.to_string().green());
-    println!();
+    if human {
+        print_configuration(&args);
+    }
 
-    let client = Arc::new(
-        Client::builder()
-            .timeout(Duration::from_secs(30))
-            .build()
-            .expect("Failed to create HTTP client"),
-    );
+    // Signs borrows like any other client, with LICENSE_API_KEY as the bearer token.
+    // Every handle is returned explicitly, and checkout-only mode keeps its
+    // licenses borrowed when the handles are dropped at the end of the run.
+    let client = LicenseClient::builder(args.url.clone())
+        .api_key(std::env::var("LICENSE_API_KEY").ok())
+        .timeout(Duration::from_secs(30))
+        .auto_return_on_drop(false)
+        .build();
 
     // Check server status
-    print!("{}", "Checking server status... ".cyan());
-    match get_status(&client, &args.url).await {
+    if human {
+        print!("{}", "Checking server status... ".cyan());
+    }
+    match client.get_all_statuses().await {
         Ok(statuses) => {
-            println!("{}", "OK".green().bold());
-            for status in statuses {
-                println!(
-                    "   {} → {} total, {} borrowed, {} available",
-                    status.tool.yellow(),
-                    status.total,
-                    status.borrowed.to_string().red(),
-                    status.available.to_string().green()
-                );
+            if human {
+                println!("{}", "OK".green().bold());
+                for status in statuses {
+                    println!(
+                        "   {} → {} total, {} borrowed, {} available",
+                        status.tool.yellow(),
+                        status.total,
+                        status.borrowed.to_string().red(),
+                        status.available.to_string().green()
+                    );
+                }
             }
         }
         Err(e) => {
-            println!("{}", "FAIL".red().bold());
+            if human {
+                println!("{}", "FAIL".red().bold());
+            }
             eprintln!("{} {}", "Error:".red().bold(), e);
             std::process::exit(1);
         }
     }
-    println!();
-
-    println!("{}", "Starting stress test...".cyan().bold());
-    println!();
+    if human {
+        println!();
+    }
 
     let multi_progress = MultiProgress::new();
     let style = ProgressStyle::default_bar()
@@ -333,60 +978,170 @@ async fn main() {
         .unwrap()
         .progress_chars("█▓▒░ ");
 
-    let base_url = Arc::new(args.url.clone());
-    let tool = Arc::new(args.tool.clone());
-    let mode = Arc::new(args.mode.clone());
-    let semaphore = Arc::new(Semaphore::new(args.workers));
+    let config = Arc::new(WorkerConfig {
+        client: client.clone(),
+        workers: args.workers,
+        tool: args.tool.clone(),
+        hold: args.hold(),
+        mode: args.mode.clone(),
+        operations: args.operations,
+        users_per_worker: args.users_per_worker.max(1),
+        user_hash: args.user_hash_key.clone().map(|key| UserHash {
+            key,
+            pool: args.user_pool.unwrap_or(args.workers).max(1),
+        }),
+        outstanding: Mutex::new(HashMap::new()),
+        returning: Arc::new(RwLock::new(())),
+    });
 
-    let start_time = Instant::now();
+    // Ctrl-C stops the run and returns whatever is still held; a second one exits at once
+    let (stop_tx, stop) = watch::channel(false);
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            let _ = stop_tx.send(true);
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        }
+    });
 
-    let mut handles = vec![];
+    if args.warmup > 0 {
+        if human {
+            print!("{}", format!("Warming up for {}s... ", args.warmup).cyan());
+            let _ = std::io::stdout().flush();
+        }
+        let Some(operations) = warm_up(Arc::clone(&config), Duration::from_secs(args.warmup), stop.clone()).await else {
+            reclaim_outstanding(&config).await;
+            std::process::exit(130);
+        };
+        if human {
+            println!("{} ({} operations, not measured)", "done".green().bold(), operations);
+            println!();
+        }
+    }
 
-    for worker_id in 0..args.workers {
-        let client = Arc::clone(&client);
-        let base_url = Arc::clone(&base_url);
-        let tool = Arc::clone(&tool);
-        let mode = Arc::clone(&mode);
-        let semaphore = Arc::clone(&semaphore);
+    if human {
+        println!("{}", "Starting stress test...".cyan().bold());
+        println!();
+    }
+    let start_time = Instant::now();
 
-        let progress = multi_progress.add(ProgressBar::new(args.operations as u64));
-        progress.set_style(style.clone());
+    let timeline = Arc::new(Mutex::new(Vec::new()));
+    let sampler = tokio::spawn(sample_overage(
+        client.clone(),
+        Duration::from_millis(args.sample_interval_ms.max(1)),
+        start_time,
+        Arc::clone(&timeline),
+    ));
 
-        // Ramp-up delay
-        if args.ramp_up > 0 {
-            let delay = (args.ramp_up * 1000) / args.workers as u64;
-            sleep(Duration::from_millis(delay * worker_id as u64)).await;
-        }
-
-        let handle = tokio::spawn(async move {
-            run_worker(
-                worker_id,
-                client,
-                base_url,
-                tool,
-                args.hold_time,
-                mode,
-                args.operations,
-                semaphore,
-                progress,
-            )
+    let outcome = if let (Some(rate), Some(duration)) = (args.rate, args.duration) {
+        let expected = (rate * duration as f64).ceil() as u64;
+        let progress = multi_progress.add(ProgressBar::new(expected));
+        progress.set_style(style.clone());
+        run_sustained(Arc::clone(&config), rate, Duration::from_secs(duration), progress, stop)
             .await
-        });
+            .map(|(stats, achieved)| (stats, Some(achieved)))
+    } else {
+        run_workers(&args, Arc::clone(&config), &multi_progress, &style, stop)
+            .await
+            .map(|stats| (stats, None))
+    };
+    let Some((all_stats, achieved_rate)) = outcome else {
+        sampler.abort();
+        reclaim_outstanding(&config).await;
+        std::process::exit(130);
+    };
+
+    let total_time = start_time.elapsed();
+    sampler.abort();
 
-        handles.push(handle);
+    let timeline = std::mem::take(&mut *timeline.lock().unwrap());
+    let report = Report::new(&args, all_stats, total_time, achieved_rate, timeline);
+
+    let machine_output = match args.output {
+        OutputFormat::Human => None,
+        OutputFormat::Json => Some(report.to_json()),
+        OutputFormat::Csv => Some(report.to_csv()),
+    };
+    match (machine_output, &args.output_file) {
+        (Some(output), Some(path)) => {
+            if let Err(e) = std::fs::write(path, output) {
+                eprintln!("{} failed to write {}: {}", "Error:".red().bold(), path.display(), e);
+                std::process::exit(1);
+            }
+        }
+        (Some(output), None) => print!("{}", output),
+        (None, _) => {}
     }
 
-    // Wait for all workers
-    let mut all_stats = TestStats::new();
-    for handle in handles {
-        let stats = handle.await.expect("Worker panicked");
-        all_stats.successful_borrows += stats.successful_borrows;
-        all_stats.failed_borrows += stats.failed_borrows;
-        all_stats.successful_returns += stats.successful_returns;
-        all_stats.failed_returns += stats.failed_returns;
+    if human {
+        print_results(&report, &client).await;
     }
+}
 
-    let total_time = start_time.elapsed();
+fn print_configuration(args: &Args) {
+    println!("{}", "╔══════════════════════════════════════════════════════════╗".cyan().bold());
+    println!("{}", "║   License Server Stress Test                             ║".cyan().bold());
+    println!("{}", "╚══════════════════════════════════════════════════════════╝".cyan().bold());
+    println!();
+
+    println!("{}", "Configuration:".yellow().bold());
+    println!("  Server:      {}", args.url.green());
+    println!("  Workers:     {}", args.workers.to_string().green());
+    if let (Some(rate), Some(duration)) = (args.rate, args.duration) {
+        println!("  Rate:        {} ops/sec", rate.to_string().green());
+        println!("  Duration:    {}s", duration.to_string().green());
+    } else {
+        println!("  Operations:  {} per worker", args.operations.to_string().green());
+        println!("  Total Ops:   {}", (args.workers * args.operations).to_string().green().bold());
+    }
+    println!("  Tool:        {}", args.tool.green());
+    match args.hold_dist {
+        Some(dist) => println!("  Hold Time:   {}", dist.to_string().green()),
+        None => println!("  Hold Time:   {}s", args.hold_time.to_string().green()),
+    }
+    println!("  Mode:        {}", args.mode.green());
+    println!("  Ramp-up:     {}s", args.ramp_up.to_string().green());
+    if args.warmup > 0 {
+        println!("  Warm-up:     {}s (not measured)", args.warmup.to_string().green());
+    }
+    if args.users_per_worker > 1 {
+        println!("  Users:       {} per worker", args.users_per_worker.to_string().green());
+    }
+    if let Some(key) = &args.user_hash_key {
+        let pool = args.user_pool.unwrap_or(args.workers);
+        println!("  Users:       {} (hashed with key {})", pool.to_string().green(), key.green());
+    }
+    println!();
+}
+
+/// Print breakdown rows as a table, highlighting flagged ones
+fn print_breakdown(label: &str, rows: &[(&str, &BorrowSummary, &[&str])]) {
+    let width = rows.iter().map(|(name, _, _)| name.len()).max().unwrap_or(0).max(label.len());
+    println!(
+        "  {:<width$}  {:>7}  {:>6}  {:>8}  {:>10}  {:>10}",
+        label, "Borrows", "Failed", "Success", "Mean Wait", "Max Wait"
+    );
+    for (name, borrows, flags) in rows {
+        let row = format!(
+            "  {:<width$}  {:>7}  {:>6}  {:>7.2}%  {:>8.2}ms  {:>8.2}ms",
+            name,
+            borrows.successful_borrows + borrows.failed_borrows,
+            borrows.failed_borrows,
+            borrows.success_rate,
+            borrows.mean_wait_ms,
+            borrows.max_wait_ms
+        );
+        if flags.is_empty() {
+            println!("{}", row);
+        } else {
+            println!("{}  {} {}", row.red(), "⚠".red().bold(), flags.join(", ").red());
+        }
+    }
+}
+
+async fn print_results(report: &Report, client: &LicenseClient) {
+    let stats = &report.stats;
 
     println!();
     println!("{}", "╔══════════════════════════════════════════════════════════╗".cyan().bold());
@@ -395,40 +1150,65 @@ async fn main() {
     println!();
 
     println!("{}", "Performance:".yellow().bold());
-    println!("  Total Time:         {:.2}s", total_time.as_secs_f64());
-    println!(
-        "  Throughput:         {:.2} ops/sec",
-        (all_stats.successful_borrows + all_stats.successful_returns) as f64 / total_time.as_secs_f64()
-    );
+    println!("  Total Time:         {:.2}s", report.total_time_secs);
+    println!("  Throughput:         {:.2} ops/sec", report.throughput_ops_per_sec);
+    if let (Some(target), Some(achieved)) = (report.target_rate_ops_per_sec, report.achieved_rate_ops_per_sec) {
+        println!("  Dispatch Rate:      {:.2} ops/sec (target {:.2})", achieved, target);
+    }
+    println!();
+
+    println!("{}", "Latency:".yellow().bold());
+    println!("  Borrow:             {}", report.borrow_latency.human());
+    if report.mode == "full-cycle" {
+        println!("  Return:             {}", report.return_latency.human());
+    }
     println!();
 
     println!("{}", "Borrow Operations:".yellow().bold());
-    println!("  Successful:         {} {}", all_stats.successful_borrows, "✓".green());
-    println!("  Failed:             {} {}", all_stats.failed_borrows, if all_stats.failed_borrows > 0 { "✗".red() } else { "✓".green() });
-    let borrow_success_rate = if all_stats.successful_borrows + all_stats.failed_borrows > 0 {
-        (all_stats.successful_borrows as f64 / (all_stats.successful_borrows + all_stats.failed_borrows) as f64) * 100.0
-    } else {
-        0.0
-    };
-    println!("  Success Rate:       {:.2}%", borrow_success_rate);
+    println!("  Successful:         {} {}", stats.successful_borrows, "✓".green());
+    println!("  Failed:             {} {}", stats.failed_borrows, if stats.failed_borrows > 0 { "✗".red() } else { "✓".green() });
+    println!("  Success Rate:       {:.2}%", report.borrow_success_rate);
     println!();
 
-    if args.mode == "full-cycle" {
+    if report.mode == "full-cycle" {
         println!("{}", "Return Operations:".yellow().bold());
-        println!("  Successful:         {} {}", all_stats.successful_returns, "✓".green());
-        println!("  Failed:             {} {}", all_stats.failed_returns, if all_stats.failed_returns > 0 { "✗".red() } else { "✓".green() });
-        let return_success_rate = if all_stats.successful_returns + all_stats.failed_returns > 0 {
-            (all_stats.successful_returns as f64 / (all_stats.successful_returns + all_stats.failed_returns) as f64) * 100.0
-        } else {
-            0.0
-        };
-        println!("  Success Rate:       {:.2}%", return_success_rate);
+        println!("  Successful:         {} {}", stats.successful_returns, "✓".green());
+        println!("  Failed:             {} {}", stats.failed_returns, if stats.failed_returns > 0 { "✗".red() } else { "✓".green() });
+        println!("  Success Rate:       {:.2}%", report.return_success_rate);
+        println!();
+    }
+
+    println!("{}", "Per-Tool Breakdown:".yellow().bold());
+    let tools: Vec<_> = report.tools.iter().map(|tool| (tool.tool.as_str(), &tool.borrows, tool.flags.as_slice())).collect();
+    print_breakdown("Tool", &tools);
+    println!();
+
+    if let Some(users) = &report.users {
+        println!("{}", "Per-User Breakdown:".yellow().bold());
+        let users: Vec<_> = users.iter().map(|user| (user.user.as_str(), &user.borrows, &[][..])).collect();
+        print_breakdown("User", &users);
         println!();
     }
 
+    println!("{}", "Overage Timeline:".yellow().bold());
+    if report.overage_timeline.is_empty() {
+        println!("  No overage transitions observed");
+    }
+    for event in report.overage_timeline.iter() {
+        let label = if event.overage > 0 { "entered overage".red() } else { "left overage".green() };
+        println!(
+            "  {:>8.2}s  {} {} (overage {})",
+            event.elapsed_secs,
+            event.tool.yellow(),
+            label,
+            event.overage
+        );
+    }
+    println!();
+
     // Final server status
     println!("{}", "Final Server Status:".yellow().bold());
-    match get_status(&client, &args.url).await {
+    match client.get_all_statuses().await {
         Ok(statuses) => {
             for status in statuses {
                 println!(
@@ -446,10 +1226,195 @@ async fn main() {
     }
     println!();
 
-    if all_stats.failed_borrows == 0 && all_stats.failed_returns == 0 {
+    if stats.failed_borrows == 0 && stats.failed_returns == 0 {
         println!("{}", "All operations completed successfully.".green().bold());
     } else {
         println!("{}", "Some operations failed - check server logs".yellow().bold());
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field_quotes_only_when_needed() {
+        assert_eq!(csv_field("full-cycle"), "full-cycle");
+        assert_eq!(csv_field("CAN Bus Analyzer Pro"), "CAN Bus Analyzer Pro");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn test_report_to_csv_has_one_header_and_value_row() {
+        let args = Args::parse_from(["stress", "--tool", "ECU, rev 2", "--rate", "5", "--duration", "10"]);
+        let report = Report::new(&args, TestStats::new(), Duration::from_secs(2), Some(4.5), Vec::new());
+        let csv = report.to_csv();
+
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("url,workers,operations,tool,mode,"));
+        assert!(lines[0].ends_with(",starved_tools,overage_transitions"));
+        assert!(lines[1].starts_with("http://localhost:8000,10,100,\"ECU, rev 2\",full-cycle,"));
+        assert!(lines[1].contains(",5.000,4.500,"));
+        // Quoted commas don't add columns
+        assert_eq!(lines[0].split(',').count(), 29);
+        assert_eq!(lines[1].replace("\"ECU, rev 2\"", "tool").split(',').count(), 29);
+    }
+
+    #[test]
+    fn test_user_hash_is_deterministic_within_pool() {
+        let hash = UserHash { key: "run-1".to_string(), pool: 4 };
+        let users: Vec<_> = (0..50).map(|op| hash.user(op % 5, op)).collect();
+        let again: Vec<_> = (0..50).map(|op| hash.user(op % 5, op)).collect();
+        assert_eq!(users, again);
+        assert!(users.iter().all(|user| {
+            let index: usize = user.strip_prefix("stress-user-").unwrap().parse().unwrap();
+            index < 4
+        }));
+
+        let other = UserHash { key: "run-2".to_string(), pool: 4 };
+        let reshuffled: Vec<_> = (0..50).map(|op| other.user(op % 5, op)).collect();
+        assert_ne!(users, reshuffled);
+    }
+
+    #[test]
+    fn test_latency_summary_percentiles() {
+        let mut histogram = latency_histogram();
+        for ms in 1..=100 {
+            record_latency(&mut histogram, Duration::from_millis(ms));
+        }
+        // Beyond the histogram's range, recorded as its maximum
+        record_latency(&mut histogram, Duration::from_secs(120));
+
+        let summary = LatencySummary::new(&histogram);
+        assert_eq!(summary.count, 101);
+        assert!((summary.p50_ms - 51.0).abs() < 0.1);
+        assert!((summary.p90_ms - 91.0).abs() < 0.1);
+        assert!((summary.max_ms - 60_000.0).abs() < 60.0);
+    }
+
+    #[test]
+    fn test_parse_rate() {
+        assert_eq!(parse_rate("2.5"), Ok(2.5));
+        assert_eq!(parse_rate("100"), Ok(100.0));
+        for invalid in ["0", "-1", "inf", "NaN", "fast", ""] {
+            assert!(parse_rate(invalid).is_err(), "{} should be rejected", invalid);
+        }
+    }
+
+    #[test]
+    fn test_hold_dist_parse() {
+        assert_eq!(HoldDist::parse("constant:2"), Ok(HoldDist::Constant(2.0)));
+        assert_eq!(HoldDist::parse("uniform:1-5"), Ok(HoldDist::Uniform { min: 1.0, max: 5.0 }));
+        assert_eq!(HoldDist::parse("exp:0.5"), Ok(HoldDist::Exp { mean: 0.5 }));
+        for dist in ["constant:2", "uniform:1-5", "exp:0.5"] {
+            assert_eq!(HoldDist::parse(dist).unwrap().to_string(), dist);
+        }
+
+        for invalid in ["2", "constant:-1", "uniform:5-1", "uniform:3", "exp:soon", "normal:1"] {
+            assert!(HoldDist::parse(invalid).is_err(), "{} should be rejected", invalid);
+        }
+    }
+
+    #[test]
+    fn test_hold_dist_sample_stays_in_range() {
+        assert_eq!(HoldDist::Constant(1.5).sample(), Duration::from_millis(1500));
+
+        let uniform = HoldDist::Uniform { min: 1.0, max: 2.0 };
+        assert!((0..1000).all(|_| (1.0..=2.0).contains(&uniform.sample().as_secs_f64())));
+
+        // Exponential samples average out near the mean
+        let exp = HoldDist::Exp { mean: 1.0 };
+        let mean = (0..10_000).map(|_| exp.sample().as_secs_f64()).sum::<f64>() / 10_000.0;
+        assert!((0.9..1.1).contains(&mean), "mean {} is off", mean);
+    }
+
+    #[test]
+    fn test_breakdown_flags_starved_tools() {
+        let stats = |successful: usize, failed: usize, max_wait_ms: u64| BorrowStats {
+            successful_borrows: successful,
+            failed_borrows: failed,
+            total_wait: Duration::from_millis(max_wait_ms),
+            max_wait: Duration::from_millis(max_wait_ms),
+        };
+        let per_tool = BTreeMap::from([
+            ("busy".to_string(), stats(9, 1, 100)),
+            ("slow".to_string(), stats(10, 0, 6000)),
+            ("starved".to_string(), stats(2, 8, 7000)),
+        ]);
+
+        let tools = ToolReport::breakdown(&per_tool, Duration::from_secs(5));
+        let flags: Vec<_> = tools.iter().map(|tool| (tool.tool.as_str(), tool.flags.clone())).collect();
+        // The mean success rate is 70%, so only "starved" is below half of it
+        assert_eq!(
+            flags,
+            [
+                ("busy", vec![]),
+                ("slow", vec!["long_wait"]),
+                ("starved", vec!["low_success_rate", "long_wait"]),
+            ]
+        );
+        assert!(ToolReport::breakdown(&BTreeMap::new(), Duration::from_secs(5)).is_empty());
+    }
+
+    #[test]
+    fn test_worker_users() {
+        let config = |users_per_worker: usize, user_hash: Option<UserHash>| WorkerConfig {
+            client: LicenseClient::builder("http://localhost:8000").build(),
+            workers: 2,
+            tool: "random".to_string(),
+            hold: HoldDist::Constant(0.0),
+            mode: "full-cycle".to_string(),
+            operations: 10,
+            users_per_worker,
+            user_hash,
+            outstanding: Mutex::new(HashMap::new()),
+            returning: Arc::new(RwLock::new(())),
+        };
+
+        let single = config(1, None);
+        assert_eq!(single.user(3, 0), "stress-worker-3");
+        assert_eq!(single.user(3, 7), "stress-worker-3");
+
+        let rotating = config(3, None);
+        let users: Vec<_> = (0..4).map(|op| rotating.user(1, op)).collect();
+        assert_eq!(users, ["stress-worker-1-0", "stress-worker-1-1", "stress-worker-1-2", "stress-worker-1-0"]);
+
+        // Hashing takes precedence over the per-worker rotation
+        let hashed = config(3, Some(UserHash { key: "run-1".to_string(), pool: 4 }));
+        assert!(hashed.user(1, 5).starts_with("stress-user-"));
+    }
+
+    #[test]
+    fn test_overage_transitions() {
+        let sample = |overages: &[(&str, i32)]| -> Vec<LicenseStatus> {
+            overages
+                .iter()
+                .map(|(tool, overage)| {
+                    serde_json::from_value(serde_json::json!({
+                        "tool": tool, "total": 10, "borrowed": 5, "available": 5, "overage": overage
+                    }))
+                    .unwrap()
+                })
+                .collect()
+        };
+        let mut in_overage = HashMap::new();
+        let changes = |in_overage: &mut HashMap<String, bool>, overages: &[(&str, i32)]| -> Vec<(String, i32)> {
+            overage_transitions(in_overage, sample(overages), Duration::from_secs(1))
+                .into_iter()
+                .map(|transition| (transition.tool, transition.overage))
+                .collect()
+        };
+
+        // Tools first seen outside overage aren't transitions
+        assert_eq!(changes(&mut in_overage, &[("cad", 0), ("ide", 2)]), [("ide".to_string(), 2)]);
+        assert!(changes(&mut in_overage, &[("cad", 0), ("ide", 3)]).is_empty());
+        assert_eq!(
+            changes(&mut in_overage, &[("cad", 1), ("ide", 0)]),
+            [("cad".to_string(), 1), ("ide".to_string(), 0)]
+        );
+    }
+}